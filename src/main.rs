@@ -6,23 +6,47 @@
 #[macro_use]
 extern crate clap;
 extern crate env_logger;
+#[cfg_attr(windows, macro_use)]
 extern crate log;
 extern crate time;
+#[cfg(windows)]
+#[macro_use]
+extern crate windows_service;
+#[cfg(windows)]
+extern crate winlog;
 
 extern crate t_rex_core;
+extern crate t_rex_service;
 extern crate t_rex_webserver;
 
 use clap::{App, AppSettings, ArgMatches, SubCommand};
 use env_logger::Builder;
 use log::Record;
+use std::cmp;
 use std::env;
-use std::io::Write;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
+use std::thread;
+use t_rex_core::core::config::insert_layer_config;
 use t_rex_core::core::grid::Extent;
+use t_rex_core::core::Config;
+use t_rex_service::mvt_service::MvtService;
+use t_rex_service::seed_state::{SeedParams, SeedState};
+use t_rex_service::{import_config, mapnik_config};
+use t_rex_webserver::access_log::ACCESS_LOG_TARGET;
 use t_rex_webserver as webserver;
 
+#[cfg(windows)]
+mod winservice;
+
 fn init_logger(args: &ArgMatches) {
     let mut builder = Builder::new();
     builder.format(|buf, record: &Record| {
+        // JSON request log records contain their own timestamp
+        if record.target() == ACCESS_LOG_TARGET {
+            return writeln!(buf, "{}", record.args());
+        }
         let t = time::now();
         writeln!(
             buf,
@@ -48,13 +72,42 @@ fn init_logger(args: &ArgMatches) {
     builder.init();
 }
 
-fn generate(args: &ArgMatches) {
+fn generate(args: &ArgMatches<'static>) {
     let config = webserver::server::config_from_args(&args);
     let mut service = webserver::server::service_from_args(&config, &args);
-    config
-        .cache
-        .expect("Missing configuration entry base in [cache.file]");
     let tileset = args.value_of("tileset");
+    let threads = args.value_of("threads").map_or(1, |s| {
+        s.parse::<u32>()
+            .expect("Error parsing 'threads' as integer value")
+    });
+    match (args.value_of("mbtiles"), args.value_of("pmtiles")) {
+        (Some(_), Some(_)) => {
+            println!("Options 'mbtiles' and 'pmtiles' can't be combined");
+            process::exit(1)
+        }
+        (Some(_), None) if threads > 1 => {
+            println!("Option 'threads' is not supported for MBTiles export");
+            process::exit(1)
+        }
+        (Some(fname), None) => set_mbtiles_cache(&mut service, fname, tileset),
+        (None, Some(_))
+            if threads > 1
+                || args.value_of("resume").is_some()
+                || args.value_of("modified-since").is_some() =>
+        {
+            println!(
+                "Options 'threads', 'resume' and 'modified-since' are not supported for PMTiles export"
+            );
+            process::exit(1)
+        }
+        (None, Some(fname)) => set_pmtiles_cache(&mut service, fname, tileset),
+        (None, None) => {
+            config
+                .cache
+                .as_ref()
+                .expect("Missing configuration entry base in [cache.file]");
+        }
+    }
     let minzoom = args.value_of("minzoom").map(|s| {
         s.parse::<u8>()
             .expect("Error parsing 'minzoom' as integer value")
@@ -63,26 +116,30 @@ fn generate(args: &ArgMatches) {
         s.parse::<u8>()
             .expect("Error parsing 'maxzoom' as integer value")
     });
-    let extent = args.value_of("extent").and_then(|numlist| {
-        let arr: Vec<f64> = numlist
-            .split(",")
-            .map(|v| {
-                v.parse()
-                    .expect("Error parsing 'extent' as list of float values")
-            })
-            .collect();
-        Some(Extent {
-            minx: arr[0],
-            miny: arr[1],
-            maxx: arr[2],
-            maxy: arr[3],
+    let extent = args.value_of("extent").map(|numlist| {
+        numlist.parse::<Extent>().unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(1)
         })
     });
-    let nodes = args.value_of("nodes").map(|s| {
+    let extent_srid = args.value_of("extent-srid").map(|s| {
+        s.parse::<i32>()
+            .expect("Error parsing 'extent-srid' as integer value")
+    });
+    if let Some(srid) = extent_srid {
+        if srid != 4326 && srid != service.grid.srid {
+            println!(
+                "Option 'extent-srid' must be 4326 or the grid SRID {}",
+                service.grid.srid
+            );
+            process::exit(1)
+        }
+    }
+    let nodes = args.value_of("nodes").map_or(1, |s| {
         s.parse::<u8>()
             .expect("Error parsing 'nodes' as integer value")
     });
-    let nodeno = args.value_of("nodeno").map(|s| {
+    let nodeno = args.value_of("nodeno").map_or(0, |s| {
         s.parse::<u8>()
             .expect("Error parsing 'nodeno' as integer value")
     });
@@ -94,13 +151,189 @@ fn generate(args: &ArgMatches) {
         s.parse::<bool>()
             .expect("Error parsing 'overwrite' as boolean value")
     });
+    if let Some(since) = args.value_of("modified-since") {
+        if threads > 1 || args.value_of("resume").is_some() || extent.is_some() {
+            println!(
+                "Option 'modified-since' can't be combined with 'threads', 'resume' or 'extent'"
+            );
+            process::exit(1)
+        }
+        service.prepare_feature_queries();
+        let stats = service
+            .generate_modified(tileset, minzoom, maxzoom, since, progress)
+            .unwrap_or_else(|err| {
+                println!("Error generating modified tiles - {}", err);
+                process::exit(1)
+            });
+        println!("Statistics:\n{:?}", stats);
+        return;
+    }
+    // Each thread renders a share of the tiles with its own service,
+    // sharing the connection pools of the PostGIS datasources
+    let threads = cmp::max(threads, 1);
+    let nodes = nodes as u32 * threads;
+    let nodeno = nodeno as u32 * threads;
+    // Resuming requires the same tiles to be assigned to each generator node
+    let seed_params = SeedParams {
+        tileset: tileset.map(|name| name.to_string()),
+        minzoom,
+        maxzoom,
+        extent: extent.clone(),
+        extent_srid,
+        tileset_extents: service
+            .tilesets
+            .iter()
+            .map(|ts| (ts.name.clone(), ts.extent.clone()))
+            .collect(),
+        nodes,
+    };
+    // Progress of each generator node in its own file
+    let seed_state = |nodeno: u32| {
+        args.value_of("resume").map(|path| {
+            let path = if nodes > 1 {
+                format!("{}.{}", path, nodeno)
+            } else {
+                path.to_string()
+            };
+            SeedState::load(&path, seed_params.clone()).unwrap_or_else(|err| {
+                println!("{}", err);
+                process::exit(1)
+            })
+        })
+    };
+    if threads > 1 {
+        println!("Generating tiles with {} threads...", threads);
+    }
+    let pools = service.datasources.postgis_pools();
+    let workers: Vec<_> = (1..threads)
+        .map(|threadno| {
+            let args = args.clone();
+            let pools = pools.clone();
+            let extent = extent.clone();
+            let seed_state = seed_state(nodeno + threadno);
+            thread::spawn(move || {
+                let config = webserver::server::config_from_args(&args);
+                let mut service =
+                    webserver::server::shared_service_from_args(&config, &args, &pools);
+                service.prepare_feature_queries();
+                service.generate(
+                    args.value_of("tileset"),
+                    minzoom,
+                    maxzoom,
+                    extent,
+                    extent_srid,
+                    Some(nodes),
+                    Some(nodeno + threadno),
+                    false,
+                    overwrite,
+                    seed_state,
+                )
+            })
+        })
+        .collect();
     service.prepare_feature_queries();
-    let stats = service.generate(
-        tileset, minzoom, maxzoom, extent, nodes, nodeno, progress, overwrite,
+    let mut stats = service.generate(
+        tileset,
+        minzoom,
+        maxzoom,
+        extent,
+        extent_srid,
+        Some(nodes),
+        Some(nodeno),
+        // Tiles are assigned alternately to the threads, the progress of
+        // the main thread is representative for all threads
+        progress,
+        overwrite,
+        seed_state(nodeno),
     );
+    for worker in workers {
+        stats.merge(worker.join().expect("Error in generator thread"));
+    }
     println!("Statistics:\n{:?}", stats);
 }
 
+fn invalidate(args: &ArgMatches) {
+    let config = webserver::server::config_from_args(&args);
+    let service = webserver::server::service_from_args(&config, &args);
+    let minzoom = args.value_of("minzoom").map(|s| {
+        s.parse::<u8>()
+            .expect("Error parsing 'minzoom' as integer value")
+    });
+    let maxzoom = args.value_of("maxzoom").map(|s| {
+        s.parse::<u8>()
+            .expect("Error parsing 'maxzoom' as integer value")
+    });
+    let extent = args.value_of("extent").map(|numlist| {
+        numlist.parse::<Extent>().unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(1)
+        })
+    });
+    let tilesets: Vec<String> = match args.value_of("tileset") {
+        Some(tileset) => vec![tileset.to_string()],
+        None => service.tilesets.iter().map(|ts| ts.name.clone()).collect(),
+    };
+    for tileset in tilesets {
+        match service.invalidate(&tileset, minzoom, maxzoom, extent.as_ref()) {
+            Ok(()) => println!("Cached tiles of tileset '{}' removed", tileset),
+            Err(e) => {
+                println!("Error invalidating tileset '{}' - {}", tileset, e);
+                process::exit(1)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "with-mbtiles")]
+fn set_mbtiles_cache(service: &mut MvtService, fname: &str, tileset: Option<&str>) {
+    use t_rex_core::cache::{Mbtiles, Tilecache};
+    use t_rex_core::core::grid::TileScheme;
+    use t_rex_core::mvt::tile::TileCompression;
+
+    if tileset.is_none() && service.tilesets.len() > 1 {
+        println!("Option 'tileset' is required for MBTiles export");
+        process::exit(1)
+    }
+    // MBTiles specification: tiles in Web Mercator with rows flipped from XYZ
+    if service.grid.srid != 3857 || service.grid.tile_scheme != TileScheme::Xyz {
+        println!("MBTiles export requires a Web Mercator grid with XYZ tile scheme");
+        process::exit(1)
+    }
+    let mbtiles = Mbtiles::create(fname).unwrap_or_else(|err| {
+        println!("Error creating MBTiles file - {} ", err);
+        process::exit(1)
+    });
+    service.cache = Tilecache::Mbtiles(mbtiles);
+    // MBTiles specification: vector tiles are gzip compressed
+    service.compression = TileCompression::Gzip;
+}
+
+#[cfg(not(feature = "with-mbtiles"))]
+fn set_mbtiles_cache(_service: &mut MvtService, _fname: &str, _tileset: Option<&str>) {
+    println!("MBTiles export not supported in this build");
+    process::exit(1)
+}
+
+fn set_pmtiles_cache(service: &mut MvtService, fname: &str, tileset: Option<&str>) {
+    use t_rex_core::cache::{Pmtiles, Tilecache};
+    use t_rex_core::core::grid::TileScheme;
+
+    if tileset.is_none() && service.tilesets.len() > 1 {
+        println!("Option 'tileset' is required for PMTiles export");
+        process::exit(1)
+    }
+    // PMTiles specification: tiles in Web Mercator XYZ scheme
+    if service.grid.srid != 3857 || service.grid.tile_scheme != TileScheme::Xyz {
+        println!("PMTiles export requires a Web Mercator grid with XYZ tile scheme");
+        process::exit(1)
+    }
+    let pmtiles = Pmtiles::create(fname, service.compression).unwrap_or_else(|err| {
+        println!("Error creating PMTiles file - {} ", err);
+        process::exit(1)
+    });
+    service.cache = Tilecache::Pmtiles(pmtiles);
+}
+
 fn drilldown(args: &ArgMatches) {
     let config = webserver::server::config_from_args(&args);
     let mut service = webserver::server::service_from_args(&config, &args);
@@ -134,6 +367,155 @@ fn drilldown(args: &ArgMatches) {
     print!("{}", stats.as_csv());
 }
 
+fn generalize(args: &ArgMatches) {
+    let config = webserver::server::config_from_args(&args);
+    let service = webserver::server::service_from_args(&config, &args);
+    let cfgpath = args.value_of("config").expect("Missing 'config' argument");
+    let tileset = args.value_of("tileset");
+    let maxzooms: Vec<u8> = args
+        .value_of("zoomlevels")
+        .unwrap_or("8,11")
+        .split(",")
+        .map(|v| {
+            v.parse()
+                .expect("Error parsing 'zoomlevels' as list of integer values")
+        })
+        .collect();
+    let mappings = service.generalize(tileset, &maxzooms);
+    if mappings.is_empty() {
+        println!("No generalized tables created");
+        return;
+    }
+    let mut config_toml = String::new();
+    File::open(cfgpath)
+        .and_then(|mut f| f.read_to_string(&mut config_toml))
+        .expect("Error reading configuration");
+    for (layer_name, toml) in mappings {
+        config_toml = insert_layer_config(&config_toml, &layer_name, &toml);
+    }
+    File::create(cfgpath)
+        .and_then(|mut f| f.write_all(config_toml.as_bytes()))
+        .expect("Error writing configuration");
+    println!("Generalized tables added to '{}'", cfgpath);
+}
+
+fn genstyle(args: &ArgMatches) {
+    let config = webserver::server::config_from_args(&args);
+    let service = MvtService::from_config(&config).unwrap_or_else(|err| {
+        println!("Error reading configuration - {} ", err);
+        process::exit(1)
+    });
+    let tileset = args
+        .value_of("tileset")
+        .expect("Missing 'tileset' argument");
+    if service.get_tileset(tileset).is_none() {
+        println!("Tileset '{}' not found", tileset);
+        process::exit(1)
+    }
+    let baseurl = match args.value_of("baseurl") {
+        Some(url) => url.trim_right_matches('/').to_string(),
+        None => match config.webserver.public_url {
+            Some(ref url) => url.trim_right_matches('/').to_string(),
+            None => format!(
+                "http://{}{}",
+                config.webserver.bind_address(),
+                config.webserver.path_prefix()
+            ),
+        },
+    };
+    let style = service
+        .get_stylejson(&baseurl, tileset)
+        .expect("Error generating style");
+    let json = format!("{:#}", style);
+    match args.value_of("output") {
+        Some(path) => {
+            File::create(path)
+                .and_then(|mut f| f.write_all(json.as_bytes()))
+                .expect("Error writing style");
+            println!("Style of tileset '{}' written to '{}'", tileset, path);
+        }
+        None => println!("{}", json),
+    }
+}
+
+fn hash(args: &ArgMatches) {
+    let config = webserver::server::config_from_args(&args);
+    let mut service = webserver::server::service_from_args(&config, &args);
+    let canonical = args.value_of("canonical").map_or(false, |s| {
+        s.parse::<bool>()
+            .expect("Error parsing 'canonical' as boolean value")
+    });
+    let fname = args
+        .value_of("tile-list")
+        .expect("Missing 'tile-list' argument");
+    let mut tile_list = String::new();
+    File::open(fname)
+        .and_then(|mut f| f.read_to_string(&mut tile_list))
+        .expect("Error reading tile list");
+    service.prepare_feature_queries();
+    let mut failed = false;
+    // One tile per line: tileset/z/x/y
+    for line in tile_list.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<&str> = line.trim_right_matches(".pbf").split('/').collect();
+        let coords: Vec<u32> = parts
+            .iter()
+            .skip(1)
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        if parts.len() != 4 || coords.len() != 3 || coords[0] > 255 {
+            println!("Invalid tile '{}' (expected tileset/z/x/y)", line);
+            failed = true;
+            continue;
+        }
+        match service.tile_hash(parts[0], coords[1], coords[2], coords[0] as u8, canonical) {
+            Ok(hash) => println!("{} {}", line, hash),
+            Err(e) => {
+                println!("{} {}", line, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Write configuration to `output` file or stdout
+fn write_config(args: &ArgMatches, config: &str) {
+    match args.value_of("output") {
+        Some(path) => {
+            File::create(path)
+                .and_then(|mut f| f.write_all(config.as_bytes()))
+                .expect("Error writing configuration");
+            println!("Configuration written to '{}'", path);
+        }
+        None => println!("{}", config),
+    }
+}
+
+fn genconfig(args: &ArgMatches) {
+    let config = webserver::server::gen_config(args);
+    write_config(args, &config);
+}
+
+fn import_mapnik(args: &ArgMatches) {
+    let fname = args.value_of("FILE").expect("Missing Mapnik XML file");
+    let config = mapnik_config(fname);
+    write_config(args, &config);
+}
+
+fn import(args: &ArgMatches) {
+    let fname = args.value_of("FILE").expect("Missing configuration file");
+    let config = import_config(fname).unwrap_or_else(|err| {
+        println!("Error reading configuration - {} ", err);
+        process::exit(1)
+    });
+    write_config(args, &config);
+}
+
 #[cfg(feature = "with-gdal")]
 extern crate t_rex_gdal;
 
@@ -167,9 +549,10 @@ fn main() {
                                               --no-transform=[true|false] 'Do not transform to grid SRS'
                                               --cache=[DIR] 'Use tile cache in DIR'
                                               -c, --config=[FILE] 'Load from custom config file'
-                                              --bind=[IPADDRESS] 'Bind web server to this address (0.0.0.0 for all)'
+                                              --bind=[IPADDRESS] 'Bind web server to this address (0.0.0.0 or :: for all)'
                                               --port=[PORT] 'Bind web server to this port'
-                                              --openbrowser=[true|false] 'Open backend URL in browser'")
+                                              --openbrowser=[true|false] 'Open backend URL in browser'
+                                              --service=[install|uninstall|run] 'Windows service mode'")
                         .about("Start web server and serve MVT vector tiles"))
         .subcommand(SubCommand::with_name("genconfig")
                         .args_from_usage("--dbconn=[SPEC] 'PostGIS connection postgresql://USER@HOST/DBNAME'
@@ -178,9 +561,12 @@ fn main() {
                                               --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
                                               --simplify=[true|false] 'Simplify geometries'
                                               --clip=[true|false] 'Clip geometries'
-                                              --no-transform=[true|false] 'Do not transform to grid SRS'")
+                                              --no-transform=[true|false] 'Do not transform to grid SRS'
+                                              --detect-geometry-types=[true|false] 'Detect geometry types when undefined'
+                                              -o, --output=[FILE] 'Output file (Default: stdout)'")
                         .about("Generate configuration template"))
         .subcommand(SubCommand::with_name("generate")
+                        .visible_alias("seed")
                         .setting(AppSettings::AllowLeadingHyphen)
                         .args_from_usage("-c, --config=<FILE> 'Load from custom config file'
                                               --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
@@ -188,11 +574,26 @@ fn main() {
                                               --minzoom=[LEVEL] 'Minimum zoom level'
                                               --maxzoom=[LEVEL] 'Maximum zoom level'
                                               --extent=[minx,miny,maxx,maxy] 'Extent of tiles'
+                                              --extent-srid=[SRID] 'SRID of extent: 4326 or grid SRID (Default: 4326)'
                                               --nodes=[NUM] 'Number of generator nodes'
                                               --nodeno=[NUM] 'Number of this nodes (0 <= n < nodes)'
+                                              --threads=[NUM] 'Number of rendering threads (Default: 1)'
+                                              --resume=[FILE] 'Save progress into FILE and continue an interrupted run'
+                                              --modified-since=[TIMESTAMP] 'Regenerate tiles with features modified since TIMESTAMP (layer option modified_field, old positions of moved and deleted features are not detected)'
                                               --progress=[true|false] 'Show progress bar'
-                                              --overwrite=[false|true] 'Overwrite previously cached tiles'")
+                                              --overwrite=[false|true] 'Overwrite previously cached tiles'
+                                              --mbtiles=[FILE] 'Write tiles into MBTiles file instead of cache'
+                                              --pmtiles=[FILE] 'Write tiles into PMTiles archive instead of cache'")
                         .about("Generate tiles for cache"))
+        .subcommand(SubCommand::with_name("invalidate")
+                        .setting(AppSettings::AllowLeadingHyphen)
+                        .args_from_usage("-c, --config=<FILE> 'Load from custom config file'
+                                              --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
+                                              --tileset=[NAME] 'Tileset name (Default: all tilesets)'
+                                              --minzoom=[LEVEL] 'Minimum zoom level'
+                                              --maxzoom=[LEVEL] 'Maximum zoom level'
+                                              --extent=[minx,miny,maxx,maxy] 'Extent of tiles in WGS84'")
+                        .about("Remove tiles from cache"))
         .subcommand(SubCommand::with_name("drilldown")
                         .setting(AppSettings::AllowLeadingHyphen)
                         .args_from_usage("-c, --config=<FILE> 'Load from custom config file'
@@ -202,7 +603,36 @@ fn main() {
                                               --maxzoom=[LEVEL] 'Maximum zoom level'
                                               --points=[x1,y1,x2,y2,..] 'Drilldown points'
                                               --progress=[true|false] 'Show progress bar'")
-                        .about("Tile layer statistics"));
+                        .about("Tile layer statistics"))
+        .subcommand(SubCommand::with_name("generalize")
+                        .args_from_usage("-c, --config=<FILE> 'Load from custom config file'
+                                              --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
+                                              --tileset=[NAME] 'Tileset name'
+                                              --zoomlevels=[Z1,Z2,..] 'Maximum zoom levels of generalized tables (Default: 8,11)'")
+                        .about("Create generalized tables and add them to the configuration"))
+        .subcommand(SubCommand::with_name("genstyle")
+                        .args_from_usage("-c, --config=<FILE> 'Load from custom config file'
+                                              --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
+                                              --tileset=<NAME> 'Tileset name'
+                                              --baseurl=[URL] 'Base URL of tile server (Default: from [webserver] config)'
+                                              -o, --output=[FILE] 'Output file (Default: stdout)'")
+                        .about("Generate Mapbox GL style for a tileset"))
+        .subcommand(SubCommand::with_name("hash")
+                        .args_from_usage("-c, --config=<FILE> 'Load from custom config file'
+                                              --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
+                                              --tile-list=<FILE> 'File with one tile per line (tileset/z/x/y)'
+                                              --canonical=[false|true] 'Hash canonicalized tile encoding'")
+                        .about("Print content hashes of tiles"))
+        .subcommand(SubCommand::with_name("import-mapnik")
+                        .args_from_usage("<FILE> 'Mapnik XML stylesheet'
+                                              --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
+                                              -o, --output=[FILE] 'Output file (Default: stdout)'")
+                        .about("Generate configuration from PostGIS layers of a Mapnik stylesheet"))
+        .subcommand(SubCommand::with_name("import")
+                        .args_from_usage("<FILE> 'Tegola (.toml) or Martin (.yaml) configuration file'
+                                              --loglevel=[error|warn|info|debug|trace] 'Log level (Default: info)'
+                                              -o, --output=[FILE] 'Output file (Default: stdout)'")
+                        .about("Generate configuration from a Tegola or Martin configuration"));
 
     match app.get_matches_from_safe_borrow(env::args()) {
         //app.get_matches() prohibits later call of app.print_help()
@@ -210,22 +640,55 @@ fn main() {
             println!("{}", e);
         }
         Result::Ok(matches) => match matches.subcommand() {
-            ("serve", Some(sub_m)) => {
-                init_logger(sub_m);
-                webserver::server::webserver(sub_m.clone());
-            }
+            ("serve", Some(sub_m)) => match sub_m.value_of("service") {
+                None => {
+                    init_logger(sub_m);
+                    webserver::server::webserver(sub_m.clone());
+                }
+                #[cfg(windows)]
+                Some(mode) => winservice::service(mode, sub_m),
+                #[cfg(not(windows))]
+                Some(_) => {
+                    println!("Service mode is only available on Windows");
+                    process::exit(1)
+                }
+            },
             ("genconfig", Some(sub_m)) => {
                 init_logger(sub_m);
-                println!("{}", webserver::server::gen_config(sub_m));
+                genconfig(sub_m);
             }
             ("generate", Some(sub_m)) => {
                 init_logger(sub_m);
                 generate(sub_m);
             }
+            ("invalidate", Some(sub_m)) => {
+                init_logger(sub_m);
+                invalidate(sub_m);
+            }
             ("drilldown", Some(sub_m)) => {
                 init_logger(sub_m);
                 drilldown(sub_m);
             }
+            ("generalize", Some(sub_m)) => {
+                init_logger(sub_m);
+                generalize(sub_m);
+            }
+            ("genstyle", Some(sub_m)) => {
+                init_logger(sub_m);
+                genstyle(sub_m);
+            }
+            ("hash", Some(sub_m)) => {
+                init_logger(sub_m);
+                hash(sub_m);
+            }
+            ("import-mapnik", Some(sub_m)) => {
+                init_logger(sub_m);
+                import_mapnik(sub_m);
+            }
+            ("import", Some(sub_m)) => {
+                init_logger(sub_m);
+                import(sub_m);
+            }
             _ => {
                 let _ = app.print_help();
                 println!("");
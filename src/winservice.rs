@@ -0,0 +1,135 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Windows service mode
+//!
+//! `t_rex serve --service=install -c C:\t-rex\config.toml` registers a service
+//! started with the same arguments and `--service=run`. Log output of the
+//! running service goes to the Windows event log.
+
+use clap::ArgMatches;
+use std::env;
+use std::ffi::OsString;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use t_rex_webserver as webserver;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_dispatcher;
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use winlog;
+
+const SERVICE_NAME: &str = "t-rex";
+const SERVICE_DISPLAY_NAME: &str = "t-rex vector tile server";
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Register service with arguments of current command line
+fn install() -> Result<(), String> {
+    let executable_path = env::current_exe().map_err(|e| e.to_string())?;
+    let mut launch_arguments: Vec<OsString> = env::args_os()
+        .skip(1)
+        .filter(|arg| !arg.to_string_lossy().starts_with("--service"))
+        .collect();
+    launch_arguments.push(OsString::from("--service=run"));
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(|e| e.to_string())?;
+    let service_info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(SERVICE_DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments,
+        dependencies: vec![],
+        account_name: None, // LocalSystem
+        account_password: None,
+    };
+    manager
+        .create_service(service_info, ServiceAccess::empty())
+        .map_err(|e| e.to_string())?;
+    winlog::register(SERVICE_NAME);
+    Ok(())
+}
+
+fn uninstall() -> Result<(), String> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|e| e.to_string())?;
+    let service = manager
+        .open_service(SERVICE_NAME, ServiceAccess::DELETE)
+        .map_err(|e| e.to_string())?;
+    service.delete().map_err(|e| e.to_string())?;
+    winlog::deregister(SERVICE_NAME);
+    Ok(())
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let (shutdown_tx, shutdown_rx) = mpsc::channel();
+    let event_handler = move |control_event| -> ServiceControlHandlerResult {
+        match control_event {
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                let _ = shutdown_tx.send(());
+                ServiceControlHandlerResult::NoError
+            }
+            _ => ServiceControlHandlerResult::NotImplemented,
+        }
+    };
+    let status_handle = match service_control_handler::register(SERVICE_NAME, event_handler) {
+        Ok(handle) => handle,
+        Err(e) => {
+            error!("Service registration failed: {}", e);
+            return;
+        }
+    };
+    let status = |state: ServiceState, controls_accepted: ServiceControlAccept| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+    };
+    let _ = status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+    ));
+    let _ = shutdown_rx.recv();
+    info!("Service stopped");
+    let _ = status_handle
+        .set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty()));
+    process::exit(0);
+}
+
+/// Run web server under control of the service control manager
+fn run(args: &ArgMatches<'static>) -> Result<(), String> {
+    winlog::init(SERVICE_NAME).map_err(|e| e.to_string())?;
+    let args = args.clone();
+    thread::spawn(move || webserver::server::webserver(args));
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main).map_err(|e| e.to_string())
+}
+
+/// Handle `--service` argument of serve command
+pub fn service(mode: &str, args: &ArgMatches<'static>) {
+    let result = match mode {
+        "install" => install().map(|_| println!("Service '{}' installed", SERVICE_NAME)),
+        "uninstall" => uninstall().map(|_| println!("Service '{}' removed", SERVICE_NAME)),
+        "run" => run(args),
+        _ => Err(format!("Unknown service mode '{}'", mode)),
+    };
+    if let Err(e) = result {
+        println!("Windows service error: {}", e);
+        process::exit(1);
+    }
+}
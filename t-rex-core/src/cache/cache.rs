@@ -5,16 +5,30 @@
 
 use std::io;
 use std::io::Read;
+use std::time::SystemTime;
 
+/// Metadata of a cached tile
+#[derive(Debug, PartialEq)]
+pub struct CacheEntryMetadata {
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification time, if supported by the backend
+    pub modified: Option<SystemTime>,
+}
+
+/// Tile cache backend
+///
+/// Custom backends can be used with `Tilecache::Custom`.
 pub trait Cache {
     fn info(&self) -> String;
     /// Base URL of tile cache server published in metadata
     fn baseurl(&self) -> String;
-    fn read<F>(&self, path: &str, read: F) -> bool
-    where
-        F: FnMut(&mut Read);
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool;
     fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error>;
     fn exists(&self, path: &str) -> bool;
+    /// Remove a cached tile or all tiles below a directory path (e.g. "tileset/6")
+    fn purge(&self, path: &str) -> Result<(), io::Error>;
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata>;
 }
 
 pub struct Nocache;
@@ -27,10 +41,7 @@ impl Cache for Nocache {
         "http://localhost:6767".to_string()
     }
     #[allow(unused_variables)]
-    fn read<F>(&self, path: &str, read: F) -> bool
-    where
-        F: FnMut(&mut Read),
-    {
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
         false
     }
     #[allow(unused_variables)]
@@ -41,4 +52,10 @@ impl Cache for Nocache {
     fn exists(&self, _path: &str) -> bool {
         false
     }
+    fn purge(&self, _path: &str) -> Result<(), io::Error> {
+        Ok(())
+    }
+    fn metadata(&self, _path: &str) -> Option<CacheEntryMetadata> {
+        None
+    }
 }
@@ -3,7 +3,7 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use cache::cache::Cache;
+use cache::cache::{Cache, CacheEntryMetadata};
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::Path;
@@ -22,10 +22,7 @@ impl Cache for Filecache {
             .clone()
             .unwrap_or("http://localhost:6767".to_string())
     }
-    fn read<F>(&self, path: &str, mut read: F) -> bool
-    where
-        F: FnMut(&mut Read),
-    {
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
         let fullpath = format!("{}/{}", self.basepath, path);
         debug!("Filecache.read {}", fullpath);
         match File::open(&fullpath) {
@@ -49,4 +46,31 @@ impl Cache for Filecache {
         let fullpath = format!("{}/{}", self.basepath, path);
         Path::new(&fullpath).exists()
     }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        let fullpath = format!("{}/{}", self.basepath, path);
+        debug!("Filecache.purge {}", fullpath);
+        let p = Path::new(&fullpath);
+        if p.is_dir() {
+            fs::remove_dir_all(p)
+        } else if p.exists() {
+            fs::remove_file(p)
+        } else {
+            Ok(())
+        }
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        let fullpath = format!("{}/{}", self.basepath, path);
+        fs::metadata(&fullpath)
+            .ok()
+            .and_then(|md| {
+                if md.is_file() {
+                    Some(CacheEntryMetadata {
+                        size: md.len(),
+                        modified: md.modified().ok(),
+                    })
+                } else {
+                    None
+                }
+            })
+    }
 }
@@ -26,19 +26,36 @@ fn test_dircache() {
     let obj = "0123456789";
 
     // Cache miss
-    assert_eq!(cache.read(path, |_| {}), false);
+    assert_eq!(cache.read(path, &mut |_| {}), false);
 
     // Write into cache
     let _ = cache.write(path, obj.as_bytes());
     assert!(Path::new(&fullpath).exists());
 
     // Cache hit
-    assert_eq!(cache.read(path, |_| {}), true);
+    assert_eq!(cache.read(path, &mut |_| {}), true);
 
     // Read from cache
     let mut s = String::new();
-    cache.read(path, |f| {
+    cache.read(path, &mut |f| {
         let _ = f.read_to_string(&mut s);
     });
     assert_eq!(&s, "0123456789");
+
+    // Metadata
+    let md = cache.metadata(path).unwrap();
+    assert_eq!(md.size, 10);
+    assert!(md.modified.is_some());
+    assert_eq!(cache.metadata("tileset/0"), None);
+
+    // Purge single tile
+    let _ = cache.write("tileset/0/1/3.pbf", obj.as_bytes());
+    assert!(cache.purge(path).is_ok());
+    assert!(!cache.exists(path));
+    assert!(cache.exists("tileset/0/1/3.pbf"));
+
+    // Purge directory
+    assert!(cache.purge("tileset/0").is_ok());
+    assert!(!cache.exists("tileset/0/1/3.pbf"));
+    assert!(cache.purge("tileset/0").is_ok());
 }
@@ -0,0 +1,141 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::{Cache, CacheEntryMetadata};
+use rusqlite::types::ToSql;
+use rusqlite::Connection;
+use std::io::{self, Read};
+use std::sync::Mutex;
+
+/// MBTiles file (https://github.com/mapbox/mbtiles-spec/blob/master/1.3/spec.md)
+///
+/// Tiles are written with cache paths `tileset/z/x/y.pbf` in XYZ adressing scheme.
+/// Other cache entries like TileJSON files are ignored.
+pub struct Mbtiles {
+    pub path: String,
+    conn: Mutex<Connection>,
+}
+
+fn sql_error<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Zoom level, column and row (TMS) of cache path
+fn tile_coords(path: &str) -> Option<(u8, u32, u32)> {
+    let parts: Vec<&str> = path.trim_right_matches(".pbf").split('/').collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    let zoom = parts[1].parse::<u8>().ok()?;
+    let column = parts[2].parse::<u32>().ok()?;
+    let ytile = parts[3].parse::<u32>().ok()?;
+    let maxy = 1u32.checked_shl(zoom as u32)?;
+    if ytile >= maxy {
+        return None;
+    }
+    Some((zoom, column, maxy - 1 - ytile))
+}
+
+impl Mbtiles {
+    /// Open or create MBTiles file
+    pub fn create(path: &str) -> Result<Mbtiles, String> {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "PRAGMA synchronous = OFF;
+             CREATE TABLE IF NOT EXISTS metadata (name text, value text);
+             CREATE UNIQUE INDEX IF NOT EXISTS name ON metadata (name);
+             CREATE TABLE IF NOT EXISTS tiles (zoom_level integer, tile_column integer, tile_row integer, tile_data blob);
+             CREATE UNIQUE INDEX IF NOT EXISTS tile_index ON tiles (zoom_level, tile_column, tile_row);",
+        ).map_err(|e| e.to_string())?;
+        Ok(Mbtiles {
+            path: path.to_string(),
+            conn: Mutex::new(conn),
+        })
+    }
+    /// Replace content of metadata table
+    pub fn write_metadata(&self, rows: &[(String, String)]) -> Result<(), io::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM metadata", &[])
+            .map_err(sql_error)?;
+        for &(ref name, ref value) in rows {
+            conn.execute(
+                "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+                &[name as &ToSql, value],
+            )
+            .map_err(sql_error)?;
+        }
+        Ok(())
+    }
+    fn tile_data(&self, path: &str) -> Option<Vec<u8>> {
+        let (zoom, column, row) = tile_coords(path)?;
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+            &[&(zoom as u32) as &ToSql, &column, &row],
+            |row| row.get(0),
+        ).ok()
+    }
+}
+
+impl Cache for Mbtiles {
+    fn info(&self) -> String {
+        format!("MBTiles file: {}", self.path)
+    }
+    fn baseurl(&self) -> String {
+        "http://localhost:6767".to_string()
+    }
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
+        match self.tile_data(path) {
+            Some(data) => {
+                read(&mut &data[..]);
+                true
+            }
+            None => false,
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+        let (zoom, column, row) = match tile_coords(path) {
+            Some(coords) => coords,
+            None => return Ok(()),
+        };
+        debug!("Mbtiles.write {}", path);
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            &[&(zoom as u32) as &ToSql, &column, &row, &obj],
+        ).map(|_| ())
+            .map_err(sql_error)
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.metadata(path).is_some()
+    }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        let conn = self.conn.lock().unwrap();
+        let parts: Vec<&str> = path.trim_right_matches(".pbf").split('/').collect();
+        let result = match parts.len() {
+            // tileset
+            1 => conn.execute("DELETE FROM tiles", &[]),
+            // tileset/z
+            2 => {
+                let zoom = parts[1].parse::<u32>().map_err(sql_error)?;
+                conn.execute("DELETE FROM tiles WHERE zoom_level = ?1", &[&zoom])
+            }
+            _ => match tile_coords(path) {
+                Some((zoom, column, row)) => conn.execute(
+                    "DELETE FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                    &[&(zoom as u32) as &ToSql, &column, &row],
+                ),
+                None => Ok(0),
+            },
+        };
+        result.map(|_| ()).map_err(sql_error)
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        self.tile_data(path).map(|data| CacheEntryMetadata {
+            size: data.len() as u64,
+            modified: None,
+        })
+    }
+}
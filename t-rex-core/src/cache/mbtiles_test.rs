@@ -0,0 +1,70 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::Cache;
+use cache::mbtiles::Mbtiles;
+use std::fs;
+
+#[test]
+fn test_mbtiles() {
+    use std::env;
+
+    let mut path = env::temp_dir();
+    path.push("t_rex_test.mbtiles");
+    let fname = format!("{}", &path.display());
+    let _ = fs::remove_file(&fname);
+
+    let cache = Mbtiles::create(&fname).unwrap();
+    let path = "tileset/2/1/0.pbf";
+    let obj = "0123456789";
+
+    // Cache miss
+    assert_eq!(cache.read(path, &mut |_| {}), false);
+    assert!(!cache.exists(path));
+
+    // Write tile
+    cache.write(path, obj.as_bytes()).unwrap();
+    assert!(cache.exists(path));
+    // Non-tile entries are ignored
+    cache.write("tileset.json", b"{}").unwrap();
+    assert!(!cache.exists("tileset.json"));
+
+    // Read tile
+    let mut s = String::new();
+    cache.read(path, &mut |f| {
+        let _ = f.read_to_string(&mut s);
+    });
+    assert_eq!(&s, "0123456789");
+    assert_eq!(cache.metadata(path).unwrap().size, 10);
+
+    // Metadata table
+    let rows = vec![
+        ("name".to_string(), "tileset".to_string()),
+        ("format".to_string(), "pbf".to_string()),
+    ];
+    cache.write_metadata(&rows).unwrap();
+    cache.write_metadata(&rows).unwrap();
+
+    // Purge zoom level
+    cache.purge("tileset/2").unwrap();
+    assert!(!cache.exists(path));
+
+    // Row in TMS scheme
+    cache.write(path, obj.as_bytes()).unwrap();
+    drop(cache);
+    let conn = ::rusqlite::Connection::open(&fname).unwrap();
+    let row: i64 = conn
+        .query_row(
+            "SELECT tile_row FROM tiles WHERE zoom_level = 2 AND tile_column = 1",
+            &[],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(row, 3);
+    let cnt: i64 = conn
+        .query_row("SELECT count(*) FROM metadata", &[], |row| row.get(0))
+        .unwrap();
+    assert_eq!(cnt, 2);
+}
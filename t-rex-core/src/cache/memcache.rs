@@ -0,0 +1,203 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::{Cache, CacheEntryMetadata};
+use cache::Tilecache;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Cached objects with least recently used eviction
+struct LruStore {
+    /// Data, last access and modification time of objects (unknown for objects read from the backend)
+    entries: HashMap<String, (Vec<u8>, u64, Option<SystemTime>)>,
+    /// Keys by last access
+    access: BTreeMap<u64, String>,
+    clock: u64,
+    size: usize,
+    max_size: Option<usize>,
+    max_entries: Option<usize>,
+}
+
+impl LruStore {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.access.remove(&entry.1);
+            entry.1 = clock;
+            self.access.insert(clock, key.to_string());
+        }
+    }
+    fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.touch(key);
+        self.entries.get(key).map(|entry| entry.0.clone())
+    }
+    fn insert(&mut self, key: &str, data: &[u8], modified: Option<SystemTime>) {
+        self.remove(key);
+        if self.max_size.map(|max| data.len() > max).unwrap_or(false) {
+            return;
+        }
+        self.size += data.len();
+        self.entries
+            .insert(key.to_string(), (data.to_vec(), 0, modified));
+        self.touch(key);
+        self.evict();
+    }
+    fn remove(&mut self, key: &str) {
+        if let Some((data, access, _)) = self.entries.remove(key) {
+            self.access.remove(&access);
+            self.size -= data.len();
+        }
+    }
+    fn full(&self) -> bool {
+        self.max_size.map(|max| self.size > max).unwrap_or(false)
+            || self
+                .max_entries
+                .map(|max| self.entries.len() > max)
+                .unwrap_or(false)
+    }
+    fn evict(&mut self) {
+        while self.full() {
+            let oldest = match self.access.iter().next() {
+                Some((_, key)) => key.clone(),
+                None => break,
+            };
+            self.remove(&oldest);
+        }
+    }
+}
+
+/// Cached objects of a memory cache, shared by the caches of all webserver workers
+#[derive(Clone)]
+pub struct MemoryStore(Arc<Mutex<LruStore>>);
+
+impl MemoryStore {
+    /// Store limited to `max_size` bytes and/or `max_entries` objects
+    pub fn new(max_size: Option<usize>, max_entries: Option<usize>) -> MemoryStore {
+        MemoryStore(Arc::new(Mutex::new(LruStore {
+            entries: HashMap::new(),
+            access: BTreeMap::new(),
+            clock: 0,
+            size: 0,
+            max_size,
+            max_entries,
+        })))
+    }
+    fn lock(&self) -> MutexGuard<LruStore> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// In-memory cache of recently used tiles in front of another cache backend
+pub struct MemoryCache {
+    store: MemoryStore,
+    pub backend: Box<Tilecache>,
+}
+
+impl MemoryCache {
+    /// Memory cache limited to `max_size` bytes and/or `max_entries` objects
+    pub fn new(
+        max_size: Option<usize>,
+        max_entries: Option<usize>,
+        backend: Tilecache,
+    ) -> MemoryCache {
+        MemoryCache::with_store(MemoryStore::new(max_size, max_entries), backend)
+    }
+    /// Memory cache with objects in `store`
+    pub fn with_store(store: MemoryStore, backend: Tilecache) -> MemoryCache {
+        MemoryCache {
+            store,
+            backend: Box::new(backend),
+        }
+    }
+    /// Use `store` instead of the own store, e.g. to share it with other workers
+    pub fn share_store(&mut self, store: &MemoryStore) {
+        self.store = store.clone();
+    }
+    /// Number and total size of cached objects
+    pub fn usage(&self) -> (usize, usize) {
+        let store = self.store.lock();
+        (store.entries.len(), store.size)
+    }
+}
+
+impl Cache for MemoryCache {
+    fn info(&self) -> String {
+        let store = self.store.lock();
+        let mut limits = Vec::new();
+        if let Some(max_size) = store.max_size {
+            limits.push(format!("{} MB", max_size / 1024 / 1024));
+        }
+        if let Some(max_entries) = store.max_entries {
+            limits.push(format!("{} tiles", max_entries));
+        }
+        format!(
+            "Memory tile cache ({}) - {}",
+            limits.join(", "),
+            self.backend.info()
+        )
+    }
+    fn baseurl(&self) -> String {
+        self.backend.baseurl()
+    }
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
+        let cached = self.store.lock().get(path);
+        if let Some(data) = cached {
+            debug!("MemoryCache.read {}", path);
+            read(&mut &data[..]);
+            return true;
+        }
+        let mut data = Vec::new();
+        let found = self.backend.read(path, &mut |f| {
+            let _ = f.read_to_end(&mut data);
+        });
+        if found {
+            self.store.lock().insert(path, &data, None);
+            read(&mut &data[..]);
+        }
+        found
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+        self.store.lock().insert(path, obj, Some(SystemTime::now()));
+        self.backend.write(path, obj)
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.store.lock().entries.contains_key(path) || self.backend.exists(path)
+    }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        {
+            let mut store = self.store.lock();
+            let dir = format!("{}/", path.trim_right_matches('/'));
+            let keys: Vec<String> = store
+                .entries
+                .keys()
+                .filter(|key| *key == path || key.starts_with(&dir))
+                .cloned()
+                .collect();
+            for key in keys {
+                store.remove(&key);
+            }
+        }
+        self.backend.purge(path)
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        let cached = self
+            .store
+            .lock()
+            .entries
+            .get(path)
+            .map(|entry| CacheEntryMetadata {
+                size: entry.0.len() as u64,
+                modified: entry.2,
+            });
+        match cached {
+            Some(ref md) if md.modified.is_some() => cached,
+            // Modification time of tiles read from the backend is only known by the backend
+            _ => self.backend.metadata(path).or(cached),
+        }
+    }
+}
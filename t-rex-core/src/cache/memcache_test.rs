@@ -0,0 +1,87 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::{Cache, Nocache};
+use cache::filecache::Filecache;
+use cache::memcache::{MemoryCache, MemoryStore};
+use cache::Tilecache;
+
+#[test]
+fn test_lru_eviction() {
+    let cache = MemoryCache::new(Some(25), Some(3), Tilecache::Nocache(Nocache));
+    cache.write("osm/0/0/0.pbf", b"0123456789").unwrap();
+    cache.write("osm/1/0/0.pbf", b"0123456789").unwrap();
+    assert_eq!(cache.usage(), (2, 20));
+    // Access makes 0/0/0 the most recently used tile
+    assert!(cache.read("osm/0/0/0.pbf", &mut |_| {}));
+    cache.write("osm/1/0/1.pbf", b"0123456789").unwrap();
+    assert_eq!(cache.usage(), (2, 20));
+    assert!(cache.exists("osm/0/0/0.pbf"));
+    assert!(!cache.exists("osm/1/0/0.pbf"));
+    assert!(cache.exists("osm/1/0/1.pbf"));
+
+    // Limited number of tiles
+    cache.write("osm/2/0/0.pbf", b"0").unwrap();
+    cache.write("osm/2/0/1.pbf", b"1").unwrap();
+    assert_eq!(cache.usage(), (3, 12));
+    // Tiles larger than the cache are not stored
+    cache.write("osm/2/1/1.pbf", &[0; 30]).unwrap();
+    assert!(!cache.exists("osm/2/1/1.pbf"));
+
+    cache.purge("osm/2").unwrap();
+    assert_eq!(cache.usage(), (1, 10));
+    assert_eq!(cache.metadata("osm/1/0/1.pbf").unwrap().size, 10);
+}
+
+#[test]
+fn test_memory_in_front_of_filecache() {
+    use std::env;
+    use std::fs;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_memcache_test");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+    let filecache = Filecache {
+        basepath: basepath.clone(),
+        baseurl: None,
+    };
+    filecache.write("osm/0/0/0.pbf", b"0123456789").unwrap();
+
+    let cache = MemoryCache::new(None, Some(10), Tilecache::Filecache(filecache));
+    assert_eq!(cache.usage(), (0, 0));
+    // Read from file cache and keep in memory
+    let mut s = String::new();
+    assert!(cache.read("osm/0/0/0.pbf", &mut |f| {
+        let _ = f.read_to_string(&mut s);
+    }));
+    assert_eq!(&s, "0123456789");
+    assert_eq!(cache.usage(), (1, 10));
+    // Modification time of the file
+    let modified = fs::metadata(dir.join("osm/0/0/0.pbf"))
+        .and_then(|md| md.modified())
+        .ok();
+    assert_eq!(cache.metadata("osm/0/0/0.pbf").unwrap().modified, modified);
+
+    cache.write("osm/0/0/1.pbf", b"01234").unwrap();
+    assert!(dir.join("osm/0/0/1.pbf").exists());
+    assert!(cache.metadata("osm/0/0/1.pbf").unwrap().modified.is_some());
+    cache.purge("osm").unwrap();
+    assert_eq!(cache.usage(), (0, 0));
+    assert!(!cache.exists("osm/0/0/0.pbf"));
+}
+
+#[test]
+fn test_shared_store() {
+    let store = MemoryStore::new(None, Some(10));
+    let worker1 = MemoryCache::with_store(store.clone(), Tilecache::Nocache(Nocache));
+    let mut worker2 = MemoryCache::new(None, Some(10), Tilecache::Nocache(Nocache));
+    worker2.share_store(&store);
+    worker1.write("osm/0/0/0.pbf", b"0123456789").unwrap();
+    assert!(worker2.exists("osm/0/0/0.pbf"));
+    // Purging in one worker removes the tiles of all workers
+    worker2.purge("osm").unwrap();
+    assert!(!worker1.exists("osm/0/0/0.pbf"));
+}
@@ -5,21 +5,62 @@
 
 pub mod cache;
 pub mod filecache;
+#[cfg(feature = "with-mbtiles")]
+pub mod mbtiles;
+pub mod memcache;
+pub mod pmtiles;
+#[cfg(feature = "with-redis")]
+pub mod rediscache;
+#[cfg(feature = "with-s3")]
+pub mod s3cache;
 
 #[cfg(test)]
 mod filecache_test;
+#[cfg(all(test, feature = "with-mbtiles"))]
+mod mbtiles_test;
+#[cfg(test)]
+mod memcache_test;
+#[cfg(test)]
+mod pmtiles_test;
+#[cfg(all(test, feature = "with-redis"))]
+mod rediscache_test;
+#[cfg(all(test, feature = "with-s3"))]
+mod s3cache_test;
 
 pub use self::cache::Cache;
+pub use self::cache::CacheEntryMetadata;
 pub use self::cache::Nocache;
 pub use self::filecache::Filecache;
+#[cfg(feature = "with-mbtiles")]
+pub use self::mbtiles::Mbtiles;
+pub use self::memcache::{MemoryCache, MemoryStore};
+pub use self::pmtiles::Pmtiles;
+#[cfg(feature = "with-redis")]
+pub use self::rediscache::RedisCache;
+#[cfg(feature = "with-s3")]
+pub use self::s3cache::S3Cache;
+use core::config::{CacheCfg, CacheRedisCfg, CacheS3Cfg};
 use core::ApplicationCfg;
 use core::Config;
+use mvt::tile::TileCompression;
 use std::io;
 use std::io::Read;
 
 pub enum Tilecache {
     Nocache(Nocache),
     Filecache(Filecache),
+    #[cfg(feature = "with-s3")]
+    S3Cache(S3Cache),
+    #[cfg(feature = "with-redis")]
+    RedisCache(RedisCache),
+    #[cfg(feature = "with-mbtiles")]
+    Mbtiles(Mbtiles),
+    /// PMTiles archive written after seeding
+    Pmtiles(Pmtiles),
+    /// Recently used tiles in memory in front of another backend
+    Memory(MemoryCache),
+    /// Cache backend implemented outside of t-rex
+    Custom(Box<Cache>),
 }
 
 impl Cache for Tilecache {
@@ -27,57 +68,242 @@ impl Cache for Tilecache {
         match self {
             &Tilecache::Nocache(ref cache) => cache.info(),
             &Tilecache::Filecache(ref cache) => cache.info(),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.info(),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.info(),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.info(),
+            &Tilecache::Pmtiles(ref cache) => cache.info(),
+            &Tilecache::Memory(ref cache) => cache.info(),
+            &Tilecache::Custom(ref cache) => cache.info(),
         }
     }
     fn baseurl(&self) -> String {
         match self {
             &Tilecache::Nocache(ref cache) => cache.baseurl(),
             &Tilecache::Filecache(ref cache) => cache.baseurl(),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.baseurl(),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.baseurl(),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.baseurl(),
+            &Tilecache::Pmtiles(ref cache) => cache.baseurl(),
+            &Tilecache::Memory(ref cache) => cache.baseurl(),
+            &Tilecache::Custom(ref cache) => cache.baseurl(),
         }
     }
-    fn read<F>(&self, path: &str, read: F) -> bool
-    where
-        F: FnMut(&mut Read),
-    {
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
         match self {
             &Tilecache::Nocache(ref cache) => cache.read(path, read),
             &Tilecache::Filecache(ref cache) => cache.read(path, read),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.read(path, read),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.read(path, read),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.read(path, read),
+            &Tilecache::Pmtiles(ref cache) => cache.read(path, read),
+            &Tilecache::Memory(ref cache) => cache.read(path, read),
+            &Tilecache::Custom(ref cache) => cache.read(path, read),
         }
     }
     fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
         match self {
             &Tilecache::Nocache(ref cache) => cache.write(path, obj),
             &Tilecache::Filecache(ref cache) => cache.write(path, obj),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.write(path, obj),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.write(path, obj),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.write(path, obj),
+            &Tilecache::Pmtiles(ref cache) => cache.write(path, obj),
+            &Tilecache::Memory(ref cache) => cache.write(path, obj),
+            &Tilecache::Custom(ref cache) => cache.write(path, obj),
         }
     }
     fn exists(&self, path: &str) -> bool {
         match self {
             &Tilecache::Nocache(ref cache) => cache.exists(path),
             &Tilecache::Filecache(ref cache) => cache.exists(path),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.exists(path),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.exists(path),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.exists(path),
+            &Tilecache::Pmtiles(ref cache) => cache.exists(path),
+            &Tilecache::Memory(ref cache) => cache.exists(path),
+            &Tilecache::Custom(ref cache) => cache.exists(path),
+        }
+    }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        match self {
+            &Tilecache::Nocache(ref cache) => cache.purge(path),
+            &Tilecache::Filecache(ref cache) => cache.purge(path),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.purge(path),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.purge(path),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.purge(path),
+            &Tilecache::Pmtiles(ref cache) => cache.purge(path),
+            &Tilecache::Memory(ref cache) => cache.purge(path),
+            &Tilecache::Custom(ref cache) => cache.purge(path),
+        }
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        match self {
+            &Tilecache::Nocache(ref cache) => cache.metadata(path),
+            &Tilecache::Filecache(ref cache) => cache.metadata(path),
+            #[cfg(feature = "with-s3")]
+            &Tilecache::S3Cache(ref cache) => cache.metadata(path),
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(ref cache) => cache.metadata(path),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(ref cache) => cache.metadata(path),
+            &Tilecache::Pmtiles(ref cache) => cache.metadata(path),
+            &Tilecache::Memory(ref cache) => cache.metadata(path),
+            &Tilecache::Custom(ref cache) => cache.metadata(path),
         }
     }
 }
 
+impl Tilecache {
+    /// New store of the memory cache configured in `[cache.memory]`
+    pub fn memory_store_from_config(config: &ApplicationCfg) -> Option<MemoryStore> {
+        let memory = config.cache.as_ref()?.memory.as_ref()?;
+        // Default: 64 MB
+        let max_mb = match (memory.max_mb, memory.max_tiles) {
+            (None, None) => Some(64),
+            (max_mb, _) => max_mb,
+        };
+        Some(MemoryStore::new(
+            max_mb.map(|mb| mb as usize * 1024 * 1024),
+            memory.max_tiles,
+        ))
+    }
+    /// Whether the backend reports modification times of cached tiles, required for cache TTLs
+    pub fn tracks_modification(&self) -> bool {
+        match self {
+            #[cfg(feature = "with-redis")]
+            &Tilecache::RedisCache(_) => false,
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtiles(_) => false,
+            &Tilecache::Pmtiles(_) => false,
+            &Tilecache::Memory(ref cache) => cache.backend.tracks_modification(),
+            _ => true,
+        }
+    }
+    /// Share `store` with a memory cache
+    pub fn share_memory_store(&mut self, store: &MemoryStore) {
+        if let Tilecache::Memory(ref mut cache) = *self {
+            cache.share_store(store);
+        }
+    }
+    fn backend_from_config(cache: &CacheCfg) -> Result<Self, String> {
+        if let Some(ref file) = cache.file {
+            let fc = Filecache {
+                basepath: file.base.clone(),
+                baseurl: file.baseurl.clone(),
+            };
+            return Ok(Tilecache::Filecache(fc));
+        }
+        if let Some(ref s3) = cache.s3 {
+            let compression = match cache.compression {
+                Some(ref compression) => compression.parse()?,
+                None => TileCompression::default(),
+            };
+            return Tilecache::s3_from_config(s3, compression);
+        }
+        if let Some(ref redis) = cache.redis {
+            return Tilecache::redis_from_config(redis);
+        }
+        Ok(Tilecache::Nocache(Nocache))
+    }
+    #[cfg(feature = "with-s3")]
+    fn s3_from_config(cfg: &CacheS3Cfg, compression: TileCompression) -> Result<Self, String> {
+        let credentials = match (&cfg.access_key, &cfg.secret_key) {
+            (&Some(ref access_key), &Some(ref secret_key)) => {
+                Some((access_key.clone(), secret_key.clone()))
+            }
+            _ => None,
+        };
+        S3Cache::new(
+            &cfg.bucket,
+            cfg.prefix.clone(),
+            cfg.region.clone(),
+            cfg.endpoint.clone(),
+            credentials,
+            cfg.baseurl.clone(),
+        )
+        .map(|mut cache| {
+            cache.compression = compression;
+            Tilecache::S3Cache(cache)
+        })
+    }
+    #[cfg(not(feature = "with-s3"))]
+    fn s3_from_config(_cfg: &CacheS3Cfg, _compression: TileCompression) -> Result<Self, String> {
+        Err("S3 cache not supported in this build".to_string())
+    }
+    #[cfg(feature = "with-redis")]
+    fn redis_from_config(cfg: &CacheRedisCfg) -> Result<Self, String> {
+        RedisCache::new(&cfg.url, cfg.prefix.clone(), cfg.ttl, cfg.baseurl.clone())
+            .map(Tilecache::RedisCache)
+    }
+    #[cfg(not(feature = "with-redis"))]
+    fn redis_from_config(_cfg: &CacheRedisCfg) -> Result<Self, String> {
+        Err("Redis cache not supported in this build".to_string())
+    }
+}
+
 impl<'a> Config<'a, ApplicationCfg> for Tilecache {
     fn from_config(config: &ApplicationCfg) -> Result<Self, String> {
-        config
-            .cache
-            .as_ref()
-            .map(|cache| {
-                let fc = Filecache {
-                    basepath: cache.file.base.clone(),
-                    baseurl: cache.file.baseurl.clone(),
-                };
-                Tilecache::Filecache(fc)
-            })
-            .or(Some(Tilecache::Nocache(Nocache)))
-            .ok_or("".to_string())
+        let cache = match config.cache {
+            Some(ref cache) => cache,
+            None => return Ok(Tilecache::Nocache(Nocache)),
+        };
+        let backend = Tilecache::backend_from_config(cache)?;
+        match Tilecache::memory_store_from_config(config) {
+            Some(store) => Ok(Tilecache::Memory(MemoryCache::with_store(store, backend))),
+            None => Ok(backend),
+        }
     }
     fn gen_config() -> String {
         let toml = r#"
+#[cache]
+## Compression of cached tiles: gzip or brotli
+#compression = "brotli"
+
+## Recently used tiles in memory shared by all workers, in front of the file, S3 or Redis cache
+#[cache.memory]
+#max_mb = 256
+#max_tiles = 10000
+
 #[cache.file]
 #base = "/tmp/mvtcache"
+## Tiles are served from baseurl without a Content-Encoding header for brotli compressed files,
+## configure the web server to add `Content-Encoding: br` when using compression = "brotli"
 #baseurl = "http://example.com/tiles"
+
+#[cache.s3]
+#bucket = "mvtcache"
+#prefix = "tiles"
+#region = "eu-central-1"
+## S3 compatible service like MinIO
+#endpoint = "http://localhost:9000"
+## Default: AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables
+#access_key = "minio"
+#secret_key = "minio123"
+#baseurl = "https://tiles.example.com"
+
+#[cache.redis]
+#url = "redis://127.0.0.1/"
+#prefix = "t-rex"
+## Expiration of cached tiles in seconds
+#ttl = 86400
 "#;
         toml.to_string()
     }
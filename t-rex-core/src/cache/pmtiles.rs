@@ -0,0 +1,430 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::{Cache, CacheEntryMetadata};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mvt::tile::TileCompression;
+use serde_json::{self, Value};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+
+const HEADER_SIZE: usize = 127;
+/// Header and root directory have to fit into the first 16 KB of the archive
+const MAX_ROOT_SIZE: usize = 16384 - HEADER_SIZE;
+/// Initial number of entries per leaf directory
+const LEAF_SIZE: usize = 4096;
+
+/// PMTiles archive (https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md)
+///
+/// Tiles are written with cache paths `tileset/z/x/y.pbf` in XYZ adressing scheme
+/// into a temporary file and copied into the archive in tile id order by `finalize`.
+/// Other cache entries like TileJSON files are ignored.
+pub struct Pmtiles {
+    pub path: String,
+    compression: TileCompression,
+    store: Mutex<TileStore>,
+}
+
+/// Tile data collected before finalizing the archive
+struct TileStore {
+    file: File,
+    size: u64,
+    /// Offset and length in temporary file by tile id
+    tiles: BTreeMap<u64, (u64, u32)>,
+}
+
+/// Directory entry
+#[derive(Debug, PartialEq)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+    /// Number of consecutive tiles with the same content. 0 for leaf directories.
+    run_length: u32,
+}
+
+fn pmtiles_error<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Number of tiles of all zoom levels lower than `zoom`
+fn zoom_offset(zoom: u8) -> u64 {
+    ((1u64 << (zoom as u64 * 2)) - 1) / 3
+}
+
+/// Tile id of XYZ tile (position on Hilbert curve, counted over all zoom levels)
+pub fn tile_id(zoom: u8, x: u32, y: u32) -> u64 {
+    let n = 1u64 << zoom;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u64;
+        let ry = (y & s > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        // rotate quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            let t = x;
+            x = y;
+            y = t;
+        }
+        s /= 2;
+    }
+    zoom_offset(zoom) + d
+}
+
+/// Zoom level of tile id
+fn tile_zoom(tile_id: u64) -> u8 {
+    let mut zoom = 0;
+    while zoom < 31 && zoom_offset(zoom + 1) <= tile_id {
+        zoom += 1;
+    }
+    zoom
+}
+
+/// Zoom level, column and row (XYZ) of cache path
+fn tile_coords(path: &str) -> Option<(u8, u32, u32)> {
+    let parts: Vec<&str> = path.trim_right_matches(".pbf").split('/').collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    let zoom = parts[1].parse::<u8>().ok()?;
+    let x = parts[2].parse::<u32>().ok()?;
+    let y = parts[3].parse::<u32>().ok()?;
+    let max = 1u32.checked_shl(zoom as u32)?;
+    if x >= max || y >= max {
+        return None;
+    }
+    Some((zoom, x, y))
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Append little-endian integer of `bytes` length
+fn write_le(buf: &mut Vec<u8>, value: u64, bytes: usize) {
+    for i in 0..bytes {
+        buf.push((value >> (i * 8)) as u8);
+    }
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(data)?;
+    gz.finish()
+}
+
+/// Gzip compressed directory
+fn serialize_directory(entries: &[Entry]) -> Result<Vec<u8>, io::Error> {
+    let mut buf = Vec::new();
+    write_varint(&mut buf, entries.len() as u64);
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut buf, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut buf, entry.run_length as u64);
+    }
+    for entry in entries {
+        write_varint(&mut buf, entry.length as u64);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        // 0: entry follows directly after the previous one
+        if i > 0 && entry.offset == entries[i - 1].offset + entries[i - 1].length as u64 {
+            write_varint(&mut buf, 0);
+        } else {
+            write_varint(&mut buf, entry.offset + 1);
+        }
+    }
+    gzip(&buf)
+}
+
+/// Root directory and leaf directories, if the root directory would exceed `MAX_ROOT_SIZE`
+fn build_directories(entries: &[Entry]) -> Result<(Vec<u8>, Vec<u8>), io::Error> {
+    let root = serialize_directory(entries)?;
+    if root.len() <= MAX_ROOT_SIZE {
+        return Ok((root, Vec::new()));
+    }
+    let mut leaf_size = LEAF_SIZE;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = serialize_directory(chunk)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend_from_slice(&leaf);
+        }
+        let root = serialize_directory(&root_entries)?;
+        if root.len() <= MAX_ROOT_SIZE {
+            return Ok((root, leaves));
+        }
+        leaf_size *= 2;
+    }
+}
+
+/// Coordinate in header encoding
+fn e7(value: f64) -> u64 {
+    (value * 10_000_000.0).round() as i32 as u32 as u64
+}
+
+/// Numbers of JSON array
+fn json_numbers(value: &Value) -> Vec<f64> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(|v| v.as_f64()).collect())
+        .unwrap_or_default()
+}
+
+fn read_at(file: &mut File, offset: u64, length: u32) -> Result<Vec<u8>, io::Error> {
+    let mut data = vec![0; length as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+impl Pmtiles {
+    /// Create PMTiles archive with tiles in `compression`.
+    /// The archive is written by `finalize`.
+    pub fn create(path: &str, compression: TileCompression) -> Result<Pmtiles, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(Pmtiles::tmppath(path))
+            .map_err(|e| e.to_string())?;
+        Ok(Pmtiles {
+            path: path.to_string(),
+            compression,
+            store: Mutex::new(TileStore {
+                file,
+                size: 0,
+                tiles: BTreeMap::new(),
+            }),
+        })
+    }
+    fn tmppath(path: &str) -> String {
+        format!("{}.tiles", path)
+    }
+    fn tile_data(&self, path: &str) -> Option<Vec<u8>> {
+        let (zoom, x, y) = tile_coords(path)?;
+        let mut store = self.store.lock().unwrap();
+        let (offset, length) = *store.tiles.get(&tile_id(zoom, x, y))?;
+        read_at(&mut store.file, offset, length).ok()
+    }
+    /// Write archive with all collected tiles and JSON `metadata`.
+    /// Header bounds and center are taken from the TileJSON entries `bounds` and `center`.
+    pub fn finalize(&self, metadata: &Value) -> Result<(), io::Error> {
+        let mut guard = self.store.lock().unwrap();
+        let store = &mut *guard;
+        let file = &mut store.file;
+
+        // Directory entries in tile id order with consecutive equal tiles as runs
+        let mut entries: Vec<Entry> = Vec::new();
+        // Position of tile contents in temporary file
+        let mut contents: Vec<(u64, u32)> = Vec::new();
+        let mut last_data = Vec::new();
+        let mut offset = 0;
+        for (&tile_id, &(tmp_offset, length)) in &store.tiles {
+            let data = read_at(file, tmp_offset, length)?;
+            if let Some(last) = entries.last_mut() {
+                if last.tile_id + last.run_length as u64 == tile_id && data == last_data {
+                    last.run_length += 1;
+                    continue;
+                }
+            }
+            entries.push(Entry {
+                tile_id,
+                offset,
+                length,
+                run_length: 1,
+            });
+            contents.push((tmp_offset, length));
+            offset += length as u64;
+            last_data = data;
+        }
+        let tile_data_length = offset;
+
+        let (root, leaves) = build_directories(&entries)?;
+        let bounds = match json_numbers(&metadata["bounds"]) {
+            ref bounds if bounds.len() == 4 => bounds.clone(),
+            _ => vec![-180.0, -85.0511, 180.0, 85.0511],
+        };
+        let minzoom = store.tiles.keys().next().map_or(0, |id| tile_zoom(*id));
+        let maxzoom = store.tiles.keys().last().map_or(0, |id| tile_zoom(*id));
+        let center = match json_numbers(&metadata["center"]) {
+            ref center if center.len() == 3 => center.clone(),
+            _ => vec![
+                (bounds[0] + bounds[2]) / 2.0,
+                (bounds[1] + bounds[3]) / 2.0,
+                minzoom as f64,
+            ],
+        };
+        let metadata = gzip(&serde_json::to_vec(metadata).map_err(pmtiles_error)?)?;
+
+        let root_offset = HEADER_SIZE as u64;
+        let metadata_offset = root_offset + root.len() as u64;
+        let leaves_offset = metadata_offset + metadata.len() as u64;
+        let data_offset = leaves_offset + leaves.len() as u64;
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(b"PMTiles");
+        header.push(3);
+        for &value in &[
+            root_offset,
+            root.len() as u64,
+            metadata_offset,
+            metadata.len() as u64,
+            leaves_offset,
+            leaves.len() as u64,
+            data_offset,
+            tile_data_length,
+            // addressed tiles, tile entries, tile contents
+            store.tiles.len() as u64,
+            entries.len() as u64,
+            contents.len() as u64,
+        ] {
+            write_le(&mut header, value, 8);
+        }
+        // clustered
+        header.push(1);
+        // internal compression: gzip
+        header.push(2);
+        header.push(match self.compression {
+            TileCompression::None => 1,
+            TileCompression::Gzip => 2,
+            TileCompression::Brotli => 3,
+        });
+        // tile type: MVT
+        header.push(1);
+        header.push(minzoom);
+        header.push(maxzoom);
+        for &coord in &bounds {
+            write_le(&mut header, e7(coord), 4);
+        }
+        header.push(cmp::min(cmp::max(center[2] as u8, minzoom), maxzoom));
+        write_le(&mut header, e7(center[0]), 4);
+        write_le(&mut header, e7(center[1]), 4);
+
+        let mut out = BufWriter::new(File::create(&self.path)?);
+        out.write_all(&header)?;
+        out.write_all(&root)?;
+        out.write_all(&metadata)?;
+        out.write_all(&leaves)?;
+        for &(tmp_offset, length) in &contents {
+            out.write_all(&read_at(file, tmp_offset, length)?)?;
+        }
+        out.flush()
+    }
+}
+
+impl Drop for Pmtiles {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(Pmtiles::tmppath(&self.path));
+    }
+}
+
+impl Cache for Pmtiles {
+    fn info(&self) -> String {
+        format!("PMTiles file: {}", self.path)
+    }
+    fn baseurl(&self) -> String {
+        "http://localhost:6767".to_string()
+    }
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
+        match self.tile_data(path) {
+            Some(data) => {
+                read(&mut &data[..]);
+                true
+            }
+            None => false,
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+        let (zoom, x, y) = match tile_coords(path) {
+            Some(coords) => coords,
+            None => return Ok(()),
+        };
+        debug!("Pmtiles.write {}", path);
+        let mut guard = self.store.lock().unwrap();
+        let store = &mut *guard;
+        store.file.seek(SeekFrom::Start(store.size))?;
+        store.file.write_all(obj)?;
+        store
+            .tiles
+            .insert(tile_id(zoom, x, y), (store.size, obj.len() as u32));
+        store.size += obj.len() as u64;
+        Ok(())
+    }
+    fn exists(&self, path: &str) -> bool {
+        match tile_coords(path) {
+            Some((zoom, x, y)) => self
+                .store
+                .lock()
+                .unwrap()
+                .tiles
+                .contains_key(&tile_id(zoom, x, y)),
+            None => false,
+        }
+    }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        let mut store = self.store.lock().unwrap();
+        let parts: Vec<&str> = path.trim_right_matches(".pbf").split('/').collect();
+        match parts.len() {
+            // tileset
+            1 => store.tiles.clear(),
+            // tileset/z
+            2 => {
+                let zoom = parts[1].parse::<u8>().map_err(pmtiles_error)?;
+                if zoom > 30 {
+                    return Ok(());
+                }
+                let ids: Vec<u64> = store
+                    .tiles
+                    .range(zoom_offset(zoom)..zoom_offset(zoom + 1))
+                    .map(|(id, _)| *id)
+                    .collect();
+                for id in ids {
+                    store.tiles.remove(&id);
+                }
+            }
+            _ => {
+                if let Some((zoom, x, y)) = tile_coords(path) {
+                    store.tiles.remove(&tile_id(zoom, x, y));
+                }
+            }
+        }
+        Ok(())
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        let (zoom, x, y) = tile_coords(path)?;
+        let store = self.store.lock().unwrap();
+        store
+            .tiles
+            .get(&tile_id(zoom, x, y))
+            .map(|&(_, length)| CacheEntryMetadata {
+                size: length as u64,
+                modified: None,
+            })
+    }
+}
@@ -0,0 +1,133 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::Cache;
+use cache::pmtiles::{tile_id, Pmtiles};
+use flate2::read::GzDecoder;
+use mvt::tile::TileCompression;
+use std::fs::{self, File};
+use std::io::Read;
+
+#[test]
+fn test_tile_id() {
+    assert_eq!(tile_id(0, 0, 0), 0);
+    assert_eq!(tile_id(1, 0, 0), 1);
+    assert_eq!(tile_id(1, 0, 1), 2);
+    assert_eq!(tile_id(1, 1, 1), 3);
+    assert_eq!(tile_id(1, 1, 0), 4);
+    assert_eq!(tile_id(2, 0, 0), 5);
+    assert_eq!(tile_id(3, 0, 0), 21);
+    assert_eq!(tile_id(3, 7, 0), 84);
+    assert_eq!(tile_id(12, 3423, 1763), 19078479);
+    assert_eq!(tile_id(20, 0, 0), 366503875925);
+}
+
+fn le(data: &[u8], pos: usize, bytes: usize) -> u64 {
+    (0..bytes).fold(0, |value, i| value | (data[pos + i] as u64) << (i * 8))
+}
+
+fn read_varints(data: &[u8]) -> Vec<u64> {
+    let mut values = Vec::new();
+    let mut value = 0;
+    let mut shift = 0;
+    for &byte in data {
+        value |= ((byte & 0x7f) as u64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        }
+    }
+    values
+}
+
+#[test]
+fn test_pmtiles() {
+    use std::env;
+
+    let mut path = env::temp_dir();
+    path.push("t_rex_test.pmtiles");
+    let fname = format!("{}", &path.display());
+    let _ = fs::remove_file(&fname);
+
+    let cache = Pmtiles::create(&fname, TileCompression::Gzip).unwrap();
+    let path = "tileset/2/1/0.pbf";
+    let obj = "0123456789";
+
+    // Cache miss
+    assert_eq!(cache.read(path, &mut |_| {}), false);
+    assert!(!cache.exists(path));
+
+    // Write tile
+    cache.write(path, obj.as_bytes()).unwrap();
+    assert!(cache.exists(path));
+    // Non-tile entries are ignored
+    cache.write("tileset.json", b"{}").unwrap();
+    assert!(!cache.exists("tileset.json"));
+
+    // Read tile
+    let mut s = String::new();
+    cache.read(path, &mut |f| {
+        let _ = f.read_to_string(&mut s);
+    });
+    assert_eq!(&s, "0123456789");
+    assert_eq!(cache.metadata(path).unwrap().size, 10);
+
+    // Purge zoom level
+    cache.purge("tileset/2").unwrap();
+    assert!(!cache.exists(path));
+
+    // Tiles 1/0/0 and 1/0/1 with equal content are stored once
+    cache.write("tileset/1/0/0.pbf", b"same").unwrap();
+    cache.write("tileset/1/0/1.pbf", b"same").unwrap();
+    cache.write(path, obj.as_bytes()).unwrap();
+    let metadata = json!({
+        "name": "tileset",
+        "bounds": [-180.0, -85.0, 180.0, 85.0],
+        "center": [8.0, 47.0, 1]
+    });
+    cache.finalize(&metadata).unwrap();
+    drop(cache);
+    assert!(fs::metadata(format!("{}.tiles", fname)).is_err());
+
+    let mut data = Vec::new();
+    File::open(&fname).unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(&data[0..7], b"PMTiles");
+    assert_eq!(data[7], 3);
+    // addressed tiles, tile entries, tile contents
+    assert_eq!(le(&data, 72, 8), 3);
+    assert_eq!(le(&data, 80, 8), 2);
+    assert_eq!(le(&data, 88, 8), 2);
+    // tile compression, tile type, min and max zoom
+    assert_eq!(&data[98..102], &[2, 1, 1, 2]);
+    assert_eq!(le(&data, 102, 4) as u32 as i32, -1_800_000_000);
+    assert_eq!(le(&data, 119, 4), 80_000_000);
+    let data_offset = le(&data, 56, 8) as usize;
+    assert_eq!(le(&data, 64, 8), 14);
+    assert_eq!(&data[data_offset..], b"same0123456789");
+
+    // Root directory: count, tile ids, run lengths, lengths, offsets
+    let root_offset = le(&data, 8, 8) as usize;
+    let root_length = le(&data, 16, 8) as usize;
+    let mut root = Vec::new();
+    GzDecoder::new(&data[root_offset..root_offset + root_length])
+        .read_to_end(&mut root)
+        .unwrap();
+    assert_eq!(
+        read_varints(&root),
+        vec![2, 1, tile_id(2, 1, 0) - 1, 2, 1, 4, 10, 1, 0]
+    );
+
+    // JSON metadata
+    let metadata_offset = le(&data, 24, 8) as usize;
+    let metadata_length = le(&data, 32, 8) as usize;
+    let mut json = String::new();
+    GzDecoder::new(&data[metadata_offset..metadata_offset + metadata_length])
+        .read_to_string(&mut json)
+        .unwrap();
+    assert!(json.contains(r#""name":"tileset""#));
+    let _ = fs::remove_file(&fname);
+}
@@ -0,0 +1,144 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::{Cache, CacheEntryMetadata};
+use redis::{self, Client, Connection, RedisResult};
+use std::io::{self, Read};
+use std::sync::Mutex;
+
+/// Tile cache in Redis, shared by multiple t-rex instances
+pub struct RedisCache {
+    /// Key prefix of cached tiles
+    pub prefix: Option<String>,
+    /// Expiration of cached tiles in seconds
+    pub ttl: Option<u64>,
+    pub baseurl: Option<String>,
+    client: Client,
+    /// Connection opened on first access and after errors
+    connection: Mutex<Option<Connection>>,
+}
+
+fn redis_error<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+impl RedisCache {
+    /// Create Redis cache with connection URL `redis://[:PASSWORD@]HOST[:PORT][/DB]`
+    pub fn new(
+        url: &str,
+        prefix: Option<String>,
+        ttl: Option<u64>,
+        baseurl: Option<String>,
+    ) -> Result<RedisCache, String> {
+        let client = Client::open(url).map_err(|e| e.to_string())?;
+        Ok(RedisCache {
+            prefix,
+            ttl,
+            baseurl,
+            client,
+            connection: Mutex::new(None),
+        })
+    }
+    /// Key of cache path
+    pub fn key(&self, path: &str) -> String {
+        match self.prefix {
+            Some(ref prefix) if !prefix.is_empty() => {
+                format!("{}:{}", prefix.trim_right_matches(':'), path)
+            }
+            _ => path.to_string(),
+        }
+    }
+    fn query<T, F>(&self, f: F) -> RedisResult<T>
+    where
+        F: FnOnce(&Connection) -> RedisResult<T>,
+    {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(self.client.get_connection()?);
+        }
+        let result = f(connection.as_ref().unwrap());
+        if result.is_err() {
+            // Reconnect on next access
+            *connection = None;
+        }
+        result
+    }
+}
+
+impl Cache for RedisCache {
+    fn info(&self) -> String {
+        format!("Tile cache Redis key prefix: {}", self.key(""))
+    }
+    fn baseurl(&self) -> String {
+        self.baseurl
+            .clone()
+            .unwrap_or("http://localhost:6767".to_string())
+    }
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
+        debug!("RedisCache.read {}", self.key(path));
+        let data: RedisResult<Option<Vec<u8>>> =
+            self.query(|con| redis::cmd("GET").arg(self.key(path)).query(con));
+        match data {
+            Ok(Some(data)) => {
+                read(&mut &data[..]);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                warn!("RedisCache.read {}: {}", self.key(path), e);
+                false
+            }
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+        debug!("RedisCache.write {}", self.key(path));
+        self.query(|con| {
+            let mut cmd = redis::cmd("SET");
+            cmd.arg(self.key(path)).arg(obj);
+            if let Some(ttl) = self.ttl {
+                cmd.arg("EX").arg(ttl);
+            }
+            cmd.query(con)
+        })
+        .map_err(redis_error)
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.query(|con| redis::cmd("EXISTS").arg(self.key(path)).query(con))
+            .unwrap_or(false)
+    }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        debug!("RedisCache.purge {}", self.key(path));
+        self.query(|con| {
+            let deleted: u64 = redis::cmd("DEL").arg(self.key(path)).query(con)?;
+            if deleted > 0 {
+                return Ok(());
+            }
+            // Delete all keys below path
+            let pattern = format!("{}/*", self.key(path).trim_right_matches('/'));
+            let keys: Vec<String> = redis::cmd("SCAN")
+                .cursor_arg(0)
+                .arg("MATCH")
+                .arg(pattern)
+                .iter(con)?
+                .collect();
+            for key in keys {
+                redis::cmd("DEL").arg(key).query::<u64>(con)?;
+            }
+            Ok(())
+        })
+        .map_err(redis_error)
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        if !self.exists(path) {
+            return None;
+        }
+        self.query(|con| redis::cmd("STRLEN").arg(self.key(path)).query(con))
+            .ok()
+            .map(|size| CacheEntryMetadata {
+                size,
+                modified: None,
+            })
+    }
+}
@@ -0,0 +1,46 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::Cache;
+use cache::rediscache::RedisCache;
+
+#[test]
+fn test_redis_keys() {
+    let cache = RedisCache::new(
+        "redis://127.0.0.1/",
+        Some("t-rex".to_string()),
+        Some(3600),
+        None,
+    )
+    .unwrap();
+    assert_eq!(cache.key("osm/6/33/22.pbf"), "t-rex:osm/6/33/22.pbf");
+    assert_eq!(cache.info(), "Tile cache Redis key prefix: t-rex:");
+
+    let cache = RedisCache::new("redis://127.0.0.1/", None, None, None).unwrap();
+    assert_eq!(cache.key("osm/6/33/22.pbf"), "osm/6/33/22.pbf");
+    assert!(RedisCache::new("http://127.0.0.1/", None, None, None).is_err());
+}
+
+#[test]
+#[ignore]
+fn test_redis_cache() {
+    use std::env;
+
+    let url = env::var("REDIS_URL").expect("REDIS_URL undefined");
+    let cache = RedisCache::new(&url, Some("t-rex-test".to_string()), Some(60), None).unwrap();
+    let _ = cache.purge("osm");
+    assert!(!cache.exists("osm/6/33/22.pbf"));
+    cache.write("osm/6/33/22.pbf", b"tile").unwrap();
+    cache.write("osm/6/33/23.pbf", b"tile").unwrap();
+    assert!(cache.exists("osm/6/33/22.pbf"));
+    assert_eq!(cache.metadata("osm/6/33/22.pbf").unwrap().size, 4);
+    let mut data = Vec::new();
+    assert!(cache.read("osm/6/33/22.pbf", &mut |f| {
+        let _ = f.read_to_end(&mut data);
+    }));
+    assert_eq!(data, b"tile");
+    cache.purge("osm/6").unwrap();
+    assert!(!cache.exists("osm/6/33/23.pbf"));
+}
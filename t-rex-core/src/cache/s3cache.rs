@@ -0,0 +1,209 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::{Cache, CacheEntryMetadata};
+use mvt::tile::TileCompression;
+use rusoto_core::credential::StaticProvider;
+use rusoto_core::{HttpClient, Region};
+use rusoto_s3::{
+    DeleteObjectRequest, GetObjectRequest, HeadObjectRequest, ListObjectsV2Request,
+    PutObjectRequest, S3Client, S3,
+};
+use std::io::{self, Read};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use time;
+
+/// Tile cache in S3 compatible object storage
+pub struct S3Cache {
+    pub bucket: String,
+    /// Key prefix of cached objects
+    pub prefix: Option<String>,
+    pub baseurl: Option<String>,
+    /// Compression of stored tiles (Content-Encoding of objects)
+    pub compression: TileCompression,
+    client: S3Client,
+}
+
+/// Parse HTTP date of `Last-Modified` header, e.g. "Wed, 21 Oct 2015 07:28:00 GMT"
+pub(crate) fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let tm = time::strptime(date, "%a, %d %b %Y %H:%M:%S GMT").ok()?;
+    let secs = tm.to_timespec().sec;
+    if secs < 0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+fn s3_error<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+impl S3Cache {
+    /// Create S3 cache. Without credentials, they are read from the environment
+    /// (AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY) or the instance profile.
+    pub fn new(
+        bucket: &str,
+        prefix: Option<String>,
+        region: Option<String>,
+        endpoint: Option<String>,
+        credentials: Option<(String, String)>,
+        baseurl: Option<String>,
+    ) -> Result<S3Cache, String> {
+        let region_name = region.unwrap_or("us-east-1".to_string());
+        let region = match endpoint {
+            Some(endpoint) => Region::Custom {
+                name: region_name,
+                endpoint,
+            },
+            None => region_name.parse::<Region>().map_err(|e| e.to_string())?,
+        };
+        let client = match credentials {
+            Some((access_key, secret_key)) => S3Client::new_with(
+                HttpClient::new().map_err(|e| e.to_string())?,
+                StaticProvider::new_minimal(access_key, secret_key),
+                region,
+            ),
+            None => S3Client::new(region),
+        };
+        Ok(S3Cache {
+            bucket: bucket.to_string(),
+            prefix,
+            baseurl,
+            compression: TileCompression::default(),
+            client,
+        })
+    }
+    /// Object key of cache path
+    pub fn key(&self, path: &str) -> String {
+        match self.prefix {
+            Some(ref prefix) if !prefix.is_empty() => {
+                format!("{}/{}", prefix.trim_right_matches('/'), path)
+            }
+            _ => path.to_string(),
+        }
+    }
+    fn head(&self, path: &str) -> Option<CacheEntryMetadata> {
+        let request = HeadObjectRequest {
+            bucket: self.bucket.clone(),
+            key: self.key(path),
+            ..Default::default()
+        };
+        self.client
+            .head_object(request)
+            .sync()
+            .ok()
+            .map(|head| CacheEntryMetadata {
+                size: head.content_length.unwrap_or(0) as u64,
+                modified: head
+                    .last_modified
+                    .as_ref()
+                    .and_then(|date| parse_http_date(date)),
+            })
+    }
+    fn delete(&self, key: String) -> Result<(), io::Error> {
+        let request = DeleteObjectRequest {
+            bucket: self.bucket.clone(),
+            key,
+            ..Default::default()
+        };
+        self.client
+            .delete_object(request)
+            .sync()
+            .map(|_| ())
+            .map_err(s3_error)
+    }
+}
+
+impl Cache for S3Cache {
+    fn info(&self) -> String {
+        format!("Tile cache S3 bucket: {}/{}", self.bucket, self.key(""))
+    }
+    fn baseurl(&self) -> String {
+        self.baseurl
+            .clone()
+            .unwrap_or("http://localhost:6767".to_string())
+    }
+    fn read(&self, path: &str, read: &mut FnMut(&mut Read)) -> bool {
+        debug!("S3Cache.read {}", self.key(path));
+        let request = GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key: self.key(path),
+            ..Default::default()
+        };
+        match self.client.get_object(request).sync() {
+            Ok(output) => match output.body {
+                Some(body) => {
+                    read(&mut body.into_blocking_read());
+                    true
+                }
+                None => false,
+            },
+            Err(_e) => false,
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+        debug!("S3Cache.write {}", self.key(path));
+        // Tiles are stored compressed
+        let (content_type, content_encoding) = if path.ends_with(".pbf") {
+            let encoding = self.compression.content_encoding();
+            ("application/x-protobuf", encoding.map(|e| e.to_string()))
+        } else if path.ends_with(".json") {
+            ("application/json", None)
+        } else {
+            ("application/octet-stream", None)
+        };
+        let request = PutObjectRequest {
+            bucket: self.bucket.clone(),
+            key: self.key(path),
+            body: Some(obj.to_vec().into()),
+            content_type: Some(content_type.to_string()),
+            content_encoding,
+            ..Default::default()
+        };
+        self.client
+            .put_object(request)
+            .sync()
+            .map(|_| ())
+            .map_err(s3_error)
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.head(path).is_some()
+    }
+    fn purge(&self, path: &str) -> Result<(), io::Error> {
+        debug!("S3Cache.purge {}", self.key(path));
+        if self.exists(path) {
+            return self.delete(self.key(path));
+        }
+        // Delete all objects below path
+        let prefix = format!("{}/", self.key(path).trim_right_matches('/'));
+        let mut continuation_token = None;
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: Some(prefix.clone()),
+                continuation_token: continuation_token.clone(),
+                ..Default::default()
+            };
+            let list = self
+                .client
+                .list_objects_v2(request)
+                .sync()
+                .map_err(s3_error)?;
+            for object in list.contents.unwrap_or_default() {
+                if let Some(key) = object.key {
+                    self.delete(key)?;
+                }
+            }
+            continuation_token = list.next_continuation_token;
+            if !list.is_truncated.unwrap_or(false) || continuation_token.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    fn metadata(&self, path: &str) -> Option<CacheEntryMetadata> {
+        self.head(path)
+    }
+}
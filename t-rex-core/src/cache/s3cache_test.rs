@@ -0,0 +1,42 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use cache::cache::Cache;
+use cache::s3cache::S3Cache;
+
+#[test]
+fn test_s3_keys() {
+    let cache = S3Cache::new(
+        "tiles",
+        Some("t-rex/".to_string()),
+        None,
+        Some("http://localhost:9000".to_string()),
+        Some(("minio".to_string(), "minio123".to_string())),
+        None,
+    )
+    .unwrap();
+    assert_eq!(cache.key("osm/6/33/22.pbf"), "t-rex/osm/6/33/22.pbf");
+    assert_eq!(cache.info(), "Tile cache S3 bucket: tiles/t-rex/");
+
+    let cache = S3Cache::new("tiles", None, None, None, None, None).unwrap();
+    assert_eq!(cache.key("osm/6/33/22.pbf"), "osm/6/33/22.pbf");
+    assert!(S3Cache::new("tiles", None, Some("nowhere".to_string()), None, None, None).is_err());
+}
+
+#[test]
+fn test_last_modified() {
+    use cache::s3cache::parse_http_date;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    assert_eq!(
+        parse_http_date("Thu, 01 Jan 1970 00:01:00 GMT"),
+        Some(UNIX_EPOCH + Duration::from_secs(60))
+    );
+    assert_eq!(
+        parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(UNIX_EPOCH + Duration::from_secs(1445412480))
+    );
+    assert_eq!(parse_http_date("2015-10-21T07:28:00Z"), None);
+}
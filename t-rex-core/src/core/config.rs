@@ -6,8 +6,11 @@
 use core::grid::Extent;
 use serde::Deserialize;
 use std;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
+use toml;
+use toml::value::Table;
 use toml::Value;
 
 pub trait Config<'a, C: Deserialize<'a>>
@@ -43,6 +46,13 @@ pub struct ServiceCfg {
 #[derive(Deserialize, Debug)]
 pub struct ServiceMvtCfg {
     pub viewer: bool,
+    /// Polygon ring orientation: cw (MVT specification) or ccw (Default: cw)
+    pub winding_order: Option<String>,
+    /// External command for postprocessing rendered tiles
+    pub postprocess: Option<String>,
+    /// Log a warning with query, processing and encoding times for tiles
+    /// taking longer to render (milliseconds)
+    pub slow_tile_ms: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -51,15 +61,47 @@ pub struct DatasourceCfg {
     pub default: Option<bool>,
     // Postgis
     pub dbconn: Option<String>,
+    /// Maximum number of pooled connections (Default: 10)
     pub pool: Option<u16>,
+    /// Minimum number of idle pooled connections (Default: pool size)
+    pub pool_min: Option<u16>,
+    /// TLS mode: disable, allow, prefer or require (Default: allow)
+    pub sslmode: Option<String>,
+    /// Root certificate file (PEM or DER) for verifying the server certificate
+    pub sslrootcert: Option<String>,
+    /// Client certificate and key file (PKCS #12).
+    /// PEM files can be converted with `openssl pkcs12 -export`.
+    pub sslcert: Option<String>,
+    /// Password of client certificate file
+    pub sslpassword: Option<String>,
     // GDAL
     pub path: Option<String>,
+    // GeoPackage
+    /// GeoPackage file read without GDAL
+    pub gpkg: Option<String>,
+    // Plugin
+    /// Command line of out-of-process datasource
+    pub plugin: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct GridCfg {
     pub predefined: Option<String>,
     pub user: Option<UserGridCfg>,
+    /// Y-axis direction of tile URLs: xyz (top-left origin) or tms (bottom-left origin)
+    pub tile_scheme: Option<String>,
+    /// Additional grids for tilesets published on several grids
+    #[serde(default)]
+    pub additional: Vec<NamedGridCfg>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NamedGridCfg {
+    /// Grid name used in tile URLs
+    pub name: String,
+    pub predefined: Option<String>,
+    pub user: Option<UserGridCfg>,
+    pub tile_scheme: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -102,16 +144,39 @@ pub struct TilesetCfg {
     pub layers: Vec<LayerCfg>,
     // Inline style
     pub style: Option<Value>,
-    pub cache_limits: Option<TilesetCacheCfg>
+    pub cache_limits: Option<TilesetCacheCfg>,
+    /// Cache-Control max-age of tile responses in seconds (overrides webserver setting)
+    pub cache_control_max_age: Option<u32>,
+    /// Names of additional grids
+    #[serde(default)]
+    pub grids: Vec<String>,
+    /// Default MVT extent of layers (Default: 4096)
+    pub tile_size: Option<u32>,
+    /// Logical tile size in pixels, e.g. 512 for high-DPI clients (Default: grid tile size)
+    pub pixel_size: Option<u16>,
+    /// Attribute used as key of UTFGrid interaction tiles
+    pub utfgrid_key: Option<String>,
+    /// Mapbox GL style file served instead of the generated style
+    pub style_file: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct LayerQueryCfg {
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
+    /// Table name for this zoom range (e.g. generalized table)
+    pub table_name: Option<String>,
     pub sql: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct LayerAttributesCfg {
+    pub minzoom: Option<u8>,
+    pub maxzoom: Option<u8>,
+    /// Attributes included in tiles of this zoom range
+    pub fields: Vec<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct LayerCfg {
     pub name: String,
@@ -123,14 +188,25 @@ pub struct LayerCfg {
     /// Handle geometry like one in grid SRS
     pub no_transform: Option<bool>,
     pub fid_field: Option<String>,
+    /// Timestamp column of the last modification, for seeding changed tiles only
+    pub modified_field: Option<String>,
     // Input for derived queries
     pub table_name: Option<String>,
     pub query_limit: Option<u32>,
+    /// Feature order within the layer (SQL ORDER BY expression, e.g. "z_order" or "area DESC")
+    pub sort_by: Option<String>,
     // Explicit queries
     #[serde(default)]
     pub query: Vec<LayerQueryCfg>,
+    /// Zoom dependent attribute selection
+    #[serde(default)]
+    pub attributes: Vec<LayerAttributesCfg>,
+    /// Keys of JSONB/HSTORE columns added as attributes (Default: all keys)
+    pub json_keys: Option<Vec<String>>,
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
+    /// Query data intended for zoom level z+zoom_offset (Default: 0)
+    pub zoom_offset: Option<i8>,
     /// Width and height of the tile (Default: 4096. Grid default size is 256)
     pub tile_size: Option<u32>,
     /// Simplify geometry (lines and polygons)
@@ -141,6 +217,16 @@ pub struct LayerCfg {
     pub buffer_size: Option<u32>,
     /// Fix invalid geometries before clipping (lines and polygons)
     pub make_valid: Option<bool>,
+    /// Handling of invalid geometries: skip, warn or repair (Default: no check)
+    pub validity: Option<String>,
+    /// Remove repeated features within a tile by fid or geometry
+    pub dedup: Option<String>,
+    /// Split features into separate MVT layers by attribute value
+    pub split_by: Option<String>,
+    /// Write features into the MVT layer with this name (shared with other layers)
+    pub merge_into: Option<String>,
+    /// Attribute holding the source layer name of merged features (Default: layer)
+    pub merge_attribute: Option<String>,
     // Inline style
     pub style: Option<Value>,
 }
@@ -149,12 +235,19 @@ pub struct LayerCfg {
 pub struct TilesetCacheCfg {
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
-    pub no_cache: Option<bool>
+    pub no_cache: Option<bool>,
+    /// Seconds after which cached tiles are re-rendered
+    pub ttl: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct CacheCfg {
-    pub file: CacheFileCfg,
+    /// Compression of cached tiles: gzip or brotli (Default: gzip)
+    pub compression: Option<String>,
+    pub file: Option<CacheFileCfg>,
+    pub s3: Option<CacheS3Cfg>,
+    pub redis: Option<CacheRedisCfg>,
+    pub memory: Option<CacheMemoryCfg>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -163,16 +256,77 @@ pub struct CacheFileCfg {
     pub baseurl: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct CacheMemoryCfg {
+    /// Maximal size of cached tiles in MB (Default: 64 without `max_tiles`)
+    pub max_mb: Option<u32>,
+    /// Maximal number of cached tiles
+    pub max_tiles: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CacheRedisCfg {
+    /// Connection URL, e.g. `redis://127.0.0.1/`
+    pub url: String,
+    /// Key prefix of cached tiles
+    pub prefix: Option<String>,
+    /// Expiration of cached tiles in seconds (Default: no expiration)
+    pub ttl: Option<u64>,
+    pub baseurl: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CacheS3Cfg {
+    pub bucket: String,
+    /// Key prefix of cached objects
+    pub prefix: Option<String>,
+    pub region: Option<String>,
+    /// Endpoint of S3 compatible service
+    pub endpoint: Option<String>,
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub baseurl: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct WebserverCfg {
     pub bind: Option<String>,
     pub port: Option<u16>,
+    /// Public base URL used in generated URLs, e.g. `https://example.com/tiles`
+    /// (Default: from request and `Forwarded` / `X-Forwarded-*` headers)
+    pub public_url: Option<String>,
+    /// URL path prefix of all routes, e.g. `/tiles`
+    pub path_prefix: Option<String>,
+    /// Number of worker threads (Default: number of CPUs)
     pub threads: Option<u8>,
+    /// TLS certificate chain file (PEM). Enables HTTPS together with `tls_key`.
+    pub tls_cert: Option<String>,
+    /// TLS private key file (PEM, PKCS8 or RSA)
+    pub tls_key: Option<String>,
     // Cache-Control headers set by web server
     // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cache-Control#Expiration
     pub cache_control_max_age: Option<u32>,
+    /// Response to tile requests without features: `no_content` (HTTP 204, Default),
+    /// `empty` (empty MVT) or `not_found` (HTTP 404)
+    pub empty_tile: Option<String>,
+    /// Allowed origins of cross-origin requests (Default: `["*"]`)
+    pub cors_origins: Option<Vec<String>>,
+    /// Bearer token for the admin API (disabled if not set)
+    pub admin_token: Option<String>,
+    /// Serve Prometheus metrics at `/metrics`, requiring the `admin_token` if set (Default: false)
+    pub metrics: Option<bool>,
     #[serde(rename = "static", default)]
     pub static_: Vec<WebserverStaticCfg>,
+    /// Directory served under `/static`
+    pub static_dir: Option<String>,
+    /// Directory with files overriding the embedded viewer files (e.g. `index.html`)
+    pub viewer_dir: Option<String>,
+    /// JWT validation of tile requests (disabled if not set)
+    pub jwt: Option<WebserverJwtCfg>,
+    /// Request rate limit per client (disabled if not set)
+    pub rate_limit: Option<WebserverRateLimitCfg>,
+    /// Request log format `text` (Default) or `json`
+    pub log_format: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -181,6 +335,66 @@ pub struct WebserverStaticCfg {
     pub dir: String,
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct WebserverJwtCfg {
+    /// Shared secret of HMAC signed tokens (HS256, HS384, HS512)
+    pub secret: Option<String>,
+    /// URL of JSON Web Key Set with RSA keys (RS256, RS384, RS512)
+    pub jwks_url: Option<String>,
+    /// Required claim values, e.g. `aud = "t-rex"`
+    #[serde(default)]
+    pub claims: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WebserverRateLimitCfg {
+    /// Sustained number of requests per second
+    pub requests_per_second: f64,
+    /// Maximal number of requests in a burst (Default: `requests_per_second`)
+    pub burst: Option<u32>,
+    /// Identify clients by `ip` (Default) or `api_key` (`X-API-Key` header or `api_key` parameter)
+    pub client_key: Option<String>,
+    /// Known API keys with `client_key = "api_key"`. Requests with other keys are limited per IP.
+    pub api_keys: Option<Vec<String>>,
+}
+
+impl WebserverCfg {
+    /// Socket address from `bind` and `port` (Default: 127.0.0.1:6767)
+    ///
+    /// IPv6 addresses are enclosed in brackets, e.g. `[::]:6767`.
+    pub fn bind_address(&self) -> String {
+        let host = self
+            .bind
+            .as_ref()
+            .map(|h| h.as_str())
+            .unwrap_or("127.0.0.1");
+        let port = self.port.unwrap_or(6767);
+        if host.contains(':') && !host.starts_with('[') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        }
+    }
+    /// Normalized `path_prefix` with leading and without trailing slash (Default: empty)
+    pub fn path_prefix(&self) -> String {
+        match self.path_prefix.as_ref().map(|p| p.trim_matches('/')) {
+            Some(prefix) if !prefix.is_empty() => format!("/{}", prefix),
+            _ => String::new(),
+        }
+    }
+    /// Static file directories including `static_dir`
+    pub fn static_dirs(&self) -> Vec<WebserverStaticCfg> {
+        let mut static_dirs = self.static_.clone();
+        if let Some(ref dir) = self.static_dir {
+            static_dirs.push(WebserverStaticCfg {
+                path: "/static".to_string(),
+                dir: dir.clone(),
+            });
+        }
+        static_dirs
+    }
+}
+
 pub const DEFAULT_CONFIG: &'static str = r#"
 [service.mvt]
 viewer = true
@@ -227,3 +441,138 @@ pub fn parse_config<'a, T: Deserialize<'a>>(config_toml: String, path: &str) ->
         .and_then(|cfg| cfg.try_into::<T>())
         .map_err(|err| format!("{} - {}", path, err))
 }
+
+/// Insert TOML lines at the end of the layer section with the given name.
+pub fn insert_layer_config(config_toml: &str, layer_name: &str, toml: &str) -> String {
+    let name_line = format!(r#"name = "{}""#, layer_name);
+    let mut lines = Vec::new();
+    let mut in_layer = false;
+    let mut found = false;
+    let mut inserted = false;
+    for line in config_toml.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            let sublayer = trimmed.starts_with("[[tileset.layer.");
+            if found && !inserted && !sublayer {
+                lines.push(toml.trim_right());
+                lines.push("");
+                inserted = true;
+            }
+            if !sublayer {
+                in_layer = trimmed == "[[tileset.layer]]";
+            }
+        } else if in_layer && trimmed == name_line {
+            found = true;
+        }
+        lines.push(line);
+    }
+    if found && !inserted {
+        lines.push(toml.trim_right());
+    }
+    lines.join("\n") + "\n"
+}
+
+fn parse_table(toml: &str) -> Result<Table, String> {
+    match toml.parse::<Value>() {
+        Ok(Value::Table(table)) => Ok(table),
+        Ok(_) => Err("Table expected".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn tilesets_mut(config: &mut Value) -> Result<&mut Vec<Value>, String> {
+    config
+        .as_table_mut()
+        .ok_or("Invalid configuration".to_string())?
+        .entry("tileset".to_string())
+        .or_insert(Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or("Invalid tileset configuration".to_string())
+}
+
+fn has_name(entry: &Value, name: &str) -> bool {
+    entry.get("name").and_then(|n| n.as_str()) == Some(name)
+}
+
+/// Serialize and validate modified configuration
+fn validated_config(config: &Value) -> Result<String, String> {
+    let config_toml = toml::to_string(config).map_err(|err| err.to_string())?;
+    parse_config::<ApplicationCfg>(config_toml.clone(), "")?;
+    Ok(config_toml)
+}
+
+/// Add, update or remove (`toml` = None) a tileset.
+/// Layers of an updated tileset are kept.
+pub fn set_tileset_config(
+    config_toml: &str,
+    tileset_name: &str,
+    toml: Option<&str>,
+) -> Result<String, String> {
+    let mut config = config_toml
+        .parse::<Value>()
+        .map_err(|err| err.to_string())?;
+    {
+        let tilesets = tilesets_mut(&mut config)?;
+        let idx = tilesets.iter().position(|ts| has_name(ts, tileset_name));
+        match toml {
+            Some(toml) => {
+                let mut tileset = parse_table(toml)?;
+                tileset.insert("name".to_string(), Value::String(tileset_name.to_string()));
+                let layers = idx
+                    .and_then(|idx| tilesets[idx].get("layer").cloned())
+                    .unwrap_or(Value::Array(Vec::new()));
+                tileset.insert("layer".to_string(), layers);
+                match idx {
+                    Some(idx) => tilesets[idx] = Value::Table(tileset),
+                    None => tilesets.push(Value::Table(tileset)),
+                }
+            }
+            None => {
+                let idx = idx.ok_or(format!("Tileset '{}' not found", tileset_name))?;
+                tilesets.remove(idx);
+            }
+        }
+    }
+    validated_config(&config)
+}
+
+/// Add, update or remove (`toml` = None) a layer of an existing tileset.
+pub fn set_layer_config(
+    config_toml: &str,
+    tileset_name: &str,
+    layer_name: &str,
+    toml: Option<&str>,
+) -> Result<String, String> {
+    let mut config = config_toml
+        .parse::<Value>()
+        .map_err(|err| err.to_string())?;
+    {
+        let tileset = tilesets_mut(&mut config)?
+            .iter_mut()
+            .find(|ts| has_name(ts, tileset_name))
+            .ok_or(format!("Tileset '{}' not found", tileset_name))?;
+        let layers = tileset
+            .as_table_mut()
+            .ok_or("Invalid tileset configuration".to_string())?
+            .entry("layer".to_string())
+            .or_insert(Value::Array(Vec::new()))
+            .as_array_mut()
+            .ok_or("Invalid layer configuration".to_string())?;
+        let idx = layers.iter().position(|l| has_name(l, layer_name));
+        match toml {
+            Some(toml) => {
+                let mut layer = parse_table(toml)?;
+                layer.insert("name".to_string(), Value::String(layer_name.to_string()));
+                match idx {
+                    Some(idx) => layers[idx] = Value::Table(layer),
+                    None => layers.push(Value::Table(layer)),
+                }
+            }
+            None => {
+                let idx = idx.ok_or(format!("Layer '{}' not found", layer_name))?;
+                layers.remove(idx);
+            }
+        }
+    }
+    validated_config(&config)
+}
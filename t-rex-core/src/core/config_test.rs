@@ -26,6 +26,44 @@ fn test_load_config() {
     assert_eq!(config.webserver.port, Some(8080));
 }
 
+#[test]
+fn test_cache_config() {
+    use core::parse_config;
+    let toml = r#"
+        [service.mvt]
+        viewer = true
+
+        [[datasource]]
+        dbconn = "postgresql://pi@localhost/natural_earth_vectors"
+
+        [grid]
+        predefined = "web_mercator"
+
+        [[tileset]]
+        name = "points"
+
+        [[tileset.layer]]
+        name = "points"
+
+        [cache.s3]
+        bucket = "mvtcache"
+        prefix = "tiles"
+        endpoint = "http://localhost:9000"
+
+        [webserver]
+        bind = "127.0.0.1"
+        port = 6767
+        "#;
+    let config: ApplicationCfg = parse_config(toml.to_string(), "").unwrap();
+    let cache = config.cache.unwrap();
+    assert!(cache.file.is_none());
+    let s3 = cache.s3.unwrap();
+    assert_eq!(s3.bucket, "mvtcache");
+    assert_eq!(s3.prefix, Some("tiles".to_string()));
+    assert_eq!(s3.endpoint, Some("http://localhost:9000".to_string()));
+    assert_eq!(s3.access_key, None);
+}
+
 #[test]
 fn test_parse_error() {
     let config: Result<ApplicationCfg, _> = read_config("src/core/mod.rs");
@@ -45,6 +83,72 @@ fn test_default_config() {
     assert_eq!(config.webserver.port, Some(6767));
 }
 
+#[test]
+fn test_bind_address() {
+    use core::parse_config;
+    let mut config: ApplicationCfg = parse_config(DEFAULT_CONFIG.to_string(), "").unwrap();
+    assert_eq!(config.webserver.bind_address(), "127.0.0.1:6767");
+    config.webserver.bind = Some("0.0.0.0".to_string());
+    config.webserver.port = Some(8080);
+    assert_eq!(config.webserver.bind_address(), "0.0.0.0:8080");
+    config.webserver.bind = Some("::".to_string());
+    assert_eq!(config.webserver.bind_address(), "[::]:8080");
+    config.webserver.bind = Some("[::1]".to_string());
+    assert_eq!(config.webserver.bind_address(), "[::1]:8080");
+}
+
+#[test]
+fn test_path_prefix() {
+    use core::parse_config;
+    let mut config: ApplicationCfg = parse_config(DEFAULT_CONFIG.to_string(), "").unwrap();
+    assert_eq!(config.webserver.path_prefix(), "");
+    config.webserver.path_prefix = Some("/".to_string());
+    assert_eq!(config.webserver.path_prefix(), "");
+    config.webserver.path_prefix = Some("tiles/".to_string());
+    assert_eq!(config.webserver.path_prefix(), "/tiles");
+    config.webserver.path_prefix = Some("/maps/tiles".to_string());
+    assert_eq!(config.webserver.path_prefix(), "/maps/tiles");
+}
+
+#[test]
+fn test_static_dirs() {
+    use core::parse_config;
+    let toml = r#"
+        [service.mvt]
+        viewer = true
+
+        [[datasource]]
+        dbconn = "postgresql://pi@localhost/natural_earth_vectors"
+
+        [grid]
+        predefined = "web_mercator"
+
+        [[tileset]]
+        name = "points"
+
+        [[tileset.layer]]
+        name = "points"
+
+        [webserver]
+        static_dir = "./public/"
+
+        [[webserver.static]]
+        path = "/viewer"
+        dir = "./viewer/"
+        "#;
+    let config: ApplicationCfg = parse_config(toml.to_string(), "").unwrap();
+    let static_dirs = config.webserver.static_dirs();
+    assert_eq!(static_dirs.len(), 2);
+    assert_eq!(
+        (static_dirs[0].path.as_str(), static_dirs[0].dir.as_str()),
+        ("/viewer", "./viewer/")
+    );
+    assert_eq!(
+        (static_dirs[1].path.as_str(), static_dirs[1].dir.as_str()),
+        ("/static", "./public/")
+    );
+}
+
 #[test]
 fn test_datasource_compatibility() {
     use core::parse_config;
@@ -81,3 +185,131 @@ fn test_datasource_compatibility() {
     // assert_eq!(config.datasource[0].dbconn,
     //            Some("postgresql://pi@localhost/natural_earth_vectors".to_string()));
 }
+
+#[test]
+fn test_insert_layer_config() {
+    use core::config::insert_layer_config;
+
+    let config = r#"[[tileset]]
+name = "osm"
+
+[[tileset.layer]]
+name = "roads"
+table_name = "roads"
+[[tileset.layer.query]]
+minzoom = 14
+sql = "SELECT * FROM roads"
+
+[[tileset.layer]]
+name = "buildings"
+
+[cache.file]
+base = "/tmp/mvtcache"
+"#;
+    let toml = r#"[[tileset.layer.query]]
+maxzoom = 8
+table_name = "roads_gen_z8"
+"#;
+    let expected = r#"[[tileset]]
+name = "osm"
+
+[[tileset.layer]]
+name = "roads"
+table_name = "roads"
+[[tileset.layer.query]]
+minzoom = 14
+sql = "SELECT * FROM roads"
+
+[[tileset.layer.query]]
+maxzoom = 8
+table_name = "roads_gen_z8"
+
+[[tileset.layer]]
+name = "buildings"
+
+[cache.file]
+base = "/tmp/mvtcache"
+"#;
+    assert_eq!(insert_layer_config(config, "roads", toml), expected);
+
+    // Last layer
+    let updated = insert_layer_config(config, "buildings", toml);
+    assert!(updated.contains(
+        r#"name = "buildings"
+
+[[tileset.layer.query]]
+maxzoom = 8
+table_name = "roads_gen_z8"
+
+[cache.file]"#
+    ));
+
+    // Unknown layer
+    assert_eq!(insert_layer_config(config, "water", toml), config);
+}
+
+#[test]
+fn test_set_layer_config() {
+    use core::config::{parse_config, set_layer_config, set_tileset_config};
+
+    let config = r#"
+[service.mvt]
+viewer = true
+
+[[datasource]]
+dbconn = ""
+
+[grid]
+predefined = "web_mercator"
+
+[[tileset]]
+name = "osm"
+
+[[tileset.layer]]
+name = "roads"
+table_name = "roads"
+
+[webserver]
+bind = "127.0.0.1"
+port = 6767
+"#;
+
+    let toml =
+        set_layer_config(config, "osm", "buildings", Some("table_name = \"buildings\"")).unwrap();
+    let cfg: ApplicationCfg = parse_config(toml.clone(), "").unwrap();
+    assert_eq!(cfg.tilesets[0].layers.len(), 2);
+    assert_eq!(cfg.tilesets[0].layers[1].name, "buildings");
+
+    let toml =
+        set_layer_config(&toml, "osm", "roads", Some("table_name = \"roads_gen\"")).unwrap();
+    let cfg: ApplicationCfg = parse_config(toml.clone(), "").unwrap();
+    assert_eq!(
+        cfg.tilesets[0].layers[0].table_name,
+        Some("roads_gen".to_string())
+    );
+
+    let toml = set_layer_config(&toml, "osm", "roads", None).unwrap();
+    let cfg: ApplicationCfg = parse_config(toml.clone(), "").unwrap();
+    assert_eq!(cfg.tilesets[0].layers.len(), 1);
+
+    assert_eq!(
+        set_layer_config(&toml, "osm", "roads", None),
+        Err("Layer 'roads' not found".to_string())
+    );
+    assert_eq!(
+        set_layer_config(&toml, "missing", "roads", Some("")),
+        Err("Tileset 'missing' not found".to_string())
+    );
+    assert!(set_layer_config(&toml, "osm", "roads", Some("minzoom = \"x\"")).is_err());
+
+    let toml = set_tileset_config(&toml, "osm", Some("maxzoom = 14")).unwrap();
+    let cfg: ApplicationCfg = parse_config(toml.clone(), "").unwrap();
+    assert_eq!(cfg.tilesets[0].maxzoom, Some(14));
+    assert_eq!(cfg.tilesets[0].layers.len(), 1);
+
+    let toml = set_tileset_config(&toml, "admin", Some("")).unwrap();
+    let toml = set_tileset_config(&toml, "osm", None).unwrap();
+    let cfg: ApplicationCfg = parse_config(toml, "").unwrap();
+    assert_eq!(cfg.tilesets.len(), 1);
+    assert_eq!(cfg.tilesets[0].name, "admin");
+}
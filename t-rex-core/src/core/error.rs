@@ -0,0 +1,53 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use std::error;
+use std::fmt;
+use std::result;
+
+/// t-rex error
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Datasource or source layer could not be opened
+    Datasource(String),
+    /// Feature query failed
+    Query(String),
+    /// Unknown tileset requested
+    UnknownTileset(String),
+    /// Tile coordinates outside of grid
+    InvalidTile(String),
+    /// Tile postprocessing failed
+    Postprocess(String),
+    /// Reading or writing the tile cache failed
+    Cache(String),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Datasource(ref msg) => write!(f, "Datasource error: {}", msg),
+            Error::Query(ref msg) => write!(f, "Query error: {}", msg),
+            Error::UnknownTileset(ref name) => write!(f, "Tileset '{}' not found", name),
+            Error::InvalidTile(ref tile) => write!(f, "Invalid tile {}", tile),
+            Error::Postprocess(ref msg) => write!(f, "Postprocess error: {}", msg),
+            Error::Cache(ref msg) => write!(f, "Cache error: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Datasource(_) => "datasource error",
+            Error::Query(_) => "query error",
+            Error::UnknownTileset(_) => "tileset not found",
+            Error::InvalidTile(_) => "invalid tile",
+            Error::Postprocess(_) => "postprocess error",
+            Error::Cache(_) => "cache error",
+        }
+    }
+}
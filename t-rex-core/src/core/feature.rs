@@ -4,6 +4,7 @@
 //
 
 use core::geom::GeometryType;
+use std::fmt;
 
 /// Supported feature attribute value types
 #[derive(Clone, PartialEq, Debug)]
@@ -17,6 +18,36 @@ pub enum FeatureAttrValType {
     Bool(bool),
 }
 
+impl fmt::Display for FeatureAttrValType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FeatureAttrValType::String(ref v) => write!(f, "{}", v),
+            &FeatureAttrValType::Float(v) => write!(f, "{}", v),
+            &FeatureAttrValType::Double(v) => write!(f, "{}", v),
+            &FeatureAttrValType::Int(v) => write!(f, "{}", v),
+            &FeatureAttrValType::UInt(v) => write!(f, "{}", v),
+            &FeatureAttrValType::SInt(v) => write!(f, "{}", v),
+            &FeatureAttrValType::Bool(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl FeatureAttrValType {
+    /// Feature id of integer values, including integral floats and numeric strings
+    /// (e.g. NUMERIC or bigint columns converted by the datasource)
+    pub fn as_fid(&self) -> Option<u64> {
+        match self {
+            &FeatureAttrValType::Int(v) | &FeatureAttrValType::SInt(v) if v >= 0 => Some(v as u64),
+            &FeatureAttrValType::UInt(v) => Some(v),
+            &FeatureAttrValType::Double(v) if v >= 0.0 && v.fract() == 0.0 && v < 1.8e19 => {
+                Some(v as u64)
+            }
+            &FeatureAttrValType::String(ref v) => v.trim().parse::<u64>().ok(),
+            _ => None,
+        }
+    }
+}
+
 pub trait Feature {
     fn fid(&self) -> Option<u64>;
     fn attributes(&self) -> Vec<FeatureAttr>; //TODO: return tuples
@@ -47,3 +78,34 @@ impl Feature for FeatureStruct {
         Ok(self.geometry.clone())
     }
 }
+
+/// Feature with additional and filtered attributes
+pub struct ExtendedFeature<'a> {
+    pub feature: &'a Feature,
+    /// Additional attributes
+    pub attributes: Vec<FeatureAttr>,
+    /// Attributes to keep (None: all attributes)
+    pub fields: Option<&'a Vec<String>>,
+    /// Replacement geometry (e.g. repaired)
+    pub geometry: Option<GeometryType>,
+}
+
+impl<'a> Feature for ExtendedFeature<'a> {
+    fn fid(&self) -> Option<u64> {
+        self.feature.fid()
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        let mut attrs = self.feature.attributes();
+        if let Some(fields) = self.fields {
+            attrs.retain(|attr| fields.contains(&attr.key));
+        }
+        attrs.extend(self.attributes.iter().cloned());
+        attrs
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        match self.geometry {
+            Some(ref geom) => Ok(geom.clone()),
+            None => self.feature.geometry(),
+        }
+    }
+}
@@ -3,7 +3,9 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use core::grid::Extent;
 use postgis::ewkb;
+use std::mem;
 
 // Aliases for rust-postgis geometry types
 pub type Point = ewkb::Point;
@@ -26,11 +28,43 @@ pub enum GeometryType {
     GeometryCollection(GeometryCollection),
 }
 
+fn clone_points(points: &Vec<Point>) -> Vec<Point> {
+    points.iter().map(|p| Point::new(p.x, p.y, p.srid)).collect()
+}
+
+fn clone_line(line: &LineString) -> LineString {
+    LineString {
+        points: clone_points(&line.points),
+        srid: line.srid,
+    }
+}
+
+fn clone_polygon(polygon: &Polygon) -> Polygon {
+    Polygon {
+        rings: polygon.rings.iter().map(clone_line).collect(),
+        srid: polygon.srid,
+    }
+}
+
 impl Clone for GeometryType {
     fn clone(&self) -> Self {
         match self {
             &GeometryType::Point(ref p) => GeometryType::Point(Point::new(p.x, p.y, None)),
-            _ => panic!("Not implemented yet"), // TODO: either implement other types or don't clone (FeatureStruct)...
+            &GeometryType::LineString(ref l) => GeometryType::LineString(clone_line(l)),
+            &GeometryType::Polygon(ref p) => GeometryType::Polygon(clone_polygon(p)),
+            &GeometryType::MultiPoint(ref p) => GeometryType::MultiPoint(MultiPoint {
+                points: clone_points(&p.points),
+                srid: p.srid,
+            }),
+            &GeometryType::MultiLineString(ref l) => GeometryType::MultiLineString(MultiLineString {
+                lines: l.lines.iter().map(clone_line).collect(),
+                srid: l.srid,
+            }),
+            &GeometryType::MultiPolygon(ref p) => GeometryType::MultiPolygon(MultiPolygon {
+                polygons: p.polygons.iter().map(clone_polygon).collect(),
+                srid: p.srid,
+            }),
+            _ => panic!("Not implemented yet"), // TODO: either implement GeometryCollection or don't clone (FeatureStruct)...
         }
     }
 }
@@ -46,4 +80,378 @@ impl GeometryType {
             _ => false,
         }
     }
+    /// Check for degenerated lines, unclosed rings and self-intersecting rings
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            &GeometryType::LineString(ref l) => validate_line(l),
+            &GeometryType::MultiLineString(ref ml) => {
+                ml.lines.iter().map(validate_line).collect()
+            }
+            &GeometryType::Polygon(ref p) => validate_polygon(p),
+            &GeometryType::MultiPolygon(ref mp) => {
+                mp.polygons.iter().map(validate_polygon).collect()
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Close unclosed rings and remove degenerated lines and rings
+    pub fn repair(&mut self) {
+        match self {
+            &mut GeometryType::LineString(ref mut l) => {
+                if l.points.len() < 2 {
+                    l.points.clear();
+                }
+            }
+            &mut GeometryType::MultiLineString(ref mut ml) => {
+                ml.lines.retain(|l| l.points.len() >= 2);
+            }
+            &mut GeometryType::Polygon(ref mut p) => repair_polygon(p),
+            &mut GeometryType::MultiPolygon(ref mut mp) => {
+                for p in mp.polygons.iter_mut() {
+                    repair_polygon(p);
+                }
+                mp.polygons.retain(|p| !p.rings.is_empty());
+            }
+            _ => {}
+        }
+    }
+    /// Douglas-Peucker simplification of lines and polygon rings.
+    /// Degenerated lines and rings are removed.
+    pub fn simplify(&mut self, tolerance: f64) {
+        match self {
+            &mut GeometryType::LineString(ref mut l) => simplify_line(l, tolerance),
+            &mut GeometryType::MultiLineString(ref mut ml) => {
+                for l in ml.lines.iter_mut() {
+                    simplify_line(l, tolerance);
+                }
+            }
+            &mut GeometryType::Polygon(ref mut p) => {
+                for r in p.rings.iter_mut() {
+                    simplify_line(r, tolerance);
+                }
+            }
+            &mut GeometryType::MultiPolygon(ref mut mp) => {
+                for p in mp.polygons.iter_mut() {
+                    for r in p.rings.iter_mut() {
+                        simplify_line(r, tolerance);
+                    }
+                }
+            }
+            _ => return,
+        }
+        self.repair();
+    }
+    /// Clip lines and polygons to extent. Points outside of a MultiPoint are removed.
+    /// Polygon rings are clipped with the Sutherland-Hodgman algorithm, which can leave
+    /// degenerated edges along the extent border.
+    pub fn clip(&mut self, extent: &Extent) {
+        let clipped = match self {
+            &mut GeometryType::LineString(ref l) => {
+                let mut lines = clip_line(l, extent);
+                if lines.len() == 1 {
+                    GeometryType::LineString(lines.pop().unwrap())
+                } else {
+                    GeometryType::MultiLineString(MultiLineString {
+                        lines: lines,
+                        srid: l.srid,
+                    })
+                }
+            }
+            &mut GeometryType::MultiLineString(ref ml) => {
+                GeometryType::MultiLineString(MultiLineString {
+                    lines: ml.lines.iter().flat_map(|l| clip_line(l, extent)).collect(),
+                    srid: ml.srid,
+                })
+            }
+            &mut GeometryType::Polygon(ref p) => GeometryType::Polygon(clip_polygon(p, extent)),
+            &mut GeometryType::MultiPolygon(ref mp) => GeometryType::MultiPolygon(MultiPolygon {
+                polygons: mp
+                    .polygons
+                    .iter()
+                    .map(|p| clip_polygon(p, extent))
+                    .collect(),
+                srid: mp.srid,
+            }),
+            &mut GeometryType::MultiPoint(ref mut mp) => {
+                mp.points.retain(|p| {
+                    p.x >= extent.minx
+                        && p.x <= extent.maxx
+                        && p.y >= extent.miny
+                        && p.y <= extent.maxy
+                });
+                return;
+            }
+            _ => return,
+        };
+        *self = clipped;
+        self.repair();
+    }
+}
+
+/// Split line into parts within extent
+fn clip_line(line: &LineString, extent: &Extent) -> Vec<LineString> {
+    fn finish_part(points: &mut Vec<Point>, lines: &mut Vec<LineString>, srid: Option<i32>) {
+        if points.len() >= 2 {
+            lines.push(LineString {
+                points: mem::replace(points, Vec::new()),
+                srid: srid,
+            });
+        } else {
+            points.clear();
+        }
+    }
+    let mut lines = Vec::new();
+    let mut points: Vec<Point> = Vec::new();
+    for segment in line.points.windows(2) {
+        match clip_segment(&segment[0], &segment[1], extent) {
+            Some((start, end)) => {
+                let connected = points.last().map_or(false, |last| same_point(last, &start));
+                if !connected {
+                    finish_part(&mut points, &mut lines, line.srid);
+                    points.push(start);
+                }
+                let leaves_extent = !same_point(&end, &segment[1]);
+                points.push(end);
+                if leaves_extent {
+                    finish_part(&mut points, &mut lines, line.srid);
+                }
+            }
+            None => finish_part(&mut points, &mut lines, line.srid),
+        }
+    }
+    finish_part(&mut points, &mut lines, line.srid);
+    lines
+}
+
+/// Liang-Barsky segment clipping
+fn clip_segment(p0: &Point, p1: &Point, extent: &Extent) -> Option<(Point, Point)> {
+    let (dx, dy) = (p1.x - p0.x, p1.y - p0.y);
+    let mut t0 = 0.0f64;
+    let mut t1 = 1.0f64;
+    for &(p, q) in &[
+        (-dx, p0.x - extent.minx),
+        (dx, extent.maxx - p0.x),
+        (-dy, p0.y - extent.miny),
+        (dy, extent.maxy - p0.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                if r > t1 {
+                    return None;
+                }
+                t0 = t0.max(r);
+            } else {
+                if r < t0 {
+                    return None;
+                }
+                t1 = t1.min(r);
+            }
+        }
+    }
+    // Keep original points for exact comparison of unclipped segment ends
+    let point = |t: f64| {
+        if t == 0.0 {
+            Point::new(p0.x, p0.y, p0.srid)
+        } else if t == 1.0 {
+            Point::new(p1.x, p1.y, p1.srid)
+        } else {
+            Point::new(p0.x + t * dx, p0.y + t * dy, p0.srid)
+        }
+    };
+    Some((point(t0), point(t1)))
+}
+
+fn clip_polygon(polygon: &Polygon, extent: &Extent) -> Polygon {
+    Polygon {
+        rings: polygon.rings.iter().map(|r| clip_ring(r, extent)).collect(),
+        srid: polygon.srid,
+    }
+}
+
+/// Sutherland-Hodgman ring clipping
+fn clip_ring(ring: &LineString, extent: &Extent) -> LineString {
+    let mut points = clone_points(&ring.points);
+    if points.len() > 1 && same_point(&points[0], &points[points.len() - 1]) {
+        points.pop();
+    }
+    for edge in 0..4 {
+        if points.is_empty() {
+            break;
+        }
+        let inside = |p: &Point| match edge {
+            0 => p.x >= extent.minx,
+            1 => p.x <= extent.maxx,
+            2 => p.y >= extent.miny,
+            _ => p.y <= extent.maxy,
+        };
+        let intersection = |a: &Point, b: &Point| {
+            let t = match edge {
+                0 => (extent.minx - a.x) / (b.x - a.x),
+                1 => (extent.maxx - a.x) / (b.x - a.x),
+                2 => (extent.miny - a.y) / (b.y - a.y),
+                _ => (extent.maxy - a.y) / (b.y - a.y),
+            };
+            Point::new(a.x + t * (b.x - a.x), a.y + t * (b.y - a.y), a.srid)
+        };
+        let input = mem::replace(&mut points, Vec::new());
+        let mut prev = &input[input.len() - 1];
+        for p in &input {
+            match (inside(prev), inside(p)) {
+                (true, true) => points.push(Point::new(p.x, p.y, p.srid)),
+                (true, false) => points.push(intersection(prev, p)),
+                (false, true) => {
+                    points.push(intersection(prev, p));
+                    points.push(Point::new(p.x, p.y, p.srid));
+                }
+                (false, false) => {}
+            }
+            prev = p;
+        }
+    }
+    // Close ring
+    if let Some(first) = points.first().map(|p| Point::new(p.x, p.y, p.srid)) {
+        points.push(first);
+    }
+    LineString {
+        points: points,
+        srid: ring.srid,
+    }
+}
+
+fn simplify_line(line: &mut LineString, tolerance: f64) {
+    if line.points.len() < 3 {
+        return;
+    }
+    let last = line.points.len() - 1;
+    let mut keep = vec![false; line.points.len()];
+    keep[0] = true;
+    keep[last] = true;
+    let mut segments = vec![(0, last)];
+    while let Some((start, end)) = segments.pop() {
+        let mut dmax = 0.0;
+        let mut index = start;
+        for i in start + 1..end {
+            let d = segment_distance(&line.points[i], &line.points[start], &line.points[end]);
+            if d > dmax {
+                dmax = d;
+                index = i;
+            }
+        }
+        if dmax > tolerance {
+            keep[index] = true;
+            segments.push((start, index));
+            segments.push((index, end));
+        }
+    }
+    line.points = line
+        .points
+        .iter()
+        .zip(keep)
+        .filter(|&(_, k)| k)
+        .map(|(p, _)| Point::new(p.x, p.y, p.srid))
+        .collect();
+}
+
+/// Distance of point p to segment a-b
+fn segment_distance(p: &Point, a: &Point, b: &Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2)
+            .max(0.0)
+            .min(1.0)
+    };
+    let (x, y) = (a.x + t * dx - p.x, a.y + t * dy - p.y);
+    (x * x + y * y).sqrt()
+}
+
+fn validate_line(line: &LineString) -> Result<(), String> {
+    if line.points.len() < 2 {
+        return Err("Line with less than 2 points".to_string());
+    }
+    Ok(())
+}
+
+fn validate_polygon(polygon: &Polygon) -> Result<(), String> {
+    for ring in &polygon.rings {
+        let points = &ring.points;
+        if points.len() < 4 {
+            return Err("Ring with less than 4 points".to_string());
+        }
+        if !same_point(&points[0], &points[points.len() - 1]) {
+            return Err("Unclosed ring".to_string());
+        }
+        if is_self_intersecting(points) {
+            return Err("Self-intersecting ring".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn repair_polygon(polygon: &mut Polygon) {
+    for ring in polygon.rings.iter_mut() {
+        let unclosed = match (ring.points.first(), ring.points.last()) {
+            (Some(first), Some(last)) => !same_point(first, last),
+            _ => false,
+        };
+        if unclosed {
+            let first = Point::new(ring.points[0].x, ring.points[0].y, ring.points[0].srid);
+            ring.points.push(first);
+        }
+    }
+    // A polygon without valid exterior ring is dropped completely
+    if polygon.rings.first().map_or(false, |r| r.points.len() < 4) {
+        polygon.rings.clear();
+    }
+    polygon.rings.retain(|r| r.points.len() >= 4);
+}
+
+fn same_point(p1: &Point, p2: &Point) -> bool {
+    p1.x == p2.x && p1.y == p2.y
+}
+
+/// Check intersections of non-adjacent segments of a closed ring (O(n^2))
+fn is_self_intersecting(points: &Vec<Point>) -> bool {
+    let nseg = points.len() - 1;
+    for i in 0..nseg {
+        for j in i + 2..nseg {
+            if i == 0 && j == nseg - 1 {
+                // first and last segment share the closing point
+                continue;
+            }
+            if segments_intersect(&points[i], &points[i + 1], &points[j], &points[j + 1]) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn segments_intersect(p1: &Point, p2: &Point, q1: &Point, q2: &Point) -> bool {
+    fn orientation(a: &Point, b: &Point, c: &Point) -> f64 {
+        (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+    }
+    fn on_segment(a: &Point, b: &Point, p: &Point) -> bool {
+        p.x >= a.x.min(b.x) && p.x <= a.x.max(b.x) && p.y >= a.y.min(b.y) && p.y <= a.y.max(b.y)
+    }
+    let d1 = orientation(q1, q2, p1);
+    let d2 = orientation(q1, q2, p2);
+    let d3 = orientation(p1, p2, q1);
+    let d4 = orientation(p1, p2, q2);
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    (d1 == 0.0 && on_segment(q1, q2, p1))
+        || (d2 == 0.0 && on_segment(q1, q2, p2))
+        || (d3 == 0.0 && on_segment(p1, p2, q1))
+        || (d4 == 0.0 && on_segment(p1, p2, q2))
 }
@@ -24,3 +24,133 @@ fn test_geom_creation() {
     };
     assert_eq!(p.x, 960000.0);
 }
+
+#[cfg(test)]
+fn ring(coords: &[(f64, f64)]) -> ewkb::LineString {
+    ewkb::LineString {
+        points: coords.iter().map(|&(x, y)| Point::new(x, y, None)).collect(),
+        srid: None,
+    }
+}
+
+#[test]
+fn test_validate() {
+    let square = ring(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.), (0., 0.)]);
+    let polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![square],
+        srid: None,
+    });
+    assert!(polygon.validate().is_ok());
+
+    let bowtie = ring(&[(0., 0.), (10., 10.), (10., 0.), (0., 10.), (0., 0.)]);
+    let polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![bowtie],
+        srid: None,
+    });
+    assert_eq!(polygon.validate(), Err("Self-intersecting ring".to_string()));
+
+    let unclosed = ring(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.)]);
+    let mut polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![unclosed],
+        srid: None,
+    });
+    assert_eq!(polygon.validate(), Err("Unclosed ring".to_string()));
+    polygon.repair();
+    assert!(polygon.validate().is_ok());
+
+    let degenerated = ring(&[(0., 0.), (10., 0.)]);
+    let mut polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![degenerated],
+        srid: None,
+    });
+    assert!(polygon.validate().is_err());
+    polygon.repair();
+    assert!(polygon.is_empty());
+}
+
+#[test]
+fn test_simplify() {
+    let mut line = GeometryType::LineString(ring(&[(0., 0.), (5., 0.4), (10., 0.), (10., 10.)]));
+    line.simplify(0.5);
+    match line {
+        GeometryType::LineString(ref l) => assert_eq!(l.points.len(), 3),
+        _ => panic!(),
+    }
+
+    let square = ring(&[
+        (0., 0.),
+        (5., 0.1),
+        (10., 0.),
+        (10., 10.),
+        (0., 10.),
+        (0., 0.),
+    ]);
+    let small = ring(&[(1., 1.), (1.2, 1.), (1.2, 1.2), (1., 1.)]);
+    let mut polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![square, small],
+        srid: None,
+    });
+    polygon.simplify(1.0);
+    match polygon {
+        GeometryType::Polygon(ref p) => {
+            assert_eq!(p.rings.len(), 1);
+            assert_eq!(p.rings[0].points.len(), 5);
+        }
+        _ => panic!(),
+    }
+    assert!(polygon.validate().is_ok());
+
+    let mut polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![ring(&[(0., 0.), (1., 0.), (1., 1.), (0., 0.)])],
+        srid: None,
+    });
+    polygon.simplify(10.0);
+    assert!(polygon.is_empty());
+}
+
+#[test]
+fn test_clip() {
+    use core::grid::Extent;
+
+    let extent = Extent {
+        minx: 0.,
+        miny: 0.,
+        maxx: 10.,
+        maxy: 10.,
+    };
+
+    // Line leaving and reentering the extent
+    let mut line = GeometryType::LineString(ring(&[(-5., 5.), (5., 5.), (5., 15.), (8., 5.)]));
+    line.clip(&extent);
+    match line {
+        GeometryType::MultiLineString(ref ml) => {
+            assert_eq!(ml.lines.len(), 2);
+            let first: Vec<_> = ml.lines[0].points.iter().map(|p| (p.x, p.y)).collect();
+            assert_eq!(first, vec![(0., 5.), (5., 5.), (5., 10.)]);
+            assert_eq!(ml.lines[1].points.len(), 2);
+        }
+        _ => panic!(),
+    }
+
+    let mut line = GeometryType::LineString(ring(&[(20., 20.), (30., 30.)]));
+    line.clip(&extent);
+    assert!(line.is_empty());
+
+    let square = ring(&[(-5., -5.), (5., -5.), (5., 5.), (-5., 5.), (-5., -5.)]);
+    let mut polygon = GeometryType::Polygon(ewkb::Polygon {
+        rings: vec![square],
+        srid: None,
+    });
+    polygon.clip(&extent);
+    match polygon {
+        GeometryType::Polygon(ref p) => {
+            let points: Vec<_> = p.rings[0].points.iter().map(|p| (p.x, p.y)).collect();
+            assert_eq!(points.len(), 5);
+            assert!(points.contains(&(0., 0.)));
+            assert!(points.contains(&(5., 5.)));
+            assert!(points.iter().all(|&(x, y)| x >= 0. && y >= 0.));
+        }
+        _ => panic!(),
+    }
+    assert!(polygon.validate().is_ok());
+}
@@ -3,15 +3,16 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use core::config::GridCfg;
+use core::config::{GridCfg, NamedGridCfg, UserGridCfg};
 use core::enum_serializer::EnumString;
 use core::Config;
 use serde;
 use serde::de::{Deserialize, Deserializer};
 use std::f64::consts;
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(PartialEq, Deserialize, Clone, Debug)]
+#[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
 pub struct Extent {
     pub minx: f64,
     pub miny: f64,
@@ -19,6 +20,31 @@ pub struct Extent {
     pub maxy: f64,
 }
 
+impl FromStr for Extent {
+    type Err = String;
+
+    /// Parse extent from `minx,miny,maxx,maxy`
+    fn from_str(s: &str) -> Result<Extent, String> {
+        let arr = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid extent '{}'", s))?;
+        if arr.len() != 4 {
+            return Err(format!(
+                "Invalid extent '{}' - expected minx,miny,maxx,maxy",
+                s
+            ));
+        }
+        Ok(Extent {
+            minx: arr[0],
+            miny: arr[1],
+            maxx: arr[2],
+            maxy: arr[3],
+        })
+    }
+}
+
 /// Min and max grid cell numbers
 #[derive(PartialEq, Debug)]
 pub struct ExtentInt {
@@ -31,7 +57,7 @@ pub struct ExtentInt {
 // Max grid cell numbers
 type CellIndex = (u32, u32);
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Origin {
     TopLeft,
     BottomLeft, //TopRight, BottomRight
@@ -55,7 +81,7 @@ impl EnumString<Origin> for Origin {
 
 enum_string_serialization!(Origin OriginVisitor);
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Unit {
     Meters,
     Degrees,
@@ -82,8 +108,33 @@ impl EnumString<Unit> for Unit {
 
 enum_string_serialization!(Unit UnitVisitor);
 
+/// Y-axis direction of tile adressing
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TileScheme {
+    /// Top-left origin (Google, OSM)
+    Xyz,
+    /// Bottom-left origin (Tile Map Service)
+    Tms,
+}
+
+impl EnumString<TileScheme> for TileScheme {
+    fn from_str(val: &str) -> Result<TileScheme, String> {
+        match &val.to_lowercase() as &str {
+            "xyz" => Ok(TileScheme::Xyz),
+            "tms" => Ok(TileScheme::Tms),
+            _ => Err(format!("Unexpected enum value '{}'", val)),
+        }
+    }
+    fn as_str(&self) -> &'static str {
+        match *self {
+            TileScheme::Xyz => "xyz",
+            TileScheme::Tms => "tms",
+        }
+    }
+}
+
 // Credits: MapCache by Thomas Bonfort (http://mapserver.org/mapcache/)
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Grid {
     /// The width and height of an individual tile, in pixels.
     width: u16,
@@ -109,6 +160,8 @@ pub struct Grid {
     level_max: Vec<CellIndex>,
     /// Grid origin
     pub origin: Origin,
+    /// Adressing scheme of tile URLs and cache paths
+    pub tile_scheme: TileScheme,
 }
 
 impl Grid {
@@ -147,6 +200,7 @@ impl Grid {
             ],
             level_max: Vec::new(),
             origin: Origin::BottomLeft,
+            tile_scheme: TileScheme::Tms,
         };
         grid.level_max = grid.level_max();
         grid
@@ -193,17 +247,46 @@ impl Grid {
             ],
             level_max: Vec::new(),
             origin: Origin::BottomLeft,
+            tile_scheme: TileScheme::Xyz,
         };
         grid.level_max = grid.level_max();
         grid
     }
 
+    /// Web Mercator grid with 512x512 pixel tiles
+    ///
+    /// Tiles have the same extents as in the 256 pixel grid, but half the resolution per pixel.
+    pub fn web_mercator_512() -> Grid {
+        Grid::web_mercator().with_tile_size(512)
+    }
+
+    /// Grid with tiles of `size` pixels covering the same extents
+    ///
+    /// Resolutions are scaled, e.g. halved for 512 pixel tiles in a 256 pixel grid.
+    pub fn with_tile_size(&self, size: u16) -> Grid {
+        let mut grid = self.clone();
+        let factor = self.width as f64 / size as f64;
+        grid.width = size;
+        grid.height = (self.height as f64 / factor).round() as u16;
+        grid.resolutions = grid.resolutions.iter().map(|res| res * factor).collect();
+        grid.level_max = grid.level_max();
+        grid
+    }
+
+    /// Width and height of the tiles in pixels
+    pub fn tile_size(&self) -> u16 {
+        self.width
+    }
     pub fn nlevels(&self) -> u8 {
         self.resolutions.len() as u8
     }
     pub fn maxzoom(&self) -> u8 {
         self.nlevels() - 1
     }
+    /// Resolution of grid level in grid units per pixel
+    pub fn resolution(&self, zoom: u8) -> f64 {
+        self.resolutions[zoom as usize]
+    }
     pub fn pixel_width(&self, zoom: u8) -> f64 {
         const METERS_PER_DEGREE: f64 = 6378137.0 * 2.0 * consts::PI / 360.0;
         match self.units {
@@ -247,6 +330,20 @@ impl Grid {
         let y = maxy.saturating_sub(ytile).saturating_sub(1);
         y
     }
+    /// y tile in TMS adressing scheme from y tile in grid tile scheme and vice versa
+    pub fn ytile_tms(&self, ytile: u32, zoom: u8) -> u32 {
+        match self.tile_scheme {
+            TileScheme::Xyz => self.ytile_from_xyz(ytile, zoom),
+            TileScheme::Tms => ytile,
+        }
+    }
+    /// y tile in TMS adressing scheme from WMTS TileRow (counted from the top)
+    pub fn ytile_from_wmts(&self, row: u32, zoom: u8) -> u32 {
+        match self.origin {
+            Origin::BottomLeft => self.ytile_from_xyz(row, zoom),
+            Origin::TopLeft => row,
+        }
+    }
     /// Extent of a given tile in XYZ adressing scheme
     pub fn tile_extent_xyz(&self, xtile: u32, ytile: u32, zoom: u8) -> Extent {
         let y = self.ytile_from_xyz(ytile, zoom);
@@ -264,6 +361,18 @@ impl Grid {
             ((self.extent.maxx - self.extent.minx - 0.01 * unitwidth) / unitwidth).ceil() as u32;
         (maxx, maxy)
     }
+    /// Number of tiles in x and y direction of grid level
+    pub fn matrix_size(&self, zoom: u8) -> (u32, u32) {
+        self.level_max[zoom as usize]
+    }
+    /// Check whether tile coordinates are within the grid
+    pub fn contains_tile(&self, xtile: u32, ytile: u32, zoom: u8) -> bool {
+        if zoom >= self.nlevels() {
+            return false;
+        }
+        let (maxx, maxy) = self.level_max[zoom as usize];
+        xtile < maxx && ytile < maxy
+    }
     /// (maxx, maxy) of all grid levels
     fn level_max(&self) -> Vec<CellIndex> {
         (0..self.nlevels())
@@ -330,7 +439,7 @@ impl Grid {
 }
 
 /// Returns the Spherical Mercator (x, y) in meters
-fn lonlat_to_merc(lon: f64, lat: f64) -> (f64, f64) {
+pub fn lonlat_to_merc(lon: f64, lat: f64) -> (f64, f64) {
     // from mod web_mercator in grid_test
     //lng, lat = truncate_lnglat(lng, lat)
     let x = 6378137.0 * lon.to_radians();
@@ -350,15 +459,61 @@ pub fn extent_to_merc(extent: &Extent) -> Extent {
     }
 }
 
-impl<'a> Config<'a, GridCfg> for Grid {
-    fn from_config(grid_cfg: &GridCfg) -> Result<Self, String> {
-        if let Some(ref gridname) = grid_cfg.predefined {
+/// Returns the WGS84 (lon, lat) of Spherical Mercator (x, y) in meters
+pub fn merc_to_lonlat(x: f64, y: f64) -> (f64, f64) {
+    let lon = (x / 6378137.0).to_degrees();
+    let lat = (2.0 * (y / 6378137.0).exp().atan() - consts::PI * 0.5).to_degrees();
+    (lon, lat)
+}
+
+/// WGS84 extent of Spherical Mercator extent
+pub fn extent_from_merc(extent: &Extent) -> Extent {
+    let (minx, miny) = merc_to_lonlat(extent.minx, extent.miny);
+    let (maxx, maxy) = merc_to_lonlat(extent.maxx, extent.maxy);
+    Extent {
+        minx,
+        miny,
+        maxx,
+        maxy,
+    }
+}
+
+impl Grid {
+    /// Grid from `[[grid.additional]]` configuration
+    pub fn from_named_config(grid_cfg: &NamedGridCfg) -> Result<Grid, String> {
+        Grid::from_grid_config(&grid_cfg.predefined, &grid_cfg.user, &grid_cfg.tile_scheme)
+    }
+    fn from_grid_config(
+        predefined: &Option<String>,
+        user: &Option<UserGridCfg>,
+        tile_scheme: &Option<String>,
+    ) -> Result<Grid, String> {
+        let mut grid = Grid::from_grid_definition(predefined, user)?;
+        if let Some(ref scheme) = *tile_scheme {
+            grid.tile_scheme = TileScheme::from_str(scheme)?;
+        }
+        Ok(grid)
+    }
+    fn from_grid_definition(
+        predefined: &Option<String>,
+        user: &Option<UserGridCfg>,
+    ) -> Result<Grid, String> {
+        if let Some(ref gridname) = *predefined {
             match gridname.as_str() {
                 "wgs84" => Ok(Grid::wgs84()),
                 "web_mercator" => Ok(Grid::web_mercator()),
+                "web_mercator_512" => Ok(Grid::web_mercator_512()),
                 _ => Err(format!("Unkown grid '{}'", gridname)),
             }
-        } else if let Some(ref usergrid) = grid_cfg.user {
+        } else if let Some(ref usergrid) = *user {
+            if usergrid.width == 0 || usergrid.height == 0 {
+                return Err("Invalid grid tile size".to_string());
+            }
+            if usergrid.resolutions.is_empty()
+                || usergrid.resolutions.windows(2).any(|r| r[0] <= r[1])
+            {
+                return Err("Grid resolutions must be ordered from largest to smallest".to_string());
+            }
             let mut grid = Grid {
                 width: usergrid.width,
                 height: usergrid.height,
@@ -368,6 +523,12 @@ impl<'a> Config<'a, GridCfg> for Grid {
                 resolutions: usergrid.resolutions.clone(),
                 level_max: Vec::new(),
                 origin: Origin::from_str(&usergrid.origin)?,
+                // Web Mercator tiles are adressed in XYZ scheme like the predefined grid
+                tile_scheme: if usergrid.srid == 3857 {
+                    TileScheme::Xyz
+                } else {
+                    TileScheme::Tms
+                },
             };
             grid.level_max = grid.level_max();
             Ok(grid)
@@ -375,11 +536,19 @@ impl<'a> Config<'a, GridCfg> for Grid {
             Err("Invalid grid definition".to_string())
         }
     }
+}
+
+impl<'a> Config<'a, GridCfg> for Grid {
+    fn from_config(grid_cfg: &GridCfg) -> Result<Self, String> {
+        Grid::from_grid_config(&grid_cfg.predefined, &grid_cfg.user, &grid_cfg.tile_scheme)
+    }
     fn gen_config() -> String {
         let toml = r#"
 [grid]
-# Predefined grids: web_mercator, wgs84
+# Predefined grids: web_mercator, web_mercator_512, wgs84
 predefined = "web_mercator"
+# Tile adressing: xyz (default for grids in EPSG:3857) or tms
+#tile_scheme = "xyz"
 "#;
         toml.to_string()
     }
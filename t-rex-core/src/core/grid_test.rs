@@ -4,7 +4,7 @@
 //
 
 use core::config::GridCfg;
-use core::grid::{extent_to_merc, Extent, ExtentInt, Grid, Origin};
+use core::grid::{extent_from_merc, extent_to_merc, Extent, ExtentInt, Grid, Origin, TileScheme};
 use core::Config;
 use toml;
 
@@ -200,6 +200,51 @@ fn test_wgs84_grid() {
     assert_eq!(grid.scale_denominator(10), 272989.38673277234);
 }
 
+#[test]
+fn test_web_mercator_512() {
+    let grid = Grid::web_mercator();
+    let grid512 = Grid::web_mercator_512();
+    assert_eq!(grid512.tile_size(), 512);
+    assert_eq!(grid512.nlevels(), grid.nlevels());
+    assert_eq!(grid512.tile_extent(3, 5, 4), grid.tile_extent(3, 5, 4));
+    assert!((grid512.pixel_width(10) - grid.pixel_width(11)).abs() < 1e-9);
+    assert!(grid512.contains_tile(15, 15, 4));
+    assert!(!grid512.contains_tile(16, 0, 4));
+
+    let grid1024 = grid.with_tile_size(1024);
+    assert_eq!(grid1024.tile_size(), 1024);
+    assert_eq!(grid1024.tile_extent(3, 5, 4), grid.tile_extent(3, 5, 4));
+    assert!((grid1024.pixel_width(10) - grid.pixel_width(12)).abs() < 1e-9);
+}
+
+#[test]
+fn test_contains_tile() {
+    let grid = Grid::web_mercator();
+    assert!(grid.contains_tile(0, 0, 0));
+    assert!(!grid.contains_tile(1, 0, 0));
+    assert!(grid.contains_tile(131071, 131071, 17));
+    assert!(!grid.contains_tile(131072, 0, 17));
+    assert!(grid.contains_tile(4194303, 4194303, 22));
+    assert!(!grid.contains_tile(0, 0, 23));
+}
+
+#[test]
+fn test_wmts_matrix() {
+    let grid = Grid::web_mercator();
+    assert_eq!(grid.matrix_size(0), (1, 1));
+    assert_eq!(grid.matrix_size(3), (8, 8));
+    // TileRow is counted from the top
+    assert_eq!(grid.ytile_from_wmts(0, 3), 7);
+    assert_eq!(
+        grid.tile_extent(2, grid.ytile_from_wmts(1, 3), 3),
+        grid.tile_extent_xyz(2, 1, 3)
+    );
+
+    let grid = Grid::wgs84();
+    assert_eq!(grid.matrix_size(0), (2, 1));
+    assert_eq!(grid.ytile_from_wmts(0, 1), 1);
+}
+
 #[test]
 fn test_projected_extent() {
     let extent_wgs84 = Extent {
@@ -223,6 +268,11 @@ fn test_projected_extent() {
         maxy: 6982997.920389788,
     };
     assert_eq!(extent_to_merc(&extent_wgs84), extent_3857);
+    let extent = extent_from_merc(&extent_3857);
+    assert!((extent.minx - 4.0).abs() < 1e-9);
+    assert!((extent.miny - 52.0).abs() < 1e-9);
+    assert!((extent.maxx - 5.0).abs() < 1e-9);
+    assert!((extent.maxy - 53.0).abs() < 1e-9);
 }
 
 #[test]
@@ -245,6 +295,35 @@ fn test_grid_from_config() {
         }
     );
 
+    assert_eq!(grid.tile_scheme, TileScheme::Xyz);
+    assert_eq!(grid.ytile_tms(0, 1), 1);
+
+    let toml = r#"
+        #[grid]
+        predefined = "web_mercator"
+        tile_scheme = "tms"
+        "#;
+    let config: GridCfg = parse_config(toml.to_string(), "").unwrap();
+    let grid = Grid::from_config(&config).unwrap();
+    assert_eq!(grid.tile_scheme, TileScheme::Tms);
+    assert_eq!(grid.ytile_tms(0, 1), 0);
+
+    // User grids in Web Mercator default to XYZ
+    let toml = r#"
+        #[grid.user]
+        [user]
+        width = 256
+        height = 256
+        extent = { minx = -20037508.3427892480, miny = -20037508.3427892480, maxx = 20037508.3427892480, maxy = 20037508.3427892480 }
+        srid = 3857
+        units = "m"
+        resolutions = [156543.0339280410,78271.5169640205,39135.7584820102]
+        origin = "BottomLeft"
+        "#;
+    let config: GridCfg = parse_config(toml.to_string(), "").unwrap();
+    let grid = Grid::from_config(&config).unwrap();
+    assert_eq!(grid.tile_scheme, TileScheme::Xyz);
+
     let toml = r#"
         #[grid.user]
         [user]
@@ -268,6 +347,7 @@ fn test_grid_from_config() {
         }
     );
     assert_eq!(grid.origin, Origin::TopLeft);
+    assert_eq!(grid.tile_scheme, TileScheme::Tms);
 
     let extent = grid.tile_extent(10, 4, 17); // lake of Zurich
     assert_eq!(
@@ -290,6 +370,38 @@ fn test_grid_from_config() {
             maxy: 1145200.0,
         }
     );
+
+    let toml = r#"
+        # ETRS89 / UTM zone 32N
+        [user]
+        width = 256
+        height = 256
+        extent = { minx = -46133.17, miny = 5048875.27, maxx = 1206211.10, maxy = 6301219.54 }
+        srid = 25832
+        units = "m"
+        resolutions = [4891.97,2445.98,1222.99,611.50,305.75,152.87,76.44,38.22,19.11,9.55,4.78,2.39,1.19,0.60,0.30]
+        origin = "TopLeft"
+        "#;
+    let config: GridCfg = parse_config(toml.to_string(), "").unwrap();
+    let grid = Grid::from_config(&config).unwrap();
+    assert_eq!(grid.srid, 25832);
+    assert_eq!(grid.maxzoom(), 14);
+
+    let toml = r#"
+        [user]
+        width = 256
+        height = 256
+        extent = { minx = 2420000.0, miny = 1030000.0, maxx = 2900000.0, maxy = 1350000.0 }
+        srid = 2056
+        units = "m"
+        resolutions = [0.5,1.0]
+        origin = "TopLeft"
+        "#;
+    let config: GridCfg = parse_config(toml.to_string(), "").unwrap();
+    assert_eq!(
+        Grid::from_config(&config).err(),
+        Some("Grid resolutions must be ordered from largest to smallest".to_string())
+    );
 }
 
 mod web_mercator {
@@ -412,3 +524,21 @@ mod web_mercator {
     }
 
 }
+
+#[test]
+fn test_extent_from_str() {
+    assert_eq!(
+        "-180,-90,180, 90".parse::<Extent>(),
+        Ok(Extent {
+            minx: -180.0,
+            miny: -90.0,
+            maxx: 180.0,
+            maxy: 90.0,
+        })
+    );
+    assert_eq!(
+        "1,2,3".parse::<Extent>(),
+        Err("Invalid extent '1,2,3' - expected minx,miny,maxx,maxy".to_string())
+    );
+    assert!("1,2,3,x".parse::<Extent>().is_err());
+}
@@ -4,17 +4,91 @@
 //
 
 use core::config::LayerCfg;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
 use core::Config;
 use service::glstyle_converter::toml_style_to_gljson;
+use std::cmp;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct LayerQuery {
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
+    /// Table name for this zoom range (e.g. generalized table)
+    pub table_name: Option<String>,
     pub sql: Option<String>,
 }
 
+#[derive(Debug)]
+pub struct LayerAttributes {
+    pub minzoom: Option<u8>,
+    pub maxzoom: Option<u8>,
+    pub fields: Vec<String>,
+}
+
+/// Handling of invalid geometries
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ValidityPolicy {
+    /// Omit invalid features
+    Skip,
+    /// Log a warning and keep invalid features
+    Warn,
+    /// Close rings and remove degenerated parts, omit features which can't be repaired
+    Repair,
+}
+
+impl FromStr for ValidityPolicy {
+    type Err = String;
+    fn from_str(val: &str) -> Result<ValidityPolicy, String> {
+        match val {
+            "skip" => Ok(ValidityPolicy::Skip),
+            "warn" => Ok(ValidityPolicy::Warn),
+            "repair" => Ok(ValidityPolicy::Repair),
+            _ => Err(format!("Unexpected validity policy '{}'", val)),
+        }
+    }
+}
+
+impl ValidityPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ValidityPolicy::Skip => "skip",
+            ValidityPolicy::Warn => "warn",
+            ValidityPolicy::Repair => "repair",
+        }
+    }
+}
+
+/// Key for detecting repeated features
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum DedupKey {
+    /// Feature id
+    Fid,
+    /// Encoded tile geometry
+    Geometry,
+}
+
+impl FromStr for DedupKey {
+    type Err = String;
+    fn from_str(val: &str) -> Result<DedupKey, String> {
+        match val {
+            "fid" => Ok(DedupKey::Fid),
+            "geometry" => Ok(DedupKey::Geometry),
+            _ => Err(format!("Unexpected dedup value '{}'", val)),
+        }
+    }
+}
+
+impl DedupKey {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            DedupKey::Fid => "fid",
+            DedupKey::Geometry => "geometry",
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct Layer {
     pub name: String,
@@ -26,13 +100,23 @@ pub struct Layer {
     /// Handle geometry like one in grid SRS
     pub no_transform: bool,
     pub fid_field: Option<String>,
+    /// Timestamp column of the last modification, for seeding changed tiles only
+    pub modified_field: Option<String>,
     // Input for derived queries
     pub table_name: Option<String>,
     pub query_limit: Option<u32>,
+    /// Feature order within the layer (SQL ORDER BY expression, e.g. "z_order" or "area DESC")
+    pub sort_by: Option<String>,
     // Explicit queries
     pub query: Vec<LayerQuery>,
+    /// Zoom dependent attribute selection
+    pub attributes: Vec<LayerAttributes>,
+    /// Keys of JSONB/HSTORE columns added as attributes (None: all keys)
+    pub json_keys: Option<Vec<String>>,
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
+    /// Query data intended for zoom level z+zoom_offset
+    pub zoom_offset: i8,
     /// Width and height of the tiles
     pub tile_size: u32,
     /// Simplify geometry (lines and polygons)
@@ -43,6 +127,16 @@ pub struct Layer {
     pub buffer_size: Option<u32>,
     /// Fix invalid geometries before clipping (lines and polygons)
     pub make_valid: bool,
+    /// Geometry validity check (None: no check)
+    pub validity: Option<ValidityPolicy>,
+    /// Remove repeated features within a tile
+    pub dedup: Option<DedupKey>,
+    /// Split features into separate MVT layers by attribute value
+    pub split_by: Option<String>,
+    /// Write features into the MVT layer with this name (shared with other layers)
+    pub merge_into: Option<String>,
+    /// Attribute holding the source layer name of merged features
+    pub merge_attribute: String,
     // Inline style
     pub style: Option<String>,
 }
@@ -52,6 +146,7 @@ impl Layer {
         Layer {
             name: String::from(name),
             tile_size: 4096,
+            merge_attribute: DEFAULT_MERGE_ATTRIBUTE.to_string(),
             ..Default::default()
         }
     }
@@ -73,6 +168,12 @@ impl Layer {
                 .unwrap_or(default),
         )
     }
+    /// Zoom level of queried data for a tile at given zoom level
+    pub fn query_zoom(&self, zoom: u8, grid_maxzoom: u8) -> u8 {
+        let maxzoom = cmp::min(self.maxzoom(22), grid_maxzoom) as i16;
+        let level = zoom as i16 + self.zoom_offset as i16;
+        cmp::max(cmp::min(level, maxzoom), self.minzoom() as i16) as u8
+    }
     // SQL query for zoom level
     pub fn query(&self, level: u8) -> Option<&String> {
         let mut queries = self
@@ -93,6 +194,79 @@ impl Layer {
             .find(|ref q| level >= q.0 && level <= q.1);
         query.and_then(|ref q| q.2)
     }
+    /// Table name at zoom level, overridden by query table_name
+    pub fn table_name_at(&self, level: u8) -> Option<&String> {
+        self.query
+            .iter()
+            .rev()
+            .filter(|q| level >= q.minzoom.unwrap_or(0) && level <= q.maxzoom.unwrap_or(22))
+            .filter_map(|q| q.table_name.as_ref())
+            .next()
+            .or(self.table_name.as_ref())
+    }
+    /// Simplification tolerance for given pixel width.
+    /// Supports expressions with `!pixel_width!`, numbers, `*` and `/` (e.g. "!pixel_width!/2").
+    pub fn tolerance_value(&self, pixel_width: f64) -> Option<f64> {
+        let expr = self
+            .tolerance
+            .replace("!pixel_width!", &pixel_width.to_string());
+        let mut value = 1.0;
+        let mut op = '*';
+        let mut rest = expr.as_str();
+        loop {
+            let pos = rest.find(|c: char| c == '*' || c == '/');
+            let term = match pos {
+                Some(pos) => &rest[..pos],
+                None => rest,
+            };
+            let num = term.trim().parse::<f64>().ok()?;
+            if op == '*' {
+                value *= num;
+            } else {
+                value /= num;
+            }
+            match pos {
+                Some(pos) => {
+                    op = rest[pos..].chars().next().unwrap();
+                    rest = &rest[pos + 1..];
+                }
+                None => break,
+            }
+        }
+        Some(value)
+    }
+    /// Attributes included at zoom level (None: all attributes)
+    pub fn attribute_fields(&self, level: u8) -> Option<&Vec<String>> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|a| level >= a.minzoom.unwrap_or(0) && level <= a.maxzoom.unwrap_or(22))
+            .map(|a| &a.fields)
+    }
+    /// Name of the MVT layer without split suffix
+    pub fn mvt_name(&self) -> &String {
+        self.merge_into.as_ref().unwrap_or(&self.name)
+    }
+    /// Name of the MVT layer a feature is written to
+    pub fn mvt_layer_name(&self, feature: &Feature) -> String {
+        if let Some(ref split_by) = self.split_by {
+            if let Some(attr) = feature.attributes().iter().find(|a| &a.key == split_by) {
+                return self.split_layer_name(&attr.value.to_string());
+            }
+        }
+        self.mvt_name().clone()
+    }
+    /// Name of the MVT layer of features with `split_by` attribute `value`
+    pub fn split_layer_name(&self, value: &str) -> String {
+        format!("{}_{}", self.mvt_name(), value)
+    }
+    /// Discriminator attribute added to features of merged layers
+    pub fn merge_discriminator(&self) -> Option<FeatureAttr> {
+        self.merge_into.as_ref().map(|_| FeatureAttr {
+            key: self.merge_attribute.clone(),
+            value: FeatureAttrValType::String(self.name.clone()),
+        })
+    }
     /// Layer properties needed e.g. for metadata.json
     pub fn metadata(&self) -> HashMap<&str, String> {
         //TODO: return Zoom-Level Array
@@ -109,6 +283,9 @@ impl Layer {
 }
 
 const DEFAULT_TOLERANCE: &str = "!pixel_width!/2";
+const DEFAULT_MERGE_ATTRIBUTE: &str = "layer";
+/// Default buffer size in pixels for point layers
+pub const POINT_BUFFER_SIZE: u32 = 10;
 
 impl<'a> Config<'a, LayerCfg> for Layer {
     fn from_config(layer_cfg: &LayerCfg) -> Result<Self, String> {
@@ -118,9 +295,27 @@ impl<'a> Config<'a, LayerCfg> for Layer {
             .map(|lq| LayerQuery {
                 minzoom: lq.minzoom,
                 maxzoom: lq.maxzoom,
+                table_name: lq.table_name.clone(),
                 sql: lq.sql.clone(),
             })
             .collect();
+        let attributes = layer_cfg
+            .attributes
+            .iter()
+            .map(|la| LayerAttributes {
+                minzoom: la.minzoom,
+                maxzoom: la.maxzoom,
+                fields: la.fields.clone(),
+            })
+            .collect();
+        let validity = match layer_cfg.validity {
+            Some(ref policy) => Some(policy.parse()?),
+            None => None,
+        };
+        let dedup = match layer_cfg.dedup {
+            Some(ref key) => Some(key.parse()?),
+            None => None,
+        };
         let style = match layer_cfg.style {
             Some(ref style) => {
                 let gljson = toml_style_to_gljson(&style);
@@ -136,19 +331,38 @@ impl<'a> Config<'a, LayerCfg> for Layer {
             srid: layer_cfg.srid,
             no_transform: layer_cfg.no_transform.unwrap_or(false),
             fid_field: layer_cfg.fid_field.clone(),
+            modified_field: layer_cfg.modified_field.clone(),
             table_name: layer_cfg.table_name.clone(),
             query_limit: layer_cfg.query_limit,
+            sort_by: layer_cfg.sort_by.clone(),
             query: queries,
+            attributes: attributes,
+            json_keys: layer_cfg.json_keys.clone(),
             minzoom: layer_cfg.minzoom,
             maxzoom: layer_cfg.maxzoom,
+            zoom_offset: layer_cfg.zoom_offset.unwrap_or(0),
             tile_size: layer_cfg.tile_size.unwrap_or(4096),
             simplify: layer_cfg.simplify.unwrap_or(false),
             tolerance: layer_cfg
                 .tolerance
                 .clone()
                 .unwrap_or(DEFAULT_TOLERANCE.to_string()),
-            buffer_size: layer_cfg.buffer_size,
+            buffer_size: layer_cfg
+                .buffer_size
+                .or_else(|| match layer_cfg.geometry_type {
+                    // Include points near the tile border, whose symbols or labels overlap the tile
+                    Some(ref geom) if geom.contains("POINT") => Some(POINT_BUFFER_SIZE),
+                    _ => None,
+                }),
             make_valid: layer_cfg.make_valid.unwrap_or(false),
+            validity: validity,
+            dedup: dedup,
+            split_by: layer_cfg.split_by.clone(),
+            merge_into: layer_cfg.merge_into.clone(),
+            merge_attribute: layer_cfg
+                .merge_attribute
+                .clone()
+                .unwrap_or(DEFAULT_MERGE_ATTRIBUTE.to_string()),
             style: style,
         })
     }
@@ -160,13 +374,17 @@ name = "points"
 #minzoom = 0
 #maxzoom = 22
 #attribution = "© Contributeurs de OpenStreetMap" # Acknowledgment of ownership, authorship or copyright.
-#cache_limits = {minzoom = 0, maxzoom = 22, no_cache = false}
+#cache_limits = {minzoom = 0, maxzoom = 22, no_cache = false, ttl = 86400} # Re-render cached tiles older than ttl seconds
+#cache_control_max_age = 43200
+#style_file = "points.style.json" # Mapbox GL style served instead of the generated style
 
 [[tileset.layer]]
 name = "points"
 table_name = "mytable"
 geometry_field = "wkb_geometry"
 geometry_type = "POINT"
+#fid_field = "id"
+#modified_field = "updated_at" # Timestamp column for seeding changed tiles with `--modified-since` (at the current feature position)
 #simplify = true
 #tolerance = "!pixel_width!/2"
 #buffer_size = 10
@@ -212,6 +430,18 @@ geometry_type = "POINT"
         if let Some(ref fid_field) = self.fid_field {
             lines.push(format!("fid_field = \"{}\"", fid_field));
         }
+        if let Some(ref modified_field) = self.modified_field {
+            lines.push(format!("modified_field = \"{}\"", modified_field));
+        }
+        if let Some(minzoom) = self.minzoom {
+            lines.push(format!("minzoom = {}", minzoom));
+        }
+        if let Some(maxzoom) = self.maxzoom {
+            lines.push(format!("maxzoom = {}", maxzoom));
+        }
+        if self.zoom_offset != 0 {
+            lines.push(format!("zoom_offset = {}", self.zoom_offset));
+        }
         if self.tile_size != 4096 {
             lines.push(format!(r#"tile_size = "{}""#, self.tile_size));
         }
@@ -223,6 +453,21 @@ geometry_type = "POINT"
             true => lines.push(format!("make_valid = true")),
             _ => lines.push(format!("#make_valid = true")),
         }
+        if let Some(validity) = self.validity {
+            lines.push(format!("validity = \"{}\"", validity.as_str()));
+        }
+        if let Some(dedup) = self.dedup {
+            lines.push(format!("dedup = \"{}\"", dedup.as_str()));
+        }
+        if let Some(ref split_by) = self.split_by {
+            lines.push(format!("split_by = \"{}\"", split_by));
+        }
+        if let Some(ref merge_into) = self.merge_into {
+            lines.push(format!("merge_into = \"{}\"", merge_into));
+            if self.merge_attribute != DEFAULT_MERGE_ATTRIBUTE {
+                lines.push(format!("merge_attribute = \"{}\"", self.merge_attribute));
+            }
+        }
         if self.geometry_type != Some("POINT".to_string()) {
             // simplify is ignored for points
             lines.push(format!("simplify = {}", self.simplify));
@@ -234,15 +479,37 @@ geometry_type = "POINT"
             Some(ref query_limit) => lines.push(format!("query_limit = {}", query_limit)),
             _ => lines.push("#query_limit = 1000".to_string()),
         }
+        if let Some(ref sort_by) = self.sort_by {
+            lines.push(format!("sort_by = \"{}\"", sort_by));
+        }
+        if let Some(ref json_keys) = self.json_keys {
+            let keys: Vec<String> = json_keys.iter().map(|k| format!("\"{}\"", k)).collect();
+            lines.push(format!("json_keys = [{}]", keys.join(", ")));
+        }
         match self.query(0) {
             Some(ref query) => {
                 lines.push("[[tileset.layer.query]]".to_string());
-                lines.push(format!("sql = \"{}\"", query))
+                lines.push(format!("sql = '''{}'''", query))
             }
             _ => {
                 lines.push("#[[tileset.layer.query]]".to_string());
             }
         }
+        for attributes in &self.attributes {
+            lines.push("[[tileset.layer.attributes]]".to_string());
+            if let Some(minzoom) = attributes.minzoom {
+                lines.push(format!("minzoom = {}", minzoom));
+            }
+            if let Some(maxzoom) = attributes.maxzoom {
+                lines.push(format!("maxzoom = {}", maxzoom));
+            }
+            let fields: Vec<String> = attributes
+                .fields
+                .iter()
+                .map(|f| format!("\"{}\"", f))
+                .collect();
+            lines.push(format!("fields = [{}]", fields.join(", ")));
+        }
         lines.join("\n") + "\n"
     }
 }
@@ -278,3 +278,286 @@ fn test_layers_from_config() {
         Some(" - missing field `name`".to_string())
     );
 }
+
+#[test]
+fn test_tileset_tile_size() {
+    use core::config::TilesetCfg;
+    use core::parse_config;
+
+    let toml = r#"
+        name = "ne"
+        tile_size = 8192
+        pixel_size = 512
+        [[layer]]
+        name = "points"
+        [[layer]]
+        name = "lines"
+        tile_size = 4096
+        "#;
+    let config: TilesetCfg = parse_config(toml.to_string(), "").unwrap();
+    let tileset = Tileset::from_config(&config).unwrap();
+    assert_eq!(tileset.pixel_size, Some(512));
+    assert_eq!(tileset.layers[0].tile_size, 8192);
+    assert_eq!(tileset.layers[1].tile_size, 4096);
+
+    let toml = r#"
+        name = "ne"
+        pixel_size = 0
+        layer = []
+        "#;
+    let config: TilesetCfg = parse_config(toml.to_string(), "").unwrap();
+    assert_eq!(
+        Tileset::from_config(&config).err(),
+        Some("Tileset 'ne': Invalid pixel_size 0".to_string())
+    );
+
+    let toml = r#"
+        name = "stats"
+        layer = []
+        "#;
+    let config: TilesetCfg = parse_config(toml.to_string(), "").unwrap();
+    assert_eq!(
+        Tileset::from_config(&config).err(),
+        Some("Tileset 'stats': Name reserved for web service resources".to_string())
+    );
+}
+
+#[test]
+fn test_tileset_style_file() {
+    use core::config::TilesetCfg;
+    use core::parse_config;
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+
+    let path = env::temp_dir().join("t_rex_test.style.json");
+    let mut file = File::create(&path).unwrap();
+    file.write_all(br#"{"version": 8, "layers": []}"#).unwrap();
+    let toml = format!(
+        r#"
+        name = "ne"
+        style_file = "{}"
+        layer = []
+        "#,
+        path.display()
+    );
+    let config: TilesetCfg = parse_config(toml, "").unwrap();
+    let tileset = Tileset::from_config(&config).unwrap();
+    assert_eq!(tileset.style_json.unwrap()["version"], 8);
+
+    let toml = r#"
+        name = "ne"
+        style_file = "missing.style.json"
+        layer = []
+        "#;
+    let config: TilesetCfg = parse_config(toml.to_string(), "").unwrap();
+    assert!(Tileset::from_config(&config)
+        .err()
+        .unwrap()
+        .starts_with("Tileset 'ne': Invalid style file 'missing.style.json'"));
+}
+
+#[test]
+fn test_split_by() {
+    use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+    use core::geom::{self, GeometryType};
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        table_name = "roads"
+        geometry_field = "wkb_geometry"
+        split_by = "class"
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.split_by, Some("class".to_string()));
+
+    let mut feature = FeatureStruct {
+        fid: None,
+        attributes: vec![FeatureAttr {
+            key: String::from("class"),
+            value: FeatureAttrValType::String(String::from("motorway")),
+        }],
+        geometry: GeometryType::Point(geom::Point::new(0.0, 0.0, Some(3857))),
+    };
+    assert_eq!(layer.mvt_layer_name(&feature), "roads_motorway");
+
+    feature.attributes[0].value = FeatureAttrValType::Int(2);
+    assert_eq!(layer.mvt_layer_name(&feature), "roads_2");
+    assert_eq!(layer.split_layer_name("2"), "roads_2");
+
+    // Features without split attribute stay in base layer
+    feature.attributes.clear();
+    assert_eq!(layer.mvt_layer_name(&feature), "roads");
+}
+
+#[test]
+fn test_merge_into() {
+    use core::feature::{FeatureAttrValType, FeatureStruct};
+    use core::geom::{self, GeometryType};
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads_major"
+        table_name = "roads_major"
+        geometry_field = "wkb_geometry"
+        merge_into = "roads"
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.mvt_name(), "roads");
+    assert_eq!(layer.merge_attribute, "layer");
+
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: vec![],
+        geometry: GeometryType::Point(geom::Point::new(0.0, 0.0, Some(3857))),
+    };
+    assert_eq!(layer.mvt_layer_name(&feature), "roads");
+    let attr = layer.merge_discriminator().unwrap();
+    assert_eq!(attr.key, "layer");
+    assert_eq!(
+        attr.value,
+        FeatureAttrValType::String("roads_major".to_string())
+    );
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads_minor"
+        merge_into = "roads"
+        merge_attribute = "source"
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.merge_discriminator().unwrap().key, "source");
+
+    // Not merged
+    let layer = Layer::new("points");
+    assert_eq!(layer.mvt_name(), "points");
+    assert!(layer.merge_discriminator().is_none());
+}
+
+#[test]
+fn test_attribute_fields() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        [[attributes]]
+        maxzoom = 9
+        fields = ["class"]
+        [[attributes]]
+        minzoom = 10
+        maxzoom = 13
+        fields = ["class", "name"]
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.attribute_fields(0), Some(&vec!["class".to_string()]));
+    assert_eq!(layer.attribute_fields(9), Some(&vec!["class".to_string()]));
+    assert_eq!(
+        layer.attribute_fields(12),
+        Some(&vec!["class".to_string(), "name".to_string()])
+    );
+    // All attributes above z13
+    assert_eq!(layer.attribute_fields(14), None);
+
+    let config = layer.gen_runtime_config();
+    assert!(config.contains(
+        r#"[[tileset.layer.attributes]]
+minzoom = 10
+maxzoom = 13
+fields = ["class", "name"]"#
+    ));
+}
+
+#[test]
+fn test_table_name_at() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        table_name = "roads"
+        [[query]]
+        maxzoom = 8
+        table_name = "roads_gen50"
+        [[query]]
+        minzoom = 9
+        maxzoom = 11
+        table_name = "roads_gen10"
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.table_name_at(0), Some(&"roads_gen50".to_string()));
+    assert_eq!(layer.table_name_at(8), Some(&"roads_gen50".to_string()));
+    assert_eq!(layer.table_name_at(10), Some(&"roads_gen10".to_string()));
+    assert_eq!(layer.table_name_at(12), Some(&"roads".to_string()));
+    assert_eq!(layer.query(0), None);
+}
+
+#[test]
+fn test_tolerance_value() {
+    let mut layer = Layer::new("roads");
+    layer.tolerance = "!pixel_width!/2".to_string();
+    assert_eq!(layer.tolerance_value(10.0), Some(5.0));
+    layer.tolerance = "!pixel_width! * 3 / 2".to_string();
+    assert_eq!(layer.tolerance_value(10.0), Some(15.0));
+    layer.tolerance = "0.5".to_string();
+    assert_eq!(layer.tolerance_value(10.0), Some(0.5));
+    layer.tolerance = "GREATEST(!pixel_width!, 1)".to_string();
+    assert_eq!(layer.tolerance_value(10.0), None);
+}
+
+#[test]
+fn test_validity() {
+    use core::layer::ValidityPolicy;
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "buildings"
+        validity = "repair"
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.validity, Some(ValidityPolicy::Repair));
+    assert!(layer.gen_runtime_config().contains(r#"validity = "repair""#));
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "buildings"
+        validity = "ignore"
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Unexpected validity policy 'ignore'".to_string())
+    );
+}
+
+#[test]
+fn test_dedup() {
+    use core::layer::DedupKey;
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "labels"
+        dedup = "geometry"
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.dedup, Some(DedupKey::Geometry));
+    assert!(layer.gen_runtime_config().contains(r#"dedup = "geometry""#));
+    assert_eq!(Layer::new("labels").dedup, None);
+}
+
+#[test]
+fn test_zoom_offset() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        minzoom = 4
+        maxzoom = 16
+        zoom_offset = -1
+        "#;
+    let layer = layer_from_config(toml).unwrap();
+    assert_eq!(layer.query_zoom(10, 22), 9);
+    assert_eq!(layer.query_zoom(4, 22), 4);
+    assert!(layer.gen_runtime_config().contains("zoom_offset = -1"));
+
+    let mut layer = Layer::new("roads");
+    layer.zoom_offset = 2;
+    assert_eq!(layer.query_zoom(10, 22), 12);
+    assert_eq!(layer.query_zoom(21, 22), 22);
+    assert_eq!(layer.query_zoom(17, 18), 18);
+}
@@ -0,0 +1,206 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Prometheus metrics (https://prometheus.io/docs/instrumenting/exposition_formats/)
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Name, type and help text of all metrics
+const METRICS: &[(&str, &str, &str)] = &[
+    (
+        "trex_tile_requests_total",
+        "counter",
+        "Tile requests by tileset and HTTP status",
+    ),
+    (
+        "trex_tile_cache_requests_total",
+        "counter",
+        "Tile cache lookups by tileset and result (hit, miss)",
+    ),
+    (
+        "trex_tile_render_duration_seconds",
+        "histogram",
+        "Duration of tile rendering including encoding",
+    ),
+    (
+        "trex_layer_query_duration_seconds",
+        "histogram",
+        "Duration of datasource queries by tileset and layer",
+    ),
+    (
+        "trex_tile_size_bytes",
+        "histogram",
+        "Size of compressed tiles",
+    ),
+];
+
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const SIZE_BUCKETS: &[f64] = &[
+    1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 524288.0, 1048576.0,
+];
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Metrics> = Mutex::new(Metrics::new());
+}
+
+struct Histogram {
+    /// Cumulative count per bucket
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// Counters and histograms by metric name and labels
+pub struct Metrics {
+    counters: BTreeMap<(String, String), u64>,
+    histograms: BTreeMap<(String, String), Histogram>,
+}
+
+fn buckets(name: &str) -> &'static [f64] {
+    if name.ends_with("_bytes") {
+        SIZE_BUCKETS
+    } else {
+        DURATION_BUCKETS
+    }
+}
+
+fn label_string(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|&(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Labels with additional `le` label of histogram bucket
+fn bucket_labels(labels: &str, le: &str) -> String {
+    if labels.is_empty() {
+        format!("le=\"{}\"", le)
+    } else {
+        format!("{},le=\"{}\"", labels, le)
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            counters: BTreeMap::new(),
+            histograms: BTreeMap::new(),
+        }
+    }
+    pub fn inc_counter(&mut self, name: &str, labels: &[(&str, &str)]) {
+        *self
+            .counters
+            .entry((name.to_string(), label_string(labels)))
+            .or_insert(0) += 1;
+    }
+    pub fn observe(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let bounds = buckets(name);
+        let histogram = self
+            .histograms
+            .entry((name.to_string(), label_string(labels)))
+            .or_insert(Histogram {
+                counts: vec![0; bounds.len()],
+                sum: 0.0,
+                count: 0,
+            });
+        for (count, &bound) in histogram.counts.iter_mut().zip(bounds) {
+            if value <= bound {
+                *count += 1;
+            }
+        }
+        histogram.sum += value;
+        histogram.count += 1;
+    }
+    /// Metrics in Prometheus text format
+    pub fn as_prometheus(&self) -> String {
+        let mut out = String::new();
+        for &(name, metric_type, help) in METRICS {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+            for (&(ref key, ref labels), value) in &self.counters {
+                if key == name {
+                    let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+                }
+            }
+            for (&(ref key, ref labels), histogram) in &self.histograms {
+                if key != name {
+                    continue;
+                }
+                for (count, bound) in histogram.counts.iter().zip(buckets(name)) {
+                    let le = bucket_labels(labels, &bound.to_string());
+                    let _ = writeln!(out, "{}_bucket{{{}}} {}", name, le, count);
+                }
+                let le = bucket_labels(labels, "+Inf");
+                let _ = writeln!(out, "{}_bucket{{{}}} {}", name, le, histogram.count);
+                let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, histogram.sum);
+                let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, histogram.count);
+            }
+        }
+        out
+    }
+}
+
+/// Increment counter of global registry
+pub fn inc_counter(name: &str, labels: &[(&str, &str)]) {
+    REGISTRY.lock().unwrap().inc_counter(name, labels);
+}
+
+/// Add observation to histogram of global registry
+pub fn observe(name: &str, labels: &[(&str, &str)], value: f64) {
+    REGISTRY.lock().unwrap().observe(name, labels, value);
+}
+
+/// Add duration in seconds to histogram of global registry
+pub fn observe_duration(name: &str, labels: &[(&str, &str)], duration: Duration) {
+    let secs = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
+    observe(name, labels, secs);
+}
+
+/// Global registry in Prometheus text format
+pub fn prometheus_text() -> String {
+    REGISTRY.lock().unwrap().as_prometheus()
+}
+
+#[test]
+fn test_prometheus_text() {
+    let mut metrics = Metrics::new();
+    metrics.inc_counter(
+        "trex_tile_requests_total",
+        &[("tileset", "osm"), ("status", "200")],
+    );
+    metrics.inc_counter(
+        "trex_tile_requests_total",
+        &[("tileset", "osm"), ("status", "200")],
+    );
+    metrics.inc_counter("trex_tile_requests_total", &[("tileset", "a\"b")]);
+    metrics.observe("trex_tile_size_bytes", &[("tileset", "osm")], 2000.0);
+    metrics.observe("trex_tile_size_bytes", &[("tileset", "osm")], 2000000.0);
+    let text = metrics.as_prometheus();
+    println!("{}", text);
+    assert!(text.contains(
+        "# TYPE trex_tile_requests_total counter\n\
+         trex_tile_requests_total{tileset=\"a\\\"b\"} 1\n\
+         trex_tile_requests_total{tileset=\"osm\",status=\"200\"} 2\n"
+    ));
+    assert!(text.contains("trex_tile_size_bytes_bucket{tileset=\"osm\",le=\"1024\"} 0\n"));
+    assert!(text.contains("trex_tile_size_bytes_bucket{tileset=\"osm\",le=\"4096\"} 1\n"));
+    assert!(text.contains("trex_tile_size_bytes_bucket{tileset=\"osm\",le=\"1048576\"} 1\n"));
+    assert!(text.contains("trex_tile_size_bytes_bucket{tileset=\"osm\",le=\"+Inf\"} 2\n"));
+    assert!(text.contains("trex_tile_size_bytes_sum{tileset=\"osm\"} 2002000\n"));
+    assert!(text.contains("trex_tile_size_bytes_count{tileset=\"osm\"} 2\n"));
+    assert!(text.contains("# TYPE trex_layer_query_duration_seconds histogram\n"));
+}
@@ -6,14 +6,17 @@
 #[macro_use]
 mod enum_serializer;
 pub mod config;
+pub mod error;
 pub mod feature;
 pub mod geom;
 pub mod grid;
 pub mod layer;
+pub mod metrics;
 pub mod screen;
 pub mod stats;
 
 pub use self::config::{parse_config, read_config, ApplicationCfg, Config};
+pub use self::error::Error;
 
 #[cfg(test)]
 mod config_test;
@@ -29,6 +29,23 @@ pub struct LineString {
     pub points: Vec<Point>,
 }
 
+impl LineString {
+    /// Twice the signed area of a ring (positive for clockwise rings with y pointing down)
+    pub fn signed_area(&self) -> i64 {
+        let n = self.points.len();
+        (0..n)
+            .map(|i| {
+                let (p1, p2) = (&self.points[i], &self.points[(i + 1) % n]);
+                p1.x as i64 * p2.y as i64 - p2.x as i64 * p1.y as i64
+            })
+            .sum()
+    }
+    /// Remove consecutive repeated points (e.g. after snapping to the tile grid)
+    pub fn dedup(&mut self) {
+        self.points.dedup();
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct MultiLineString {
     pub lines: Vec<LineString>,
@@ -43,3 +60,43 @@ pub struct Polygon {
 pub struct MultiPolygon {
     pub polygons: Vec<Polygon>,
 }
+
+impl Polygon {
+    /// Remove repeated points and rings without area.
+    /// All rings are removed if the exterior ring is degenerated.
+    pub fn clean(&mut self) {
+        for ring in self.rings.iter_mut() {
+            ring.dedup();
+        }
+        let degenerated = |ring: &LineString| ring.points.len() < 4 || ring.signed_area() == 0;
+        if self.rings.first().map_or(false, &degenerated) {
+            self.rings.clear();
+        }
+        self.rings.retain(|ring| !degenerated(ring));
+    }
+    /// Orient exterior ring with positive and interior rings with negative area or vice versa
+    pub fn orient(&mut self, exterior_positive: bool) {
+        for (i, ring) in self.rings.iter_mut().enumerate() {
+            let positive = (i == 0) == exterior_positive;
+            let area = ring.signed_area();
+            if (positive && area < 0) || (!positive && area > 0) {
+                ring.points.reverse();
+            }
+        }
+    }
+}
+
+impl MultiPolygon {
+    /// Remove repeated points, rings without area and empty polygons
+    pub fn clean(&mut self) {
+        for polygon in self.polygons.iter_mut() {
+            polygon.clean();
+        }
+        self.polygons.retain(|polygon| !polygon.rings.is_empty());
+    }
+    pub fn orient(&mut self, exterior_positive: bool) {
+        for polygon in self.polygons.iter_mut() {
+            polygon.orient(exterior_positive);
+        }
+    }
+}
@@ -6,7 +6,7 @@
 //! Statistics collector
 
 use serde_json;
-use stats::{MinMax, OnlineStats};
+use stats::{Commute, MinMax, OnlineStats};
 use std::collections::BTreeMap;
 use std::fmt;
 
@@ -14,6 +14,7 @@ type JsonResult = Result<serde_json::Value, serde_json::error::Error>;
 
 type MeasurementType = u64;
 
+#[derive(Clone)]
 struct StatCollector {
     online: OnlineStats,
     minmax: MinMax<MeasurementType>,
@@ -28,6 +29,7 @@ pub struct StatResults {
     pub variance: f64,
 }
 
+#[derive(Clone)]
 pub struct Statistics(BTreeMap<String, StatCollector>);
 
 impl Statistics {
@@ -45,6 +47,14 @@ impl Statistics {
         collector.online.add(value);
         collector.minmax.add(value);
     }
+    /// Add all measurements of another collection
+    pub fn merge(&mut self, other: Statistics) {
+        for (key, other_collector) in other.0 {
+            let collector = self.collector(key);
+            collector.online.merge(other_collector.online);
+            collector.minmax.merge(other_collector.minmax);
+        }
+    }
     /// Return the current results.
     pub fn results(&self, key: &str) -> StatResults {
         if let Some(collector) = self.0.get(key) {
@@ -180,4 +190,13 @@ fn usage() {
     assert_eq!(jsonstats, expected);
 
     assert_eq!(stats.results("Layer.layerx").mean, 0.0);
+
+    let mut other = Statistics::new();
+    other.add("Layer.layer2".to_string(), 4);
+    other.add("Layer.layer3".to_string(), 1);
+    stats.merge(other);
+    assert_eq!(stats.results("Layer.layer2").len, 2);
+    assert_eq!(stats.results("Layer.layer2").mean, 3.0);
+    assert_eq!(stats.results("Layer.layer2").max, 4);
+    assert_eq!(stats.results("Layer.layer3").len, 1);
 }
@@ -4,6 +4,7 @@
 //
 
 use core::config::DatasourceCfg;
+use core::error;
 use core::feature::Feature;
 use core::grid::Extent;
 use core::grid::Grid;
@@ -16,6 +17,8 @@ pub trait DatasourceInput {
     fn detect_layers(&self, detect_geometry_types: bool) -> Vec<Layer>;
     /// Return column field names and Rust compatible type conversion - without geometry column
     fn detect_data_columns(&self, layer: &Layer, sql: Option<&String>) -> Vec<(String, String)>;
+    /// Return column field names and MVT value types (String, Number, Boolean) - without geometry column
+    fn detect_field_types(&self, layer: &Layer, sql: Option<&String>) -> Vec<(String, String)>;
     fn layer_extent(&self, layer: &Layer, grid_srid: i32) -> Option<Extent>;
     fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32);
     /// Projected extent
@@ -28,9 +31,30 @@ pub trait DatasourceInput {
         zoom: u8,
         grid: &Grid,
         read: F,
-    ) -> u64
+    ) -> error::Result<u64>
     where
         F: FnMut(&Feature);
+    /// Distinct values of the `split_by` attribute of the layer.
+    /// Empty, if the values can't be determined in advance.
+    fn split_values(&self, _layer: &Layer) -> Vec<String> {
+        Vec::new()
+    }
+    /// Check availability of the connected datasource with a cheap request
+    fn check_connection(&self) -> error::Result<()>;
+    /// Extents in grid SRS of features modified since `since` (ISO 8601 timestamp)
+    /// according to the `modified_field` of the layer.
+    /// Only the current geometry is returned, not the geometry before the modification.
+    fn modified_extents(
+        &self,
+        layer: &Layer,
+        _since: &str,
+        _grid_srid: i32,
+    ) -> error::Result<Vec<Extent>> {
+        Err(error::Error::Datasource(format!(
+            "Layer '{}': Datasource doesn't support modification queries",
+            layer.name
+        )))
+    }
 }
 
 pub struct DummyDatasource;
@@ -45,6 +69,9 @@ impl DatasourceInput for DummyDatasource {
     fn detect_data_columns(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
         unimplemented!();
     }
+    fn detect_field_types(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        unimplemented!();
+    }
     fn extent_from_wgs84(&self, _extent: &Extent, _dest_srid: i32) -> Option<Extent> {
         unimplemented!();
     }
@@ -59,11 +86,14 @@ impl DatasourceInput for DummyDatasource {
         _zoom: u8,
         _grid: &Grid,
         _read: F,
-    ) -> u64
+    ) -> error::Result<u64>
     where
         F: FnMut(&Feature),
     {
-        0
+        Ok(0)
+    }
+    fn check_connection(&self) -> error::Result<()> {
+        Ok(())
     }
 }
 
@@ -0,0 +1,798 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Native GeoPackage datasource
+//!
+//! Reads feature tables of a GeoPackage file (http://www.geopackage.org/spec/)
+//! with SQLite, without linking GDAL. Geometries are reprojected between
+//! WGS84 and Web Mercator, other SRS have to match the grid SRS.
+
+use core::config::DatasourceCfg;
+use core::error;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{self, GeometryType};
+use core::grid::{extent_from_merc, extent_to_merc, lonlat_to_merc, merc_to_lonlat, Extent, Grid};
+use core::layer::Layer;
+use core::Config;
+use datasource::DatasourceInput;
+use rusqlite::types::{ToSql, Value};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub struct GpkgDatasource {
+    pub path: String,
+    conn: Option<Mutex<Connection>>,
+    /// Feature tables prepared for queries
+    tables: BTreeMap<String, TableInfo>,
+}
+
+/// Primary key, spatial index and boolean columns of a feature table
+#[derive(Clone, Debug)]
+struct TableInfo {
+    primary_key: Option<String>,
+    rtree: Option<String>,
+    bool_columns: Vec<String>,
+}
+
+/// Coordinate transformation from layer SRS to grid SRS
+type Transform = fn(f64, f64) -> (f64, f64);
+
+impl GpkgDatasource {
+    pub fn new(path: &str) -> GpkgDatasource {
+        GpkgDatasource {
+            path: path.to_string(),
+            conn: None,
+            tables: BTreeMap::new(),
+        }
+    }
+    fn open(&self) -> Result<Connection, String> {
+        Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("{}: {}", self.path, e))
+    }
+    fn connection(&self) -> Result<&Mutex<Connection>, error::Error> {
+        self.conn.as_ref().ok_or_else(|| {
+            error::Error::Datasource(format!("GeoPackage '{}' not opened", self.path))
+        })
+    }
+}
+
+/// Geometry type name as used in layer configuration
+fn geom_type_name(gpkg_type: &str) -> Option<String> {
+    match &gpkg_type.to_uppercase() as &str {
+        "POINT" | "MULTIPOINT" => Some("POINT".to_string()),
+        "LINESTRING" | "MULTILINESTRING" => Some("LINE".to_string()),
+        "POLYGON" | "MULTIPOLYGON" => Some("POLYGON".to_string()),
+        _ => None,
+    }
+}
+
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Primary key column of table
+fn primary_key(conn: &Connection, table: &str) -> Option<String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", quote_ident(table)))
+        .ok()?;
+    let mut rows = stmt.query(&[]).ok()?;
+    while let Some(Ok(row)) = rows.next() {
+        let pk: i64 = row.get(5);
+        if pk > 0 {
+            return Some(row.get(1));
+        }
+    }
+    None
+}
+
+/// Name of RTree spatial index table, if existing
+fn rtree_table(conn: &Connection, table: &str, geometry_field: &str) -> Option<String> {
+    let name = format!("rtree_{}_{}", table, geometry_field);
+    let cnt: i64 = conn
+        .query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            &[&name as &ToSql],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    if cnt > 0 {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Columns declared as BOOLEAN, stored as integer 0 or 1
+fn bool_columns(conn: &Connection, table: &str) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut stmt = match conn.prepare(&format!("PRAGMA table_info({})", quote_ident(table))) {
+        Ok(stmt) => stmt,
+        Err(_) => return columns,
+    };
+    if let Ok(rows) = stmt.query_map(&[], |row| {
+        (row.get::<_, String>(1), row.get::<_, String>(2))
+    }) {
+        for row in rows {
+            if let Ok((name, decl_type)) = row {
+                if decl_type.to_uppercase() == "BOOLEAN" {
+                    columns.push(name);
+                }
+            }
+        }
+    }
+    columns
+}
+
+fn table_info(conn: &Connection, table: &str, geometry_field: &str) -> TableInfo {
+    TableInfo {
+        primary_key: primary_key(conn, table),
+        rtree: rtree_table(conn, table, geometry_field),
+        bool_columns: bool_columns(conn, table),
+    }
+}
+
+/// Transformation of coordinates in `src_srid` into `dest_srid`.
+/// Returns `Ok(None)` for identical SRS.
+fn transformation(src_srid: i32, dest_srid: i32) -> Result<Option<Transform>, String> {
+    match (src_srid, dest_srid) {
+        (src, dest) if src == dest => Ok(None),
+        (4326, 3857) => Ok(Some(lonlat_to_merc)),
+        (3857, 4326) => Ok(Some(merc_to_lonlat)),
+        (src, dest) => Err(format!(
+            "Reprojection from SRID {} to {} not supported for GeoPackage layers",
+            src, dest
+        )),
+    }
+}
+
+/// Reader for WKB geometries (XY only)
+struct WkbReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    srid: Option<i32>,
+    transform: Option<Transform>,
+}
+
+impl<'a> WkbReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.pos + len > self.data.len() {
+            return Err("Unexpected end of WKB geometry".to_string());
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn read_u32(&mut self, little_endian: bool) -> Result<u32, String> {
+        let b = self.read_bytes(4)?;
+        Ok(if little_endian {
+            (b[3] as u32) << 24 | (b[2] as u32) << 16 | (b[1] as u32) << 8 | b[0] as u32
+        } else {
+            (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+        })
+    }
+    fn read_f64(&mut self, little_endian: bool) -> Result<f64, String> {
+        let b = self.read_bytes(8)?;
+        let mut bits = 0u64;
+        for i in 0..8 {
+            let byte = if little_endian { b[7 - i] } else { b[i] };
+            bits = bits << 8 | byte as u64;
+        }
+        Ok(f64::from_bits(bits))
+    }
+    /// Read byte order and geometry type. Returns (little_endian, type, number of ordinates)
+    fn read_header(&mut self) -> Result<(bool, u32, usize), String> {
+        let little_endian = self.read_bytes(1)?[0] == 1;
+        let code = self.read_u32(little_endian)?;
+        // EWKB flags
+        let mut dims = 2;
+        if code & 0x8000_0000 != 0 {
+            dims += 1;
+        }
+        if code & 0x4000_0000 != 0 {
+            dims += 1;
+        }
+        if code & 0x2000_0000 != 0 {
+            // Embedded SRID
+            self.read_u32(little_endian)?;
+        }
+        // ISO WKB: 1000 (Z), 2000 (M), 3000 (ZM)
+        let code = code & 0x0fff_ffff;
+        dims += match code / 1000 {
+            1 | 2 => 1,
+            3 => 2,
+            _ => 0,
+        };
+        Ok((little_endian, code % 1000, dims))
+    }
+    fn read_point_coords(
+        &mut self,
+        little_endian: bool,
+        dims: usize,
+    ) -> Result<geom::Point, String> {
+        let x = self.read_f64(little_endian)?;
+        let y = self.read_f64(little_endian)?;
+        for _ in 2..dims {
+            self.read_f64(little_endian)?;
+        }
+        let (x, y) = match self.transform {
+            Some(transform) => transform(x, y),
+            None => (x, y),
+        };
+        Ok(geom::Point::new(x, y, self.srid))
+    }
+    fn read_line_coords(
+        &mut self,
+        little_endian: bool,
+        dims: usize,
+    ) -> Result<geom::LineString, String> {
+        let n = self.read_u32(little_endian)?;
+        let points = (0..n)
+            .map(|_| self.read_point_coords(little_endian, dims))
+            .collect::<Result<_, _>>()?;
+        Ok(geom::LineString {
+            points,
+            srid: self.srid,
+        })
+    }
+    fn read_polygon_coords(
+        &mut self,
+        little_endian: bool,
+        dims: usize,
+    ) -> Result<geom::Polygon, String> {
+        let n = self.read_u32(little_endian)?;
+        let rings = (0..n)
+            .map(|_| self.read_line_coords(little_endian, dims))
+            .collect::<Result<_, _>>()?;
+        Ok(geom::Polygon {
+            rings,
+            srid: self.srid,
+        })
+    }
+    /// Read member geometry of multi geometry with expected type
+    fn read_member(&mut self, geom_type: u32) -> Result<(bool, usize), String> {
+        let (little_endian, member_type, dims) = self.read_header()?;
+        if member_type != geom_type {
+            return Err(format!(
+                "Unexpected geometry type {} in multi geometry",
+                member_type
+            ));
+        }
+        Ok((little_endian, dims))
+    }
+    fn read_geometry(&mut self) -> Result<GeometryType, String> {
+        let (little_endian, geom_type, dims) = self.read_header()?;
+        match geom_type {
+            1 => Ok(GeometryType::Point(
+                self.read_point_coords(little_endian, dims)?,
+            )),
+            2 => Ok(GeometryType::LineString(
+                self.read_line_coords(little_endian, dims)?,
+            )),
+            3 => Ok(GeometryType::Polygon(
+                self.read_polygon_coords(little_endian, dims)?,
+            )),
+            4 => {
+                let n = self.read_u32(little_endian)?;
+                let mut points = Vec::new();
+                for _ in 0..n {
+                    let (le, dims) = self.read_member(1)?;
+                    points.push(self.read_point_coords(le, dims)?);
+                }
+                Ok(GeometryType::MultiPoint(geom::MultiPoint {
+                    points,
+                    srid: self.srid,
+                }))
+            }
+            5 => {
+                let n = self.read_u32(little_endian)?;
+                let mut lines = Vec::new();
+                for _ in 0..n {
+                    let (le, dims) = self.read_member(2)?;
+                    lines.push(self.read_line_coords(le, dims)?);
+                }
+                Ok(GeometryType::MultiLineString(geom::MultiLineString {
+                    lines,
+                    srid: self.srid,
+                }))
+            }
+            6 => {
+                let n = self.read_u32(little_endian)?;
+                let mut polygons = Vec::new();
+                for _ in 0..n {
+                    let (le, dims) = self.read_member(3)?;
+                    polygons.push(self.read_polygon_coords(le, dims)?);
+                }
+                Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+                    polygons,
+                    srid: self.srid,
+                }))
+            }
+            _ => Err(format!("Unsupported WKB geometry type {}", geom_type)),
+        }
+    }
+}
+
+/// Size of the envelope in the GeoPackage geometry header
+fn envelope_size(blob: &[u8]) -> Result<usize, String> {
+    if blob.len() < 8 || &blob[0..2] != b"GP" {
+        return Err("Invalid GeoPackage geometry header".to_string());
+    }
+    match (blob[3] >> 1) & 0x07 {
+        0 => Ok(0),
+        1 => Ok(32),
+        2 | 3 => Ok(48),
+        4 => Ok(64),
+        _ => Err("Invalid GeoPackage envelope indicator".to_string()),
+    }
+}
+
+/// Envelope of GeoPackage geometry blob, if contained in the header
+pub fn gpkg_envelope(blob: &[u8]) -> Option<Extent> {
+    if envelope_size(blob).ok()? == 0 {
+        return None;
+    }
+    let mut reader = WkbReader {
+        data: blob,
+        pos: 8,
+        srid: None,
+        transform: None,
+    };
+    let little_endian = blob[3] & 0x01 != 0;
+    // minx, maxx, miny, maxy
+    let minx = reader.read_f64(little_endian).ok()?;
+    let maxx = reader.read_f64(little_endian).ok()?;
+    let miny = reader.read_f64(little_endian).ok()?;
+    let maxy = reader.read_f64(little_endian).ok()?;
+    Some(Extent {
+        minx,
+        miny,
+        maxx,
+        maxy,
+    })
+}
+
+/// Decode GeoPackage geometry blob (header followed by WKB geometry)
+pub fn gpkg_geometry(blob: &[u8], srid: Option<i32>) -> Result<GeometryType, String> {
+    read_gpkg_geometry(blob, srid, None)
+}
+
+fn read_gpkg_geometry(
+    blob: &[u8],
+    srid: Option<i32>,
+    transform: Option<Transform>,
+) -> Result<GeometryType, String> {
+    let envelope_size = envelope_size(blob)?;
+    if blob[3] & 0x10 != 0 {
+        return Err("Empty geometry".to_string());
+    }
+    let mut reader = WkbReader {
+        data: blob,
+        pos: 8 + envelope_size,
+        srid,
+        transform,
+    };
+    reader.read_geometry()
+}
+
+fn intersects(a: &Extent, b: &Extent) -> bool {
+    a.minx <= b.maxx && a.maxx >= b.minx && a.miny <= b.maxy && a.maxy >= b.miny
+}
+
+/// Feature read from GeoPackage table
+struct GpkgFeature {
+    fid: Option<u64>,
+    attributes: Vec<FeatureAttr>,
+    geometry: Option<Vec<u8>>,
+    srid: Option<i32>,
+    /// Transformation into grid SRS
+    transform: Option<Transform>,
+    /// Buffered tile extent for clipping
+    clip: Option<Extent>,
+}
+
+impl Feature for GpkgFeature {
+    fn fid(&self) -> Option<u64> {
+        self.fid
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        self.attributes.clone()
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        match self.geometry {
+            Some(ref blob) => {
+                read_gpkg_geometry(blob, self.srid, self.transform).map(|mut geom| {
+                    if let Some(ref extent) = self.clip {
+                        geom.clip(extent);
+                    }
+                    geom
+                })
+            }
+            None => Err("Feature without geometry".to_string()),
+        }
+    }
+}
+
+fn attr_value(value: Value, boolean: bool) -> Option<FeatureAttrValType> {
+    match value {
+        Value::Integer(v) if boolean => Some(FeatureAttrValType::Bool(v != 0)),
+        Value::Integer(v) => Some(FeatureAttrValType::Int(v)),
+        Value::Real(v) => Some(FeatureAttrValType::Double(v)),
+        Value::Text(v) => Some(FeatureAttrValType::String(v)),
+        // null and blobs are skipped
+        _ => None,
+    }
+}
+
+impl DatasourceInput for GpkgDatasource {
+    fn connected(&self) -> GpkgDatasource {
+        let conn = match self.open() {
+            Ok(conn) => Some(Mutex::new(conn)),
+            Err(e) => {
+                error!("GeoPackage {}", e);
+                None
+            }
+        };
+        GpkgDatasource {
+            path: self.path.clone(),
+            conn,
+            tables: self.tables.clone(),
+        }
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        let mut layers: Vec<Layer> = Vec::new();
+        let conn = match self.open() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("GeoPackage {}", e);
+                return layers;
+            }
+        };
+        let sql = "SELECT c.table_name, g.column_name, g.geometry_type_name, \
+                   COALESCE(s.organization_coordsys_id, g.srs_id) \
+                   FROM gpkg_contents c \
+                   JOIN gpkg_geometry_columns g ON g.table_name = c.table_name \
+                   LEFT JOIN gpkg_spatial_ref_sys s ON s.srs_id = g.srs_id \
+                   WHERE c.data_type = 'features' \
+                   ORDER BY c.table_name";
+        let mut stmt = match conn.prepare(sql) {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                error!("GeoPackage {}: {}", self.path, e);
+                return layers;
+            }
+        };
+        let rows = stmt.query_map(&[], |row| {
+            (
+                row.get::<_, String>(0),
+                row.get::<_, String>(1),
+                row.get::<_, String>(2),
+                row.get::<_, i64>(3),
+            )
+        });
+        if let Ok(rows) = rows {
+            for row in rows {
+                if let Ok((table_name, geometry_field, geometry_type, srid)) = row {
+                    let mut layer = Layer::new(&table_name);
+                    layer.table_name = Some(table_name.clone());
+                    layer.geometry_field = Some(geometry_field);
+                    layer.geometry_type = geom_type_name(&geometry_type);
+                    if srid > 0 {
+                        layer.srid = Some(srid as i32);
+                    }
+                    layer.fid_field = primary_key(&conn, &table_name);
+                    layers.push(layer);
+                }
+            }
+        }
+        layers
+    }
+    /// Return column field names and Rust compatible type conversion - without geometry column
+    fn detect_data_columns(&self, layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        self.detect_field_types(layer, None)
+            .into_iter()
+            .map(|(name, _)| (name, String::new()))
+            .collect()
+    }
+    fn detect_field_types(&self, layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        let conn = match self.open() {
+            Ok(conn) => conn,
+            Err(_) => return Vec::new(),
+        };
+        let table_name = match layer.table_name {
+            Some(ref table_name) => table_name,
+            None => return Vec::new(),
+        };
+        let mut stmt =
+            match conn.prepare(&format!("PRAGMA table_info({})", quote_ident(table_name))) {
+                Ok(stmt) => stmt,
+                Err(_) => return Vec::new(),
+            };
+        let rows = stmt.query_map(&[], |row| {
+            (row.get::<_, String>(1), row.get::<_, String>(2))
+        });
+        let mut fields = Vec::new();
+        if let Ok(rows) = rows {
+            for row in rows {
+                if let Ok((name, decl_type)) = row {
+                    if layer.geometry_field.as_ref() == Some(&name)
+                        || layer.fid_field.as_ref() == Some(&name)
+                    {
+                        continue;
+                    }
+                    let decl_type = decl_type.to_uppercase();
+                    let field_type = if decl_type == "BOOLEAN" {
+                        "Boolean"
+                    } else if decl_type.contains("INT")
+                        || decl_type == "REAL"
+                        || decl_type == "FLOAT"
+                        || decl_type == "DOUBLE"
+                    {
+                        "Number"
+                    } else {
+                        "String"
+                    };
+                    fields.push((name, field_type.to_string()));
+                }
+            }
+        }
+        fields
+    }
+    /// Projected extent
+    fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
+        match dest_srid {
+            4326 => Some(extent.clone()),
+            3857 => Some(extent_to_merc(extent)),
+            _ => None,
+        }
+    }
+    fn layer_extent(&self, layer: &Layer, _grid_srid: i32) -> Option<Extent> {
+        let conn = self.open().ok()?;
+        let table_name = layer.table_name.as_ref()?;
+        let extent = conn
+            .query_row(
+                "SELECT min_x, min_y, max_x, max_y FROM gpkg_contents WHERE table_name = ?1",
+                &[table_name as &ToSql],
+                |row| {
+                    Some(Extent {
+                        minx: row.get_checked(0).ok()?,
+                        miny: row.get_checked(1).ok()?,
+                        maxx: row.get_checked(2).ok()?,
+                        maxy: row.get_checked(3).ok()?,
+                    })
+                },
+            )
+            .ok()??;
+        match layer.srid {
+            Some(4326) => Some(extent),
+            Some(3857) => Some(extent_from_merc(&extent)),
+            _ => {
+                warn!(
+                    "Layer '{}': Extent not supported for SRID {:?}",
+                    layer.name, layer.srid
+                );
+                None
+            }
+        }
+    }
+    fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
+        let srid = layer.srid.unwrap_or(grid_srid);
+        if !layer.no_transform {
+            if let Err(e) = transformation(srid, grid_srid) {
+                warn!("Layer '{}': {}", layer.name, e);
+            }
+        }
+        if layer.query.iter().any(|q| q.sql.is_some()) {
+            warn!(
+                "Layer '{}': SQL queries not supported for GeoPackage layers",
+                layer.name
+            );
+        }
+        let geometry_field = match layer.geometry_field {
+            Some(ref geometry_field) => geometry_field,
+            None => return,
+        };
+        let conn = match self.open() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("GeoPackage {}", e);
+                return;
+            }
+        };
+        let table_names = layer
+            .table_name
+            .iter()
+            .chain(layer.query.iter().filter_map(|q| q.table_name.as_ref()));
+        for table_name in table_names {
+            let info = table_info(&conn, table_name, geometry_field);
+            if info.rtree.is_none() {
+                warn!(
+                    "Layer '{}': No spatial index found for table '{}'",
+                    layer.name, table_name
+                );
+            }
+            self.tables.insert(table_name.clone(), info);
+        }
+        if layer.simplify && layer.geometry_type != Some("POINT".to_string()) {
+            warn!(
+                "Layer '{}': Simplification not supported for GeoPackage layers",
+                layer.name
+            );
+        }
+        if layer.sort_by.is_some() {
+            warn!(
+                "Layer '{}': Sorting with sort_by not supported for GeoPackage layers",
+                layer.name
+            );
+        }
+    }
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) -> error::Result<u64>
+    where
+        F: FnMut(&Feature),
+    {
+        let conn = self.connection()?.lock().unwrap();
+        let table_name = layer.table_name_at(zoom).ok_or_else(|| {
+            error::Error::Datasource(format!("Layer '{}': table_name missing", layer.name))
+        })?;
+        let geometry_field = layer.geometry_field.as_ref().ok_or_else(|| {
+            error::Error::Datasource(format!("Layer '{}': geometry_field missing", layer.name))
+        })?;
+        let query_error = |e: String| error::Error::Query(format!("Layer '{}': {}", layer.name, e));
+        if layer.query(zoom).is_some() {
+            return Err(query_error(
+                "SQL queries not supported for GeoPackage layers".to_string(),
+            ));
+        }
+        let transform = if layer.no_transform {
+            None
+        } else {
+            transformation(layer.srid.unwrap_or(grid.srid), grid.srid).map_err(&query_error)?
+        };
+
+        let bbox_extent = if let Some(pixels) = layer.buffer_size {
+            let buf = f64::from(pixels) * grid.pixel_width(zoom);
+            Extent {
+                minx: extent.minx - buf,
+                miny: extent.miny - buf,
+                maxx: extent.maxx + buf,
+                maxy: extent.maxy + buf,
+            }
+        } else {
+            extent.clone()
+        };
+        let clip_extent = if layer.buffer_size.is_some() {
+            Some(bbox_extent.clone())
+        } else {
+            None
+        };
+        // Query extent in layer SRS
+        let bbox_extent = match (transform.is_some(), grid.srid) {
+            (true, 3857) => extent_from_merc(&bbox_extent),
+            (true, _) => extent_to_merc(&bbox_extent),
+            (false, _) => bbox_extent,
+        };
+        let info = match self.tables.get(table_name) {
+            Some(info) => info.clone(),
+            None => table_info(&conn, table_name, geometry_field),
+        };
+        let mut sql = format!("SELECT * FROM {}", quote_ident(table_name));
+        let mut params: Vec<f64> = Vec::new();
+        if let Some(ref rtree) = info.rtree {
+            // RTree ids are the rowids of the features
+            let pk = info
+                .primary_key
+                .as_ref()
+                .map_or("rowid".to_string(), |pk| quote_ident(pk));
+            sql.push_str(&format!(
+                " WHERE {} IN (SELECT id FROM {} WHERE minx <= ?1 AND maxx >= ?2 AND miny <= ?3 AND maxy >= ?4)",
+                pk,
+                quote_ident(rtree)
+            ));
+            params = vec![
+                bbox_extent.maxx,
+                bbox_extent.minx,
+                bbox_extent.maxy,
+                bbox_extent.miny,
+            ];
+        }
+        if let Some(limit) = layer.query_limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        debug!("retrieve_features layer {}: {}", layer.name, sql);
+
+        let mut stmt = conn.prepare(&sql).map_err(|e| query_error(e.to_string()))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let params: Vec<&ToSql> = params.iter().map(|p| p as &ToSql).collect();
+        let mut rows = stmt
+            .query(&params)
+            .map_err(|e| query_error(e.to_string()))?;
+        let mut cnt = 0;
+        while let Some(row) = rows.next() {
+            let row = row.map_err(|e| query_error(e.to_string()))?;
+            let mut feature = GpkgFeature {
+                fid: None,
+                attributes: Vec::new(),
+                geometry: None,
+                srid: Some(grid.srid),
+                transform,
+                clip: clip_extent.clone(),
+            };
+            for (idx, column) in columns.iter().enumerate() {
+                let value: Value = row
+                    .get_checked(idx)
+                    .map_err(|e| query_error(e.to_string()))?;
+                if column == geometry_field {
+                    if let Value::Blob(blob) = value {
+                        feature.geometry = Some(blob);
+                    }
+                } else if layer.fid_field.as_ref() == Some(column) {
+                    feature.fid = attr_value(value, false).and_then(|val| val.as_fid());
+                } else if let Some(val) = attr_value(value, info.bool_columns.contains(column)) {
+                    feature.attributes.push(FeatureAttr {
+                        key: column.clone(),
+                        value: val,
+                    });
+                }
+            }
+            // Without spatial index, features are filtered by the envelope in the geometry header
+            if info.rtree.is_none() {
+                let envelope = feature
+                    .geometry
+                    .as_ref()
+                    .and_then(|blob| gpkg_envelope(blob));
+                if let Some(envelope) = envelope {
+                    if !intersects(&envelope, &bbox_extent) {
+                        continue;
+                    }
+                }
+            }
+            read(&feature);
+            cnt += 1;
+        }
+        if Some(cnt as u32) == layer.query_limit {
+            info!(
+                "Features of layer {} limited to {} (tile query_limit reached, zoom level {})",
+                layer.name, cnt, zoom
+            );
+        }
+        Ok(cnt)
+    }
+    fn check_connection(&self) -> error::Result<()> {
+        let conn = self.connection()?.lock().unwrap();
+        conn.query_row("SELECT 1", &[], |_| ())
+            .map_err(|e| error::Error::Datasource(format!("{}: {}", self.path, e)))
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for GpkgDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        Ok(GpkgDatasource::new(ds_cfg.gpkg.as_ref().unwrap()))
+    }
+    fn gen_config() -> String {
+        let toml = r#"
+[[datasource]]
+name = "gpkg"
+# GeoPackage file
+gpkg = "<filename>"
+"#;
+        toml.to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        format!(
+            r#"
+[[datasource]]
+gpkg = "{}"
+"#,
+            self.path
+        )
+    }
+}
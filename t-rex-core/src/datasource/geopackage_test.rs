@@ -0,0 +1,269 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use core::geom::GeometryType;
+use core::grid::{Extent, Grid};
+use core::layer::{Layer, LayerQuery};
+use datasource::geopackage::{gpkg_envelope, gpkg_geometry, GpkgDatasource};
+use datasource::DatasourceInput;
+
+const GPKG: &str = "../data/natural_earth.gpkg";
+
+#[test]
+fn test_gpkg_geometry() {
+    // Header (little endian, no envelope, SRID 3857) and WKB point
+    let mut blob = vec![b'G', b'P', 0, 1, 0x11, 0x0f, 0, 0];
+    blob.extend_from_slice(&[1, 1, 0, 0, 0]);
+    blob.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0xf0, 0x3f]); // 1.0
+    blob.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x40]); // 2.0
+    match gpkg_geometry(&blob, Some(3857)) {
+        Ok(GeometryType::Point(p)) => assert_eq!((p.x, p.y, p.srid), (1.0, 2.0, Some(3857))),
+        _ => panic!(),
+    }
+
+    // Header with XY envelope and big endian WKB LineString Z (ISO)
+    let mut blob = vec![b'G', b'P', 0, 2, 0, 0, 0x08, 0x34];
+    blob.extend_from_slice(&[0; 32]);
+    blob.extend_from_slice(&[0, 0, 0, 0x03, 0xea, 0, 0, 0, 2]);
+    for coord in &[0x3ff0u16, 0x4000, 0x4008, 0x4010, 0x4014, 0x4018] {
+        blob.extend_from_slice(&[(coord >> 8) as u8, *coord as u8, 0, 0, 0, 0, 0, 0]);
+    }
+    match gpkg_geometry(&blob, None) {
+        Ok(GeometryType::LineString(l)) => {
+            assert_eq!(l.points.len(), 2);
+            assert_eq!((l.points[1].x, l.points[1].y), (4.0, 5.0));
+        }
+        _ => panic!(),
+    }
+
+    assert_eq!(
+        gpkg_geometry(&[0, 0, 0, 0], None).err(),
+        Some("Invalid GeoPackage geometry header".to_string())
+    );
+    assert_eq!(
+        gpkg_geometry(&[b'G', b'P', 0, 0x11, 0, 0, 0, 0], None).err(),
+        Some("Empty geometry".to_string())
+    );
+}
+
+#[test]
+fn test_detect_layers() {
+    let ds = GpkgDatasource::new(GPKG);
+    let layers = ds.detect_layers(false);
+    assert_eq!(
+        layers.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(),
+        vec![
+            "ne_10m_populated_places",
+            "ne_10m_rivers_lake_centerlines",
+            "ne_110m_admin_0_countries",
+        ]
+    );
+    let layer = &layers[0];
+    assert_eq!(
+        layer.table_name,
+        Some("ne_10m_populated_places".to_string())
+    );
+    assert_eq!(layer.geometry_field, Some("geom".to_string()));
+    assert_eq!(layer.geometry_type, Some("POINT".to_string()));
+    assert_eq!(layer.srid, Some(3857));
+    assert_eq!(layer.fid_field, Some("fid".to_string()));
+    assert_eq!(layers[1].geometry_type, Some("LINE".to_string()));
+    assert_eq!(layers[2].geometry_type, Some("POLYGON".to_string()));
+
+    assert_eq!(
+        ds.detect_field_types(layer, None),
+        vec![
+            ("SCALERANK".to_string(), "Number".to_string()),
+            ("NAME".to_string(), "String".to_string()),
+            ("POP_MAX".to_string(), "Number".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_retrieve_features() {
+    let mut ds = GpkgDatasource::new(GPKG).connected();
+    let grid = Grid::web_mercator();
+    let mut layer = Layer::new("points");
+    layer.table_name = Some("ne_10m_populated_places".to_string());
+    layer.geometry_field = Some("geom".to_string());
+    layer.geometry_type = Some("POINT".to_string());
+    layer.fid_field = Some("fid".to_string());
+    layer.srid = Some(3857);
+    ds.prepare_queries(&layer, grid.srid);
+
+    // Switzerland
+    let extent = Extent {
+        minx: 620000.0,
+        miny: 5700000.0,
+        maxx: 1200000.0,
+        maxy: 6100000.0,
+    };
+    let mut attrs = Vec::new();
+    let cnt = ds
+        .retrieve_features(&layer, &extent, 8, &grid, |feat| {
+            if feat.fid() == Some(106) {
+                attrs = feat.attributes();
+                match feat.geometry() {
+                    Ok(GeometryType::Point(p)) => {
+                        assert_eq!((p.x.round(), p.y.round()), (817642.0, 6002678.0))
+                    }
+                    _ => panic!(),
+                }
+            }
+        })
+        .unwrap();
+    assert_eq!(cnt, 36);
+    assert_eq!(attrs.len(), 3);
+    assert_eq!(attrs[1].key, "NAME");
+    assert_eq!(
+        attrs[1].value,
+        FeatureAttrValType::String("Delemont".to_string())
+    );
+
+    layer.query_limit = Some(5);
+    let cnt = ds
+        .retrieve_features(&layer, &extent, 8, &grid, |_| {})
+        .unwrap();
+    assert_eq!(cnt, 5);
+
+    let extent = ds.layer_extent(&layer, 3857).unwrap();
+    assert!(extent.minx < -179.0 && extent.maxx > 179.0);
+}
+
+#[test]
+fn test_reprojection() {
+    let mut ds = GpkgDatasource::new(GPKG).connected();
+    let grid = Grid::wgs84();
+    let mut layer = Layer::new("points");
+    layer.table_name = Some("ne_10m_populated_places".to_string());
+    layer.geometry_field = Some("geom".to_string());
+    layer.fid_field = Some("fid".to_string());
+    layer.srid = Some(3857);
+    ds.prepare_queries(&layer, grid.srid);
+
+    // Switzerland
+    let extent = Extent {
+        minx: 5.6,
+        miny: 45.6,
+        maxx: 10.8,
+        maxy: 47.9,
+    };
+    let mut found = false;
+    let cnt = ds
+        .retrieve_features(&layer, &extent, 8, &grid, |feat| {
+            if feat.fid() == Some(106) {
+                found = true;
+                match feat.geometry() {
+                    Ok(GeometryType::Point(p)) => {
+                        assert_eq!(
+                            ((p.x * 1000.0).round(), (p.y * 1000.0).round()),
+                            (7345.0, 47370.0)
+                        );
+                        assert_eq!(p.srid, Some(4326));
+                    }
+                    _ => panic!(),
+                }
+            }
+        })
+        .unwrap();
+    assert!(found);
+    assert!(cnt > 0);
+
+    layer.srid = Some(2056);
+    assert!(ds
+        .retrieve_features(&layer, &extent, 8, &grid, |_| {})
+        .is_err());
+}
+
+#[test]
+fn test_unsupported_sql() {
+    let ds = GpkgDatasource::new(GPKG).connected();
+    let grid = Grid::web_mercator();
+    let mut layer = Layer::new("points");
+    layer.table_name = Some("ne_10m_populated_places".to_string());
+    layer.geometry_field = Some("geom".to_string());
+    layer.query = vec![LayerQuery {
+        minzoom: None,
+        maxzoom: None,
+        table_name: None,
+        sql: Some("SELECT * FROM ne_10m_populated_places".to_string()),
+    }];
+    let extent = grid.tile_extent(0, 0, 0);
+    assert!(ds
+        .retrieve_features(&layer, &extent, 0, &grid, |_| {})
+        .is_err());
+}
+
+#[test]
+fn test_table_without_index() {
+    use rusqlite::Connection;
+    use std::env;
+    use std::fs;
+
+    let mut path = env::temp_dir();
+    path.push("t_rex_gpkg_test.gpkg");
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    // Little endian point geometries with envelope [minx, maxx, miny, maxy]
+    let point = |x: f64, y: f64| {
+        let mut blob = vec![b'G', b'P', 0, 0x03, 0, 0, 0, 0];
+        for v in &[x, x, y, y] {
+            blob.extend_from_slice(&v.to_bits().to_le_bytes());
+        }
+        blob.extend_from_slice(&[1, 1, 0, 0, 0]);
+        blob.extend_from_slice(&x.to_bits().to_le_bytes());
+        blob.extend_from_slice(&y.to_bits().to_le_bytes());
+        blob
+    };
+    {
+        let conn = Connection::open(path).unwrap();
+        conn.execute(
+            "CREATE TABLE places (fid INTEGER PRIMARY KEY, geom POINT, capital BOOLEAN)",
+            &[],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO places (geom, capital) VALUES (?1, 1), (?2, 0)",
+            &[&point(1.0, 2.0), &point(100.0, 200.0)],
+        )
+        .unwrap();
+    }
+    assert_eq!(
+        gpkg_envelope(&point(1.0, 2.0)),
+        Some(Extent {
+            minx: 1.0,
+            miny: 2.0,
+            maxx: 1.0,
+            maxy: 2.0,
+        })
+    );
+
+    let mut ds = GpkgDatasource::new(path).connected();
+    let grid = Grid::web_mercator();
+    let mut layer = Layer::new("places");
+    layer.table_name = Some("places".to_string());
+    layer.geometry_field = Some("geom".to_string());
+    layer.fid_field = Some("fid".to_string());
+    layer.srid = Some(3857);
+    ds.prepare_queries(&layer, grid.srid);
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 10.0,
+        maxy: 10.0,
+    };
+    let mut attrs = Vec::new();
+    let cnt = ds
+        .retrieve_features(&layer, &extent, 10, &grid, |feat| {
+            attrs = feat.attributes();
+        })
+        .unwrap();
+    assert_eq!(cnt, 1);
+    assert_eq!(attrs[0].key, "capital");
+    assert_eq!(attrs[0].value, FeatureAttrValType::Bool(true));
+    let _ = fs::remove_file(path);
+}
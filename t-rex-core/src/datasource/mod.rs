@@ -4,9 +4,19 @@
 //
 
 pub mod datasource;
+#[cfg(feature = "with-geopackage")]
+pub mod geopackage;
+#[cfg(all(test, feature = "with-geopackage"))]
+mod geopackage_test;
+pub mod plugin;
+#[cfg(test)]
+mod plugin_test;
 pub mod postgis;
 #[cfg(test)]
 mod postgis_test;
 
 pub use self::datasource::{DatasourceInput, DummyDatasource};
+#[cfg(feature = "with-geopackage")]
+pub use self::geopackage::GpkgDatasource;
+pub use self::plugin::PluginDatasource;
 pub use self::postgis::PostgisInput;
@@ -0,0 +1,368 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Out-of-process datasource
+//!
+//! A plugin is an executable communicating with t-rex over stdin/stdout.
+//! Messages are JSON documents prefixed with their length as 32 bit
+//! big-endian integer. For every layer of a tile, t-rex sends a request
+//!
+//! ```json
+//! {"type": "features", "layer": "roads", "table_name": "roads",
+//!  "extent": [minx, miny, maxx, maxy], "zoom": 10, "srid": 3857, "limit": null}
+//! ```
+//!
+//! The plugin answers with one message per feature
+//!
+//! ```json
+//! {"id": 1, "properties": {"name": "Main Street"},
+//!  "geometry": {"type": "LineString", "coordinates": [[0, 0], [1, 1]]}}
+//! ```
+//!
+//! with coordinates in the grid SRS, followed by an empty message (length 0).
+//! Errors are reported with `{"error": "message"}` instead of the empty message.
+//! Messages are limited to 64 MB. A plugin sending invalid messages is restarted.
+
+use core::config::DatasourceCfg;
+use core::error;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{self, GeometryType};
+use core::grid::{Extent, Grid};
+use core::layer::Layer;
+use core::Config;
+use datasource::DatasourceInput;
+use serde_json;
+use serde_json::Value;
+use std::io::{self, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+/// Maximal length of a message in bytes
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+pub struct PluginDatasource {
+    /// Plugin command line
+    pub command: String,
+    process: Option<Mutex<PluginProcess>>,
+}
+
+struct PluginProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl PluginDatasource {
+    pub fn new(command: &str) -> PluginDatasource {
+        PluginDatasource {
+            command: command.to_string(),
+            process: None,
+        }
+    }
+    fn spawn(&self) -> io::Result<PluginProcess> {
+        let mut args = self.command.split_whitespace();
+        let program = args.next().unwrap_or("");
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        Ok(PluginProcess {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+}
+
+/// Write length-prefixed JSON message
+pub fn write_message(out: &mut Write, msg: &Value) -> io::Result<()> {
+    let data = serde_json::to_vec(msg)?;
+    let len = data.len() as u32;
+    out.write_all(&[
+        (len >> 24) as u8,
+        (len >> 16) as u8,
+        (len >> 8) as u8,
+        len as u8,
+    ])?;
+    out.write_all(&data)?;
+    out.flush()
+}
+
+/// Read length-prefixed JSON message. Returns `None` for an empty message.
+pub fn read_message(input: &mut Read) -> io::Result<Option<Value>> {
+    let mut len = [0u8; 4];
+    input.read_exact(&mut len)?;
+    let len = (len[0] as usize) << 24
+        | (len[1] as usize) << 16
+        | (len[2] as usize) << 8
+        | len[3] as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message length {} exceeds {} bytes", len, MAX_MESSAGE_LEN),
+        ));
+    }
+    let mut data = vec![0; len];
+    input.read_exact(&mut data)?;
+    serde_json::from_slice(&data)
+        .map(Some)
+        .map_err(|e| e.into())
+}
+
+fn point(coord: &Value, srid: Option<i32>) -> Result<geom::Point, String> {
+    let xy = coord.as_array().map(|c| {
+        (
+            c.get(0).and_then(Value::as_f64),
+            c.get(1).and_then(Value::as_f64),
+        )
+    });
+    match xy {
+        Some((Some(x), Some(y))) => Ok(geom::Point::new(x, y, srid)),
+        _ => Err(format!("Invalid coordinate {}", coord)),
+    }
+}
+
+fn array(coords: &Value) -> Result<&Vec<Value>, String> {
+    coords
+        .as_array()
+        .ok_or(format!("Invalid coordinates {}", coords))
+}
+
+fn points(coords: &Value, srid: Option<i32>) -> Result<Vec<geom::Point>, String> {
+    array(coords)?.iter().map(|c| point(c, srid)).collect()
+}
+
+fn line(coords: &Value, srid: Option<i32>) -> Result<geom::LineString, String> {
+    Ok(geom::LineString {
+        points: points(coords, srid)?,
+        srid,
+    })
+}
+
+fn polygon(coords: &Value, srid: Option<i32>) -> Result<geom::Polygon, String> {
+    Ok(geom::Polygon {
+        rings: array(coords)?
+            .iter()
+            .map(|r| line(r, srid))
+            .collect::<Result<_, _>>()?,
+        srid,
+    })
+}
+
+/// Convert GeoJSON geometry
+pub fn geojson_geometry(geometry: &Value, srid: Option<i32>) -> Result<GeometryType, String> {
+    let coords = geometry
+        .get("coordinates")
+        .ok_or("Geometry without coordinates".to_string())?;
+    match geometry.get("type").and_then(Value::as_str) {
+        Some("Point") => Ok(GeometryType::Point(point(coords, srid)?)),
+        Some("LineString") => Ok(GeometryType::LineString(line(coords, srid)?)),
+        Some("Polygon") => Ok(GeometryType::Polygon(polygon(coords, srid)?)),
+        Some("MultiPoint") => Ok(GeometryType::MultiPoint(geom::MultiPoint {
+            points: points(coords, srid)?,
+            srid,
+        })),
+        Some("MultiLineString") => Ok(GeometryType::MultiLineString(geom::MultiLineString {
+            lines: array(coords)?
+                .iter()
+                .map(|l| line(l, srid))
+                .collect::<Result<_, _>>()?,
+            srid,
+        })),
+        Some("MultiPolygon") => Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+            polygons: array(coords)?
+                .iter()
+                .map(|p| polygon(p, srid))
+                .collect::<Result<_, _>>()?,
+            srid,
+        })),
+        Some(geomtype) => Err(format!("Unsupported geometry type {}", geomtype)),
+        None => Err("Geometry without type".to_string()),
+    }
+}
+
+fn attr_value(value: &Value) -> Option<FeatureAttrValType> {
+    match *value {
+        Value::String(ref v) => Some(FeatureAttrValType::String(v.clone())),
+        Value::Bool(v) => Some(FeatureAttrValType::Bool(v)),
+        Value::Number(ref v) => {
+            if let Some(i) = v.as_i64() {
+                Some(FeatureAttrValType::Int(i))
+            } else if let Some(u) = v.as_u64() {
+                Some(FeatureAttrValType::UInt(u))
+            } else {
+                v.as_f64().map(FeatureAttrValType::Double)
+            }
+        }
+        // null, arrays and objects are skipped
+        _ => None,
+    }
+}
+
+/// Feature received from plugin
+pub struct PluginFeature<'a> {
+    pub json: &'a Value,
+    pub srid: Option<i32>,
+}
+
+impl<'a> Feature for PluginFeature<'a> {
+    fn fid(&self) -> Option<u64> {
+        self.json.get("id").and_then(Value::as_u64)
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        match self.json.get("properties").and_then(Value::as_object) {
+            Some(props) => props
+                .iter()
+                .filter_map(|(key, val)| {
+                    attr_value(val).map(|value| FeatureAttr {
+                        key: key.clone(),
+                        value,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        let geometry = self
+            .json
+            .get("geometry")
+            .ok_or("Feature without geometry".to_string())?;
+        geojson_geometry(geometry, self.srid)
+    }
+}
+
+impl DatasourceInput for PluginDatasource {
+    fn connected(&self) -> PluginDatasource {
+        let process = match self.spawn() {
+            Ok(process) => Some(Mutex::new(process)),
+            Err(e) => {
+                error!("Plugin '{}': {}", self.command, e);
+                None
+            }
+        };
+        PluginDatasource {
+            command: self.command.clone(),
+            process,
+        }
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        warn!("Layer detection not supported by plugin datasources");
+        Vec::new()
+    }
+    fn detect_data_columns(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    fn detect_field_types(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    fn extent_from_wgs84(&self, _extent: &Extent, _dest_srid: i32) -> Option<Extent> {
+        None
+    }
+    fn layer_extent(&self, _layer: &Layer, _grid_srid: i32) -> Option<Extent> {
+        None
+    }
+    fn prepare_queries(&mut self, _layer: &Layer, _grid_srid: i32) {}
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) -> error::Result<u64>
+    where
+        F: FnMut(&Feature),
+    {
+        let process = self.process.as_ref().ok_or_else(|| {
+            error::Error::Datasource(format!("Plugin '{}' not running", self.command))
+        })?;
+        let mut process = process.lock().unwrap();
+        let query_error =
+            |e: io::Error| error::Error::Query(format!("Layer '{}': {}", layer.name, e));
+        let request = json!({
+            "type": "features",
+            "layer": layer.name,
+            "table_name": layer.table_name,
+            "extent": [extent.minx, extent.miny, extent.maxx, extent.maxy],
+            "zoom": zoom,
+            "srid": grid.srid,
+            "limit": layer.query_limit,
+        });
+        let mut cnt = 0;
+        let mut plugin_error = None;
+        let result = write_message(&mut process.stdin, &request).and_then(|_| {
+            while let Some(msg) = read_message(&mut process.stdout)? {
+                if let Some(err) = msg.get("error") {
+                    plugin_error = Some(err.to_string());
+                    break;
+                }
+                let feature = PluginFeature {
+                    json: &msg,
+                    srid: Some(grid.srid),
+                };
+                read(&feature);
+                cnt += 1;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            // Unread messages would be taken as answer of the next request
+            warn!("Plugin '{}': restarting after protocol error", self.command);
+            match self.spawn() {
+                Ok(restarted) => *process = restarted,
+                Err(e) => error!("Plugin '{}': {}", self.command, e),
+            }
+            return Err(query_error(e));
+        }
+        if let Some(err) = plugin_error {
+            return Err(error::Error::Query(format!(
+                "Layer '{}': {}",
+                layer.name, err
+            )));
+        }
+        Ok(cnt)
+    }
+    fn check_connection(&self) -> error::Result<()> {
+        let not_running =
+            || error::Error::Datasource(format!("Plugin '{}' not running", self.command));
+        let process = self.process.as_ref().ok_or_else(&not_running)?;
+        match process.lock().unwrap().child.try_wait() {
+            Ok(None) => Ok(()),
+            _ => Err(not_running()),
+        }
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for PluginDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        Ok(PluginDatasource::new(ds_cfg.plugin.as_ref().unwrap()))
+    }
+    fn gen_config() -> String {
+        "".to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        format!(
+            r#"
+[[datasource]]
+plugin = "{}"
+"#,
+            self.command
+        )
+    }
+}
@@ -0,0 +1,92 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{Feature, FeatureAttrValType};
+use core::geom::GeometryType;
+use datasource::plugin::{geojson_geometry, read_message, write_message, PluginFeature};
+
+#[test]
+fn test_message_framing() {
+    let mut buf = Vec::new();
+    write_message(&mut buf, &json!({"layer": "points"})).unwrap();
+    buf.extend_from_slice(&[0, 0, 0, 0]);
+    assert_eq!(&buf[..4], &[0, 0, 0, 18]);
+
+    let mut input = &buf[..];
+    assert_eq!(
+        read_message(&mut input).unwrap(),
+        Some(json!({"layer": "points"}))
+    );
+    assert_eq!(read_message(&mut input).unwrap(), None);
+    assert!(read_message(&mut input).is_err());
+
+    // Length exceeding the limit
+    let mut input = &[0x7f, 0xff, 0xff, 0xff, b'{', b'}'][..];
+    assert!(read_message(&mut input).is_err());
+}
+
+#[test]
+fn test_geojson_geometry() {
+    let geom = geojson_geometry(
+        &json!({"type": "Point", "coordinates": [960000.0, 6002729.0]}),
+        Some(3857),
+    );
+    match geom {
+        Ok(GeometryType::Point(p)) => {
+            assert_eq!((p.x, p.y, p.srid), (960000.0, 6002729.0, Some(3857)))
+        }
+        _ => panic!(),
+    }
+
+    let geom = geojson_geometry(
+        &json!({"type": "MultiPolygon", "coordinates": [[[[0, 0], [1, 0], [1, 1], [0, 0]]]]}),
+        Some(3857),
+    );
+    match geom {
+        Ok(GeometryType::MultiPolygon(mp)) => {
+            assert_eq!(mp.polygons.len(), 1);
+            assert_eq!(mp.polygons[0].rings[0].points.len(), 4);
+        }
+        _ => panic!(),
+    }
+
+    assert_eq!(
+        geojson_geometry(&json!({"type": "Point", "coordinates": [0]}), None).err(),
+        Some("Invalid coordinate [0]".to_string())
+    );
+    assert_eq!(
+        geojson_geometry(&json!({"type": "Curve", "coordinates": []}), None).err(),
+        Some("Unsupported geometry type Curve".to_string())
+    );
+}
+
+#[test]
+fn test_plugin_feature() {
+    let json = json!({
+        "id": 42,
+        "properties": {"name": "Bern", "population": 133115, "capital": true, "note": null},
+        "geometry": {"type": "LineString", "coordinates": [[0, 0], [1, 1]]}
+    });
+    let feature = PluginFeature {
+        json: &json,
+        srid: Some(3857),
+    };
+    assert_eq!(feature.fid(), Some(42));
+    let attrs = feature.attributes();
+    assert_eq!(attrs.len(), 3);
+    assert!(attrs
+        .iter()
+        .any(|a| a.key == "name" && a.value == FeatureAttrValType::String("Bern".to_string())));
+    assert!(attrs
+        .iter()
+        .any(|a| a.key == "population" && a.value == FeatureAttrValType::Int(133115)));
+    assert!(attrs
+        .iter()
+        .any(|a| a.key == "capital" && a.value == FeatureAttrValType::Bool(true)));
+    match feature.geometry() {
+        Ok(GeometryType::LineString(l)) => assert_eq!(l.points.len(), 2),
+        _ => panic!(),
+    }
+}
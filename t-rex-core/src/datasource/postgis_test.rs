@@ -8,7 +8,10 @@ use core::geom::*;
 use core::grid::Extent;
 use core::grid::Grid;
 use core::layer::{Layer, LayerQuery};
-use datasource::postgis::{PostgisInput, QueryParam};
+use core::Config;
+use datasource::postgis::{
+    array_attribute, json_attributes, pem_blocks, PostgisInput, QueryParam, SslMode,
+};
 use datasource::DatasourceInput;
 use postgres;
 use postgres::Connection;
@@ -71,6 +74,27 @@ fn test_detect_layers() {
     );
 }
 
+#[test]
+#[ignore]
+fn test_detect_srid() {
+    let mut pg: PostgisInput = match env::var("DBCONN") {
+        Result::Ok(val) => Some(PostgisInput::new(&val).connected()),
+        Result::Err(_) => panic!("DBCONN undefined"),
+    }.unwrap();
+    let mut layer = Layer::new("points");
+    layer.table_name = Some(String::from("ne.ne_10m_populated_places"));
+    layer.geometry_field = Some(String::from("wkb_geometry"));
+    layer.geometry_type = Some(String::from("POINT"));
+    assert_eq!(pg.detect_srid(&layer), Some(3857));
+    layer.table_name = Some(String::from("\"ne\".\"ne_10m_populated_places\""));
+    assert_eq!(pg.detect_srid(&layer), Some(3857));
+
+    // Reprojection of layer without configured srid
+    pg.prepare_queries(&layer, 4326);
+    let query = pg.build_query(&layer, 4326, None).unwrap();
+    assert!(query.sql.contains("ST_Transform(wkb_geometry,4326)"));
+}
+
 #[test]
 #[ignore]
 fn test_detect_columns() {
@@ -187,6 +211,14 @@ fn test_feature_query() {
         "SELECT geometry FROM osm_place_point WHERE geometry && ST_MakeEnvelope($1,$2,$3,$4,3857)"
     );
 
+    // feature order
+    layer.sort_by = Some("z_order DESC".to_string());
+    assert_eq!(
+        pg.build_query(&layer, 3857, None).unwrap().sql,
+        "SELECT geometry FROM osm_place_point WHERE geometry && ST_MakeEnvelope($1,$2,$3,$4,3857) ORDER BY z_order DESC"
+    );
+    layer.sort_by = None;
+
     layer.simplify = false;
     layer.query_limit = Some(1);
     assert_eq!(
@@ -199,6 +231,7 @@ fn test_feature_query() {
     layer.query = vec![LayerQuery {
         minzoom: Some(0),
         maxzoom: Some(22),
+        table_name: None,
         sql: Some(String::from("SELECT geometry AS geom FROM osm_place_point")),
     }];
     layer.query_limit = None;
@@ -210,6 +243,7 @@ fn test_feature_query() {
     layer.query = vec![LayerQuery {
         minzoom: Some(0),
         maxzoom: Some(22),
+        table_name: None,
         sql: Some(String::from(
             "SELECT * FROM osm_place_point WHERE name='Bern'",
         )),
@@ -235,6 +269,7 @@ fn test_query_params() {
     layer.query = vec![LayerQuery {
                            minzoom: Some(0),
                            maxzoom: Some(22),
+                           table_name: None,
                            sql: Some(String::from("SELECT name, type, 0 as osm_id, ST_Union(geometry) AS way FROM osm_buildings_gen0 WHERE geometry && !bbox!")),
                        }];
     let query = pg
@@ -247,6 +282,7 @@ fn test_query_params() {
     layer.query = vec![LayerQuery {
                            minzoom: Some(0),
                            maxzoom: Some(22),
+                           table_name: None,
                            sql: Some(String::from("SELECT osm_id, geometry, typen FROM landuse_z13toz14n WHERE !zoom! BETWEEN 13 AND 14) AS landuse_z9toz14n")),
                        }];
     let query = pg
@@ -259,6 +295,7 @@ fn test_query_params() {
     layer.query = vec![LayerQuery {
                            minzoom: Some(0),
                            maxzoom: Some(22),
+                           table_name: None,
                            sql: Some(String::from("SELECT name, type, 0 as osm_id, ST_SimplifyPreserveTopology(ST_Union(geometry),!pixel_width!/2) AS way FROM osm_buildings")),
                        }];
     let query = pg
@@ -269,6 +306,68 @@ fn test_query_params() {
     assert_eq!(query.params, [QueryParam::Bbox, QueryParam::PixelWidth]);
 }
 
+#[test]
+fn test_json_attributes() {
+    let json = json!({"name": "Bern", "population": 133115, "capital": true, "ele": 540.5,
+                      "note": null, "names": {"de": "Bern"}});
+    let attrs = json_attributes("tags", json.clone(), None, &[]);
+    assert_eq!(
+        attrs
+            .iter()
+            .map(|a| format!("{}={:?}", a.key, a.value))
+            .collect::<Vec<_>>(),
+        vec![
+            "capital=Bool(true)",
+            "ele=Double(540.5)",
+            "name=String(\"Bern\")",
+            r#"names=String("{\"de\":\"Bern\"}")"#,
+            "population=Int(133115)",
+        ]
+    );
+
+    let keys = vec!["name".to_string(), "other".to_string()];
+    let attrs = json_attributes("tags", json.clone(), Some(&keys), &[]);
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].key, "name");
+
+    // Keys of query columns are not overwritten
+    let attrs = json_attributes("tags", json, Some(&keys), &["name", "tags"]);
+    assert!(attrs.is_empty());
+
+    let attrs = json_attributes("tags", json!("value"), None, &["tags"]);
+    assert_eq!(attrs[0].key, "tags");
+    assert_eq!(
+        attrs[0].value,
+        FeatureAttrValType::String("value".to_string())
+    );
+}
+
+#[test]
+fn test_array_attribute() {
+    let values = vec![
+        Some(FeatureAttrValType::String("a".to_string())),
+        None,
+        Some(FeatureAttrValType::String("c\"d".to_string())),
+    ];
+    assert_eq!(
+        array_attribute(values),
+        FeatureAttrValType::String(r#"["a",null,"c\"d"]"#.to_string())
+    );
+    let values = vec![
+        Some(FeatureAttrValType::Int(1)),
+        Some(FeatureAttrValType::Double(2.5)),
+        Some(FeatureAttrValType::Bool(false)),
+    ];
+    assert_eq!(
+        array_attribute(values),
+        FeatureAttrValType::String("[1,2.5,false]".to_string())
+    );
+    assert_eq!(
+        array_attribute(Vec::new()),
+        FeatureAttrValType::String("[]".to_string())
+    );
+}
+
 #[test]
 #[ignore]
 fn test_retrieve_features() {
@@ -299,12 +398,13 @@ fn test_retrieve_features() {
         assert_eq!(4, feat.attributes().len());
         assert_eq!(None, feat.fid());
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(1, reccnt);
 
     layer.query = vec![LayerQuery {
         minzoom: Some(0),
         maxzoom: Some(22),
+        table_name: None,
         sql: Some(String::from("SELECT * FROM ne.ne_10m_populated_places")),
     }];
     layer.fid_field = Some(String::from("fid"));
@@ -325,7 +425,47 @@ fn test_retrieve_features() {
             FeatureAttrValType::String("Bern".to_string())
         );
         assert_eq!(feat.fid(), Some(6478));
+    }).unwrap();
+}
+
+#[test]
+#[ignore]
+fn test_modified_extents() {
+    let pg: PostgisInput = match env::var("DBCONN") {
+        Result::Ok(val) => Some(PostgisInput::new(&val).connected()),
+        Result::Err(_) => panic!("DBCONN undefined"),
+    }.unwrap();
+
+    let mut layer = Layer::new("points");
+    layer.table_name = Some(String::from("ne.ne_10m_populated_places"));
+    layer.geometry_field = Some(String::from("wkb_geometry"));
+    layer.srid = Some(4326);
+    assert!(pg.modified_extents(&layer, "2000-01-01", 3857).is_err());
+
+    // Every feature is modified now
+    layer.modified_field = Some(String::from("now()"));
+    let extents = pg.modified_extents(&layer, "2000-01-01", 3857).unwrap();
+    assert!(extents.len() > 1000);
+    assert!(extents[0].minx <= extents[0].maxx);
+    let extents = pg.modified_extents(&layer, "2999-01-01", 3857).unwrap();
+    assert!(extents.is_empty());
+}
+
+#[test]
+fn test_modified_extents_queries() {
+    let pg = PostgisInput::new("postgresql://pi@localhost/natural_earth_vectors");
+    let mut layer = Layer::new("points");
+    layer.table_name = Some(String::from("ne.ne_10m_populated_places"));
+    layer.geometry_field = Some(String::from("wkb_geometry"));
+    layer.modified_field = Some(String::from("updated_at"));
+    layer.query.push(LayerQuery {
+        minzoom: Some(10),
+        maxzoom: None,
+        table_name: Some(String::from("ne.ne_10m_populated_places_gen")),
+        sql: None,
     });
+    let err = pg.modified_extents(&layer, "2000-01-01", 3857).unwrap_err();
+    assert!(err.to_string().contains("not supported"));
 }
 
 #[test]
@@ -361,3 +501,90 @@ fn test_tls() {
     //assert!(conn.unwrap().execute("SELECT 1::VARCHAR", &[]).is_ok());
     // Check pg_stat_ssl? https://www.postgresql.org/docs/9.6/static/monitoring-stats.html#PG-STAT-SSL-VIEW
 }
+
+#[test]
+fn test_ssl_mode() {
+    assert_eq!("require".parse::<SslMode>(), Ok(SslMode::Require));
+    assert_eq!(SslMode::Prefer.as_str(), "prefer");
+    assert_eq!(
+        "verify-full".parse::<SslMode>(),
+        Err("Unexpected sslmode 'verify-full'".to_string())
+    );
+
+    let mut pg = PostgisInput::new("postgresql://pi@localhost/osm2vectortiles");
+    pg.ssl_mode = SslMode::Require;
+    pg.ssl_root_cert = Some("root.der".to_string());
+    assert_eq!(
+        pg.gen_runtime_config(),
+        r#"
+[[datasource]]
+dbconn = "postgresql://pi@localhost/osm2vectortiles"
+sslmode = "require"
+sslrootcert = "root.der"
+"#
+    );
+}
+
+#[test]
+fn test_pem_blocks() {
+    let pem = "-----BEGIN CERTIFICATE-----\nAQID\nBA==\n-----END CERTIFICATE-----\n\
+               -----BEGIN CERTIFICATE-----\nBQY=\n-----END CERTIFICATE-----\n";
+    assert_eq!(
+        pem_blocks(pem.as_bytes(), "CERTIFICATE"),
+        Ok(vec![vec![1, 2, 3, 4], vec![5, 6]])
+    );
+    assert_eq!(pem_blocks(pem.as_bytes(), "PRIVATE KEY"), Ok(vec![]));
+    // DER
+    assert_eq!(
+        pem_blocks(&[48, 130, 1], "CERTIFICATE"),
+        Ok(vec![vec![48, 130, 1]])
+    );
+    assert!(pem_blocks(b"-----BEGIN CERTIFICATE-----\nAQID", "CERTIFICATE").is_err());
+}
+
+#[test]
+fn test_tls_config_error() {
+    let mut pg = PostgisInput::new("postgresql://pi@localhost/osm2vectortiles");
+    pg.ssl_mode = SslMode::Require;
+    pg.ssl_root_cert = Some("missing.pem".to_string());
+    let pg = pg.connected();
+    assert_eq!(
+        pg.check_connection().err().map(|e| e.to_string()),
+        Some("Datasource error: Not connected".to_string())
+    );
+}
+
+#[test]
+fn test_invalid_connection_url() {
+    let pg = PostgisInput::new("invalid").connected();
+    assert_eq!(
+        pg.check_connection().err().map(|e| e.to_string()),
+        Some("Datasource error: Not connected".to_string())
+    );
+}
+
+#[test]
+fn test_pool_config() {
+    use core::config::DatasourceCfg;
+    use core::parse_config;
+
+    let from_toml = |toml: &str| {
+        let cfg: DatasourceCfg = parse_config(toml.to_string(), "").unwrap();
+        PostgisInput::from_config(&cfg).map(|pg| (pg.pool_size, pg.pool_min))
+    };
+    let dbconn = "dbconn = \"postgresql://pi@localhost/osm2vectortiles\"\n";
+    assert_eq!(
+        from_toml(&format!("{}pool = 4\npool_min = 2", dbconn)),
+        Ok((Some(4), Some(2)))
+    );
+    assert_eq!(
+        from_toml(&format!("{}pool = 0", dbconn)),
+        Err("Datasource: pool size must be at least 1".to_string())
+    );
+    assert_eq!(
+        from_toml(&format!("{}pool = 4\npool_min = 5", dbconn)),
+        Err("Datasource: pool_min 5 exceeds pool size 4".to_string())
+    );
+    // Default pool size 10
+    assert!(from_toml(&format!("{}pool_min = 11", dbconn)).is_err());
+}
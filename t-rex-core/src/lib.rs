@@ -3,21 +3,41 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+//! Core library of the t-rex vector tile server.
+//!
+//! Contains the building blocks for tile generation: geometries, grids and
+//! layers (`core`), datasource access (`datasource`), Mapbox Vector Tile
+//! encoding (`mvt`) and tile caches (`cache`).
+
+extern crate base64;
+extern crate brotli2;
 extern crate fallible_iterator;
 extern crate flate2;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 extern crate postgis;
 extern crate postgres;
 extern crate protobuf;
 extern crate r2d2;
 extern crate r2d2_postgres;
+#[cfg(feature = "with-redis")]
+extern crate redis;
+#[cfg(any(feature = "with-mbtiles", feature = "with-geopackage"))]
+extern crate rusqlite;
+#[cfg(feature = "with-s3")]
+extern crate rusoto_core;
+#[cfg(feature = "with-s3")]
+extern crate rusoto_s3;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
 extern crate stats;
+#[cfg(feature = "with-s3")]
+extern crate time;
 extern crate toml;
 
 pub mod cache;
@@ -0,0 +1,203 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Decode vector tiles into GeoJSON
+
+use core::grid::Extent;
+use mvt::vector_tile;
+use serde_json::{Map, Value};
+
+pub(crate) type TilePoint = (i32, i32);
+
+/// Decode geometry commands into parts (points, lines or rings) in tile coordinates
+pub(crate) fn decode_parts(commands: &[u32]) -> Vec<Vec<TilePoint>> {
+    let mut parts: Vec<Vec<TilePoint>> = Vec::new();
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut i = 0;
+    while i < commands.len() {
+        let id = commands[i] & 0x7;
+        let count = commands[i] >> 3;
+        i += 1;
+        match id {
+            // MoveTo, LineTo
+            1 | 2 => {
+                for _ in 0..count {
+                    if i + 2 > commands.len() {
+                        return parts;
+                    }
+                    x = x.wrapping_add(zigzag(commands[i]));
+                    y = y.wrapping_add(zigzag(commands[i + 1]));
+                    i += 2;
+                    if id == 1 {
+                        parts.push(vec![(x, y)]);
+                    } else if let Some(part) = parts.last_mut() {
+                        part.push((x, y));
+                    }
+                }
+            }
+            // ClosePath
+            7 => {
+                if let Some(part) = parts.last_mut() {
+                    if let Some(&first) = part.first() {
+                        part.push(first);
+                    }
+                }
+            }
+            _ => return parts,
+        }
+    }
+    parts
+}
+
+fn zigzag(param: u32) -> i32 {
+    ((param >> 1) as i32) ^ (-((param & 1) as i32))
+}
+
+/// Twice the signed area of a ring in tile coordinates
+fn signed_area(ring: &[TilePoint]) -> i64 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let (p1, p2) = (ring[i], ring[(i + 1) % n]);
+            p1.0 as i64 * p2.1 as i64 - p2.0 as i64 * p1.1 as i64
+        })
+        .sum()
+}
+
+/// Single or multi geometry
+fn geometry(geom_type: &str, mut coords: Vec<Value>) -> Option<Value> {
+    match coords.len() {
+        0 => None,
+        1 => Some(json!({"type": geom_type, "coordinates": coords.remove(0)})),
+        _ => Some(json!({"type": format!("Multi{}", geom_type), "coordinates": coords})),
+    }
+}
+
+fn geojson_geometry<F>(feature: &vector_tile::Tile_Feature, coord: F) -> Option<Value>
+where
+    F: Fn(TilePoint) -> Value,
+{
+    let parts = decode_parts(feature.get_geometry());
+    let coords = |part: &Vec<TilePoint>| Value::Array(part.iter().map(|&p| coord(p)).collect());
+    match feature.get_field_type() {
+        vector_tile::Tile_GeomType::POINT => geometry(
+            "Point",
+            parts
+                .iter()
+                .filter_map(|part| part.first())
+                .map(|&p| coord(p))
+                .collect(),
+        ),
+        vector_tile::Tile_GeomType::LINESTRING => {
+            geometry("LineString", parts.iter().map(&coords).collect())
+        }
+        vector_tile::Tile_GeomType::POLYGON => {
+            // The first ring is an exterior ring, following rings with the same
+            // orientation start a new polygon
+            let mut polygons: Vec<Vec<Value>> = Vec::new();
+            let mut exterior_positive = None;
+            for ring in &parts {
+                let area = signed_area(ring);
+                if area == 0 {
+                    continue;
+                }
+                let positive = area > 0;
+                if *exterior_positive.get_or_insert(positive) == positive {
+                    polygons.push(vec![coords(ring)]);
+                } else if let Some(polygon) = polygons.last_mut() {
+                    polygon.push(coords(ring));
+                }
+            }
+            geometry(
+                "Polygon",
+                polygons
+                    .into_iter()
+                    .map(|rings| Value::Array(rings))
+                    .collect(),
+            )
+        }
+        vector_tile::Tile_GeomType::UNKNOWN => None,
+    }
+}
+
+/// Decoded feature attributes
+pub(crate) fn feature_properties(
+    mvt_layer: &vector_tile::Tile_Layer,
+    feature: &vector_tile::Tile_Feature,
+) -> Map<String, Value> {
+    let mut properties = Map::new();
+    for tag in feature.get_tags().chunks(2) {
+        if let (Some(key), Some(value)) = (
+            mvt_layer.get_keys().get(tag[0] as usize),
+            tag.get(1)
+                .and_then(|&idx| mvt_layer.get_values().get(idx as usize)),
+        ) {
+            properties.insert(key.clone(), value_json(value));
+        }
+    }
+    properties
+}
+
+fn value_json(value: &vector_tile::Tile_Value) -> Value {
+    if value.has_string_value() {
+        json!(value.get_string_value())
+    } else if value.has_float_value() {
+        json!(value.get_float_value())
+    } else if value.has_double_value() {
+        json!(value.get_double_value())
+    } else if value.has_int_value() {
+        json!(value.get_int_value())
+    } else if value.has_uint_value() {
+        json!(value.get_uint_value())
+    } else if value.has_sint_value() {
+        json!(value.get_sint_value())
+    } else if value.has_bool_value() {
+        json!(value.get_bool_value())
+    } else {
+        Value::Null
+    }
+}
+
+/// GeoJSON FeatureCollection with the features of all tile layers.
+///
+/// Tile coordinates (y axis pointing down) are converted into grid coordinates
+/// of `extent` and transformed with `transform`, e.g. into WGS84.
+/// The source layer name is added as `layer` member of each feature.
+pub fn tile_to_geojson<F>(mvt_tile: &vector_tile::Tile, extent: &Extent, transform: F) -> Value
+where
+    F: Fn(f64, f64) -> (f64, f64),
+{
+    let mut features = Vec::new();
+    for mvt_layer in mvt_tile.get_layers() {
+        let tile_size = mvt_layer.get_extent() as f64;
+        let coord = |(x, y): TilePoint| {
+            let (x, y) = transform(
+                extent.minx + x as f64 * (extent.maxx - extent.minx) / tile_size,
+                extent.maxy - y as f64 * (extent.maxy - extent.miny) / tile_size,
+            );
+            json!([x, y])
+        };
+        for feature in mvt_layer.get_features() {
+            let geometry = match geojson_geometry(feature, &coord) {
+                Some(geometry) => geometry,
+                None => continue,
+            };
+            let mut geojson_feature = json!({
+                "type": "Feature",
+                "layer": mvt_layer.get_name(),
+                "geometry": geometry,
+                "properties": feature_properties(mvt_layer, feature),
+            });
+            if feature.has_id() {
+                geojson_feature["id"] = json!(feature.get_id());
+            }
+            features.push(geojson_feature);
+        }
+    }
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
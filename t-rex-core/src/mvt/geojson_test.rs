@@ -0,0 +1,91 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use core::layer::Layer;
+use mvt::geojson::tile_to_geojson;
+use mvt::tile::Tile;
+use postgis::ewkb;
+
+fn ring(coords: &[(f64, f64)]) -> ewkb::LineString {
+    ewkb::LineString {
+        points: coords
+            .iter()
+            .map(|&(x, y)| geom::Point::new(x, y, None))
+            .collect(),
+        srid: None,
+    }
+}
+
+#[test]
+fn test_tile_to_geojson() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, true);
+    let mut mvt_layer = tile.new_layer(&Layer::new("points"));
+    let feature = FeatureStruct {
+        fid: Some(7),
+        attributes: vec![
+            FeatureAttr {
+                key: "name".to_string(),
+                value: FeatureAttrValType::String("Bern".to_string()),
+            },
+            FeatureAttr {
+                key: "population".to_string(),
+                value: FeatureAttrValType::Int(133115),
+            },
+        ],
+        geometry: GeometryType::Point(geom::Point::new(100.0, 200.0, None)),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+
+    let mut mvt_layer = tile.new_layer(&Layer::new("polygons"));
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: Vec::new(),
+        geometry: GeometryType::Polygon(ewkb::Polygon {
+            rings: vec![
+                ring(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.), (0., 0.)]),
+                ring(&[(2., 2.), (2., 4.), (4., 4.), (4., 2.), (2., 2.)]),
+            ],
+            srid: None,
+        }),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: Vec::new(),
+        geometry: GeometryType::LineString(ring(&[(0., 0.), (20., 10.)])),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+
+    let geojson = tile_to_geojson(&tile.mvt_tile, &extent, |x, y| (x, y));
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(features.len(), 3);
+    assert_eq!(
+        features[0],
+        json!({"type": "Feature", "id": 7, "layer": "points",
+               "geometry": {"type": "Point", "coordinates": [100.0, 200.0]},
+               "properties": {"name": "Bern", "population": 133115}})
+    );
+    assert_eq!(features[1]["layer"], "polygons");
+    assert_eq!(features[1]["geometry"]["type"], "Polygon");
+    let rings = features[1]["geometry"]["coordinates"].as_array().unwrap();
+    assert_eq!(rings.len(), 2);
+    assert_eq!(rings[0].as_array().unwrap().len(), 5);
+    assert_eq!(rings[1][0], json!([2.0, 2.0]));
+    assert_eq!(
+        features[2]["geometry"],
+        json!({"type": "LineString", "coordinates": [[0.0, 0.0], [20.0, 10.0]]})
+    );
+}
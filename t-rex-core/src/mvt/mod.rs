@@ -3,11 +3,18 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+pub mod geojson;
+#[cfg(test)]
+mod geojson_test;
 pub mod geom_encoder;
 #[cfg(test)]
 mod geom_encoder_test;
+pub mod postprocess;
 pub mod tile;
 #[cfg(test)]
 mod tile_test;
+pub mod utfgrid;
+#[cfg(test)]
+mod utfgrid_test;
 #[cfg_attr(rustfmt, rustfmt_skip)]
 pub mod vector_tile; // protoc --rust_out . vector_tile.proto
@@ -0,0 +1,78 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use mvt::tile::Tile;
+use mvt::vector_tile;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Transformation of rendered tiles before caching and serving
+pub trait TilePostprocessor {
+    /// Process tile of tileset at x, y, z in TMS adressing scheme
+    fn process(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        mvt_tile: vector_tile::Tile,
+    ) -> Result<vector_tile::Tile, String>;
+}
+
+/// Postprocessing with an external command
+///
+/// The uncompressed tile is written to stdin of the command and the
+/// processed tile is read from stdout. Tile coordinates are passed in the
+/// environment variables `TREX_TILESET`, `TREX_X`, `TREX_Y` and `TREX_Z`.
+pub struct CommandPostprocessor {
+    pub command: String,
+}
+
+impl CommandPostprocessor {
+    pub fn new(command: &str) -> CommandPostprocessor {
+        CommandPostprocessor {
+            command: command.to_string(),
+        }
+    }
+}
+
+impl TilePostprocessor for CommandPostprocessor {
+    fn process(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        mvt_tile: vector_tile::Tile,
+    ) -> Result<vector_tile::Tile, String> {
+        let mut args = self.command.split_whitespace();
+        let program = args.next().ok_or("Empty postprocess command".to_string())?;
+        let mut child = Command::new(program)
+            .args(args)
+            .env("TREX_TILESET", tileset)
+            .env("TREX_X", xtile.to_string())
+            .env("TREX_Y", ytile.to_string())
+            .env("TREX_Z", zoom.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}: {}", program, e))?;
+        // Write in a separate thread to avoid blocking on a full stdout pipe
+        let mut stdin = child.stdin.take().unwrap();
+        let data = Tile::tile_bytevec(&mvt_tile);
+        let writer = thread::spawn(move || stdin.write_all(&data));
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("{}: {}", program, e))?;
+        if let Ok(Err(e)) = writer.join() {
+            return Err(format!("{}: {}", program, e));
+        }
+        if !output.status.success() {
+            return Err(format!("{}: {}", program, output.status));
+        }
+        Tile::read_from(&mut &output.stdout[..]).map_err(|e| format!("{}: {}", program, e))
+    }
+}
@@ -3,6 +3,8 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use brotli2::read::BrotliDecoder;
+use brotli2::write::BrotliEncoder;
 use core::feature::{Feature, FeatureAttrValType};
 use core::geom;
 use core::geom::GeometryType;
@@ -16,14 +18,107 @@ use mvt::geom_encoder::{CommandSequence, EncodableGeom};
 use mvt::vector_tile;
 use protobuf::error::ProtobufError;
 use protobuf::stream::CodedOutputStream;
-use protobuf::{parse_from_reader, Message};
+use protobuf::{parse_from_reader, Message, RepeatedField};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{BufReader, Read, Write};
+use std::io::{self, BufReader, Read, Write};
+use std::str::FromStr;
+
+/// Polygon ring orientation in tile coordinates
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum WindingOrder {
+    /// Exterior rings clockwise, interior rings counter-clockwise (MVT specification)
+    Cw,
+    /// Exterior rings counter-clockwise, interior rings clockwise
+    Ccw,
+}
+
+impl FromStr for WindingOrder {
+    type Err = String;
+    fn from_str(val: &str) -> Result<WindingOrder, String> {
+        match val {
+            "cw" => Ok(WindingOrder::Cw),
+            "ccw" => Ok(WindingOrder::Ccw),
+            _ => Err(format!("Unexpected winding order '{}'", val)),
+        }
+    }
+}
+
+impl Default for WindingOrder {
+    fn default() -> WindingOrder {
+        WindingOrder::Cw
+    }
+}
+
+/// Compression of encoded tiles
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TileCompression {
+    None,
+    Gzip,
+    Brotli,
+}
+
+/// Magic bytes of gzip compressed data
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+impl FromStr for TileCompression {
+    type Err = String;
+    fn from_str(val: &str) -> Result<TileCompression, String> {
+        match val {
+            "none" => Ok(TileCompression::None),
+            "gzip" => Ok(TileCompression::Gzip),
+            "brotli" => Ok(TileCompression::Brotli),
+            _ => Err(format!("Unexpected compression '{}'", val)),
+        }
+    }
+}
+
+impl Default for TileCompression {
+    fn default() -> TileCompression {
+        TileCompression::Gzip
+    }
+}
+
+impl TileCompression {
+    /// Value of Content-Encoding header
+    pub fn content_encoding(&self) -> Option<&'static str> {
+        match *self {
+            TileCompression::None => None,
+            TileCompression::Gzip => Some("gzip"),
+            TileCompression::Brotli => Some("br"),
+        }
+    }
+    /// Compressions accepted by client in order of preference (Brotli first)
+    pub fn from_accept_encoding(header: &str) -> Vec<TileCompression> {
+        let codings: Vec<&str> = header
+            .split(',')
+            .filter(|coding| {
+                // Ignore codings with quality 0
+                let mut params = coding.split(';').skip(1);
+                !params.any(|param| {
+                    let param = param.trim();
+                    param.starts_with("q=") && param[2..].parse::<f32>() == Ok(0.0)
+                })
+            })
+            .map(|coding| coding.split(';').next().unwrap_or("").trim())
+            .collect();
+        let mut accepted = Vec::new();
+        if codings.contains(&"br") {
+            accepted.push(TileCompression::Brotli);
+        }
+        if codings.contains(&"gzip") {
+            accepted.push(TileCompression::Gzip);
+        }
+        accepted
+    }
+}
 
 pub struct Tile<'a> {
     pub mvt_tile: vector_tile::Tile,
     extent: &'a Extent,
     reverse_y: bool,
+    /// Output orientation of polygon rings
+    pub winding_order: WindingOrder,
 }
 
 impl GeometryType {
@@ -157,6 +252,7 @@ impl<'a> Tile<'a> {
             mvt_tile: mvt_tile,
             extent: extent,
             reverse_y: reverse_y,
+            winding_order: WindingOrder::default(),
         }
     }
 
@@ -184,10 +280,18 @@ impl<'a> Tile<'a> {
                     .encode()
             }
             GeometryType::Polygon(ref g) => {
-                screen::Polygon::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
+                let mut polygon =
+                    screen::Polygon::from_geom(&self.extent, self.reverse_y, tile_size, g);
+                polygon.clean();
+                polygon.orient(self.winding_order == WindingOrder::Cw);
+                polygon.encode()
             }
             GeometryType::MultiPolygon(ref g) => {
-                screen::MultiPolygon::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
+                let mut multipolygon =
+                    screen::MultiPolygon::from_geom(&self.extent, self.reverse_y, tile_size, g);
+                multipolygon.clean();
+                multipolygon.orient(self.winding_order == WindingOrder::Cw);
+                multipolygon.encode()
             }
             GeometryType::GeometryCollection(_) => panic!("GeometryCollection not supported"),
         }
@@ -222,7 +326,25 @@ impl<'a> Tile<'a> {
         mvt_feature.mut_tags().push(validx as u32);
     }
 
-    pub fn add_feature(&self, mut mvt_layer: &mut vector_tile::Tile_Layer, feature: &Feature) {
+    pub fn add_feature(&self, mvt_layer: &mut vector_tile::Tile_Layer, feature: &Feature) {
+        let geometry = feature.geometry().ok().and_then(|geom| {
+            if geom.is_empty() {
+                None
+            } else {
+                let field_type = geom.mvt_field_type();
+                Some((field_type, self.encode_geom(geom, mvt_layer.get_extent()).vec()))
+            }
+        });
+        self.add_encoded_feature(mvt_layer, feature, geometry);
+    }
+
+    /// Add feature with geometry already encoded into tile commands
+    pub fn add_encoded_feature(
+        &self,
+        mut mvt_layer: &mut vector_tile::Tile_Layer,
+        feature: &Feature,
+        geometry: Option<(vector_tile::Tile_GeomType, Vec<u32>)>,
+    ) {
         let mut mvt_feature = vector_tile::Tile_Feature::new();
         if let Some(fid) = feature.fid() {
             mvt_feature.set_id(fid);
@@ -259,10 +381,11 @@ impl<'a> Tile<'a> {
                 mvt_value,
             );
         }
-        if let Ok(geom) = feature.geometry() {
-            if !geom.is_empty() {
-                mvt_feature.set_field_type(geom.mvt_field_type());
-                mvt_feature.set_geometry(self.encode_geom(geom, mvt_layer.get_extent()).vec());
+        if let Some((field_type, commands)) = geometry {
+            // Polygons may collapse to nothing in tile coordinates
+            if !commands.is_empty() {
+                mvt_feature.set_field_type(field_type);
+                mvt_feature.set_geometry(commands);
                 mvt_layer.mut_features().push(mvt_feature);
             }
         }
@@ -311,14 +434,70 @@ impl<'a> Tile<'a> {
         v
     }
 
-    pub fn tile_content(tilegz: Vec<u8>, gzip: bool) -> Vec<u8> {
-        if gzip {
-            tilegz
+    pub fn tile_bytevec_compressed(
+        mvt_tile: &vector_tile::Tile,
+        compression: TileCompression,
+    ) -> io::Result<Vec<u8>> {
+        match compression {
+            TileCompression::None => Ok(Self::tile_bytevec(mvt_tile)),
+            TileCompression::Gzip => Ok(Self::tile_bytevec_gz(mvt_tile)),
+            TileCompression::Brotli => Self::compress(&Self::tile_bytevec(mvt_tile), compression),
+        }
+    }
+
+    pub fn compress(data: &[u8], compression: TileCompression) -> io::Result<Vec<u8>> {
+        match compression {
+            TileCompression::None => Ok(data.to_vec()),
+            TileCompression::Gzip => {
+                let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+                gz.write_all(data)?;
+                gz.finish()
+            }
+            TileCompression::Brotli => {
+                let mut br = BrotliEncoder::new(Vec::new(), 9);
+                br.write_all(data)?;
+                br.finish()
+            }
+        }
+    }
+
+    pub fn decompress(data: &[u8], compression: TileCompression) -> io::Result<Vec<u8>> {
+        let mut unc_tile = Vec::with_capacity(data.len());
+        match compression {
+            TileCompression::None => unc_tile.extend_from_slice(data),
+            TileCompression::Gzip => {
+                GzDecoder::new(data).read_to_end(&mut unc_tile)?;
+            }
+            TileCompression::Brotli => {
+                BrotliDecoder::new(data).read_to_end(&mut unc_tile)?;
+            }
+        }
+        Ok(unc_tile)
+    }
+
+    /// Tile content in a compression accepted by the client.
+    /// Returns `data` unchanged if its compression is accepted.
+    /// Gzip compressed data is recognized independent of `compression`,
+    /// e.g. in a cache filled before changing the configured compression.
+    pub fn tile_content(
+        data: Vec<u8>,
+        compression: TileCompression,
+        accepted: &[TileCompression],
+    ) -> io::Result<(Vec<u8>, TileCompression)> {
+        let compression = if data.starts_with(&GZIP_MAGIC) {
+            TileCompression::Gzip
+        } else if compression == TileCompression::Gzip {
+            TileCompression::None
         } else {
-            let mut gz = GzDecoder::new(&tilegz[..]);
-            let mut unc_tile = Vec::with_capacity(tilegz.len());
-            let _ = gz.read_to_end(&mut unc_tile);
-            unc_tile
+            compression
+        };
+        if compression == TileCompression::None || accepted.contains(&compression) {
+            return Ok((data, compression));
+        }
+        let unc_tile = Self::decompress(&data, compression)?;
+        match accepted.first() {
+            Some(&preferred) => Ok((Self::compress(&unc_tile, preferred)?, preferred)),
+            None => Ok((unc_tile, TileCompression::None)),
         }
     }
 
@@ -330,4 +509,98 @@ impl<'a> Tile<'a> {
     pub fn size(mvt_tile: &vector_tile::Tile) -> u32 {
         mvt_tile.compute_size()
     }
+
+    /// Tile with deterministic encoding. Layers are sorted by name, keys and values
+    /// of layers are sorted and features are ordered by id, geometry and attributes.
+    pub fn canonical(mvt_tile: &vector_tile::Tile) -> vector_tile::Tile {
+        let mut layers: Vec<vector_tile::Tile_Layer> = mvt_tile
+            .get_layers()
+            .iter()
+            .map(Self::canonical_layer)
+            .collect();
+        layers.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        let mut canonical = vector_tile::Tile::new();
+        canonical.set_layers(RepeatedField::from_vec(layers));
+        canonical
+    }
+
+    fn canonical_layer(mvt_layer: &vector_tile::Tile_Layer) -> vector_tile::Tile_Layer {
+        let keys = mvt_layer.get_keys();
+        // Values are compared by their encoding
+        let values: Vec<Vec<u8>> = mvt_layer
+            .get_values()
+            .iter()
+            .map(|v| v.write_to_bytes().unwrap_or_default())
+            .collect();
+        let mut value_map = BTreeMap::new();
+        let mut key_set = BTreeSet::new();
+        let mut features: Vec<(&vector_tile::Tile_Feature, Vec<(&str, &[u8])>)> = mvt_layer
+            .get_features()
+            .iter()
+            .map(|feature| {
+                let mut tags: Vec<(&str, &[u8])> = feature
+                    .get_tags()
+                    .chunks(2)
+                    .filter_map(|kv| {
+                        if kv.len() < 2 {
+                            return None;
+                        }
+                        let key = keys.get(kv[0] as usize)?;
+                        let value = values.get(kv[1] as usize)?;
+                        key_set.insert(key.as_str());
+                        value_map.insert(&value[..], &mvt_layer.get_values()[kv[1] as usize]);
+                        Some((key.as_str(), &value[..]))
+                    })
+                    .collect();
+                tags.sort();
+                (feature, tags)
+            })
+            .collect();
+        features.sort_by(|&(a, ref a_tags), &(b, ref b_tags)| {
+            a.get_id()
+                .cmp(&b.get_id())
+                .then_with(|| (a.get_field_type() as i32).cmp(&(b.get_field_type() as i32)))
+                .then_with(|| a.get_geometry().cmp(b.get_geometry()))
+                .then_with(|| a_tags.cmp(b_tags))
+        });
+        let sorted_keys: Vec<&str> = key_set.into_iter().collect();
+        let sorted_values: Vec<&[u8]> = value_map.keys().cloned().collect();
+
+        let mut layer = vector_tile::Tile_Layer::new();
+        layer.set_version(mvt_layer.get_version());
+        layer.set_name(mvt_layer.get_name().to_string());
+        layer.set_extent(mvt_layer.get_extent());
+        for &(feature, ref tags) in &features {
+            let mut canonical_feature = vector_tile::Tile_Feature::new();
+            if feature.has_id() {
+                canonical_feature.set_id(feature.get_id());
+            }
+            canonical_feature.set_field_type(feature.get_field_type());
+            canonical_feature.set_geometry(feature.get_geometry().to_vec());
+            let mut feature_tags = Vec::with_capacity(tags.len() * 2);
+            for &(key, value) in tags {
+                feature_tags.push(sorted_keys.binary_search(&key).unwrap() as u32);
+                feature_tags.push(sorted_values.binary_search(&value).unwrap() as u32);
+            }
+            canonical_feature.set_tags(feature_tags);
+            layer.mut_features().push(canonical_feature);
+        }
+        layer.set_keys(RepeatedField::from_vec(
+            sorted_keys.iter().map(|k| k.to_string()).collect(),
+        ));
+        layer.set_values(RepeatedField::from_vec(
+            value_map.values().map(|&v| v.clone()).collect(),
+        ));
+        layer
+    }
+
+    /// Stable content hash (64-bit FNV-1a)
+    pub fn content_hash(data: &[u8]) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in data {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
 }
@@ -65,6 +65,42 @@ fn test_tile_values() {
     assert_eq!(value.get_string_value(), String::from("Hello, world!"));
 }
 
+#[test]
+fn test_feature_id() {
+    assert_eq!(FeatureAttrValType::Int(42).as_fid(), Some(42));
+    assert_eq!(
+        FeatureAttrValType::Int(9007199254740993).as_fid(),
+        Some(9007199254740993)
+    );
+    assert_eq!(FeatureAttrValType::Int(-1).as_fid(), None);
+    assert_eq!(FeatureAttrValType::UInt(7).as_fid(), Some(7));
+    assert_eq!(FeatureAttrValType::Double(12.0).as_fid(), Some(12));
+    assert_eq!(FeatureAttrValType::Double(12.5).as_fid(), None);
+    assert_eq!(
+        FeatureAttrValType::String("123456789012".to_string()).as_fid(),
+        Some(123456789012)
+    );
+    assert_eq!(FeatureAttrValType::String("a1".to_string()).as_fid(), None);
+    assert_eq!(FeatureAttrValType::Bool(true).as_fid(), None);
+
+    // Bigint ids are written as MVT feature id
+    let extent = Extent {
+        minx: 958826.08,
+        miny: 5987771.04,
+        maxx: 978393.96,
+        maxy: 6007338.92,
+    };
+    let mut tile = Tile::new(&extent, false);
+    let mut mvt_layer = tile.new_layer(&Layer::new("points"));
+    let feature = FeatureStruct {
+        fid: FeatureAttrValType::Int(9007199254740993).as_fid(),
+        attributes: Vec::new(),
+        geometry: GeometryType::Point(geom::Point::new(960000.0, 6002729.0, Some(3857))),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    assert_eq!(mvt_layer.get_features()[0].get_id(), 9007199254740993);
+}
+
 #[test]
 fn test_read_from_file() {
     // Command line decoding:
@@ -370,3 +406,249 @@ fn test_build_mvt_with_helpers() {
     path.push("out.pbf");
     tile.to_file(&format!("{}", &path.display()));
 }
+
+#[test]
+fn test_canonical_tile() {
+    let extent = Extent {
+        minx: 958826.08,
+        miny: 5987771.04,
+        maxx: 978393.96,
+        maxy: 6007338.92,
+    };
+    let feature = |fid, attrs: Vec<(&str, &str)>| FeatureStruct {
+        fid: Some(fid),
+        attributes: attrs
+            .iter()
+            .map(|&(key, value)| FeatureAttr {
+                key: key.to_string(),
+                value: FeatureAttrValType::String(value.to_string()),
+            })
+            .collect(),
+        geometry: GeometryType::Point(geom::Point::new(960000.0, 6002729.0, Some(3857))),
+    };
+    let mut tiles = Vec::new();
+    for reverse in vec![false, true] {
+        let mut tile = Tile::new(&extent, false);
+        let mut features = vec![
+            feature(1, vec![("name", "Bern"), ("type", "city")]),
+            feature(2, vec![("type", "village"), ("name", "Ins")]),
+        ];
+        if reverse {
+            features.reverse();
+        }
+        let mut mvt_layer = tile.new_layer(&Layer::new("places"));
+        for f in &features {
+            tile.add_feature(&mut mvt_layer, f);
+        }
+        tile.add_layer(mvt_layer);
+        let empty_layer = tile.new_layer(&Layer::new("empty"));
+        tile.add_layer(empty_layer);
+        if reverse {
+            tile.mvt_tile.mut_layers().reverse();
+        }
+        tiles.push(tile.mvt_tile);
+    }
+    assert!(Tile::tile_bytevec(&tiles[0]) != Tile::tile_bytevec(&tiles[1]));
+    let canonical0 = Tile::tile_bytevec(&Tile::canonical(&tiles[0]));
+    let canonical1 = Tile::tile_bytevec(&Tile::canonical(&tiles[1]));
+    assert_eq!(canonical0, canonical1);
+    assert_eq!(
+        Tile::content_hash(&canonical0),
+        Tile::content_hash(&canonical1)
+    );
+
+    let canonical = Tile::canonical(&tiles[1]);
+    assert_eq!(canonical.get_layers()[0].get_name(), "empty");
+    let places = &canonical.get_layers()[1];
+    assert_eq!(places.get_keys(), &["name".to_string(), "type".to_string()]);
+    assert_eq!(places.get_features()[0].get_id(), 1);
+    assert_eq!(places.get_features()[1].get_tags(), &[0, 0, 1, 3]);
+}
+
+#[test]
+fn test_content_hash() {
+    assert_eq!(Tile::content_hash(&[]), "cbf29ce484222325");
+    assert_eq!(Tile::content_hash(b"a"), "af63dc4c8601ec8c");
+}
+
+#[test]
+fn test_tile_compression() {
+    use mvt::tile::TileCompression;
+
+    let data = b"MVT data MVT data MVT data MVT data".to_vec();
+    let tilegz = Tile::compress(&data, TileCompression::Gzip).unwrap();
+    let tilebr = Tile::compress(&data, TileCompression::Brotli).unwrap();
+    assert!(tilebr.len() < data.len());
+    assert_eq!(
+        Tile::decompress(&tilegz, TileCompression::Gzip).unwrap(),
+        data
+    );
+    assert_eq!(
+        Tile::decompress(&tilebr, TileCompression::Brotli).unwrap(),
+        data
+    );
+    assert!(Tile::decompress(&data, TileCompression::Gzip).is_err());
+
+    let accepted = TileCompression::from_accept_encoding("gzip, deflate, br");
+    assert_eq!(
+        accepted,
+        vec![TileCompression::Brotli, TileCompression::Gzip]
+    );
+    assert_eq!(
+        TileCompression::from_accept_encoding("br;q=0, gzip;q=0.8"),
+        vec![TileCompression::Gzip]
+    );
+    assert!(TileCompression::from_accept_encoding("identity").is_empty());
+
+    // Stored compression accepted
+    let content = Tile::tile_content(tilegz.clone(), TileCompression::Gzip, &accepted).unwrap();
+    assert_eq!(content, (tilegz.clone(), TileCompression::Gzip));
+    // Recompressed with accepted compression
+    let content =
+        Tile::tile_content(tilebr.clone(), TileCompression::Brotli, &accepted[1..]).unwrap();
+    assert_eq!(content.1, TileCompression::Gzip);
+    assert_eq!(
+        Tile::decompress(&content.0, TileCompression::Gzip).unwrap(),
+        data
+    );
+    // Uncompressed
+    let content = Tile::tile_content(tilebr.clone(), TileCompression::Brotli, &[]).unwrap();
+    assert_eq!(content, (data.clone(), TileCompression::None));
+    // Gzip compressed tile cached before switching to Brotli
+    let content = Tile::tile_content(tilegz.clone(), TileCompression::Brotli, &[]).unwrap();
+    assert_eq!(content, (data.clone(), TileCompression::None));
+    // Uncompressed tile in a gzip cache
+    let content = Tile::tile_content(data.clone(), TileCompression::Gzip, &accepted).unwrap();
+    assert_eq!(content, (data.clone(), TileCompression::None));
+    // Corrupt data
+    assert!(Tile::tile_content(tilegz[..10].to_vec(), TileCompression::Gzip, &[]).is_err());
+
+    assert_eq!(
+        "brotli".parse::<TileCompression>(),
+        Ok(TileCompression::Brotli)
+    );
+    assert!("br".parse::<TileCompression>().is_err());
+}
+
+#[test]
+fn test_winding_order() {
+    use mvt::tile::WindingOrder;
+
+    // Counter-clockwise exterior ring in tile coordinates
+    let exterior = screen::LineString {
+        points: vec![
+            screen::Point { x: 0, y: 0 },
+            screen::Point { x: 0, y: 10 },
+            screen::Point { x: 10, y: 10 },
+            screen::Point { x: 10, y: 0 },
+            screen::Point { x: 0, y: 0 },
+        ],
+    };
+    assert_eq!(exterior.signed_area(), -200);
+    // Clockwise interior ring
+    let interior = screen::LineString {
+        points: vec![
+            screen::Point { x: 2, y: 2 },
+            screen::Point { x: 4, y: 2 },
+            screen::Point { x: 4, y: 4 },
+            screen::Point { x: 2, y: 4 },
+            screen::Point { x: 2, y: 2 },
+        ],
+    };
+    assert_eq!(interior.signed_area(), 8);
+
+    let mut polygon = screen::Polygon {
+        rings: vec![exterior, interior],
+    };
+    polygon.orient(true);
+    assert_eq!(polygon.rings[0].signed_area(), 200);
+    assert_eq!(polygon.rings[1].signed_area(), -8);
+    polygon.orient(false);
+    assert_eq!(polygon.rings[0].signed_area(), -200);
+    assert_eq!(polygon.rings[1].signed_area(), 8);
+
+    assert_eq!("ccw".parse::<WindingOrder>(), Ok(WindingOrder::Ccw));
+    assert!("left".parse::<WindingOrder>().is_err());
+}
+
+#[test]
+fn test_degenerated_rings() {
+    let ring = |coords: &[(i32, i32)]| screen::LineString {
+        points: coords
+            .iter()
+            .map(|&(x, y)| screen::Point { x, y })
+            .collect(),
+    };
+    let mut polygon = screen::Polygon {
+        rings: vec![
+            ring(&[(0, 0), (0, 10), (0, 10), (10, 10), (10, 0), (0, 0)]),
+            // Collapsed interior ring
+            ring(&[(2, 2), (4, 2), (2, 2)]),
+            ring(&[(2, 2), (4, 2), (4, 4), (2, 4), (2, 2)]),
+        ],
+    };
+    polygon.clean();
+    assert_eq!(polygon.rings.len(), 2);
+    assert_eq!(polygon.rings[0].points.len(), 5);
+
+    // Exterior ring without area
+    let mut multipolygon = screen::MultiPolygon {
+        polygons: vec![
+            screen::Polygon {
+                rings: vec![
+                    ring(&[(0, 0), (5, 5), (10, 10), (0, 0)]),
+                    ring(&[(2, 2), (4, 2), (4, 4), (2, 4), (2, 2)]),
+                ],
+            },
+            screen::Polygon {
+                rings: vec![ring(&[(0, 0), (0, 10), (10, 10), (0, 0)])],
+            },
+        ],
+    };
+    multipolygon.clean();
+    assert_eq!(multipolygon.polygons.len(), 1);
+
+    // Polygon smaller than a tile pixel is not added
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, true);
+    let mut mvt_layer = tile.new_layer(&Layer::new("polygons"));
+    let tiny = geom::Polygon {
+        rings: vec![geom::LineString {
+            points: [(1.1, 1.1), (1.2, 1.1), (1.2, 1.2), (1.1, 1.1)]
+                .iter()
+                .map(|&(x, y)| geom::Point::new(x, y, None))
+                .collect(),
+            srid: None,
+        }],
+        srid: None,
+    };
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: Vec::new(),
+        geometry: GeometryType::Polygon(tiny),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    assert_eq!(mvt_layer.get_features().len(), 0);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_command_postprocessor() {
+    use mvt::postprocess::{CommandPostprocessor, TilePostprocessor};
+
+    let mut f = File::open("../t-rex-service/src/test/tile.pbf").unwrap();
+    let tile = Tile::read_from(&mut f).unwrap();
+    let expected = format!("{:?}", tile);
+
+    let cat = CommandPostprocessor::new("cat");
+    let processed = cat.process("roads", 0, 0, 0, tile).unwrap();
+    assert_eq!(format!("{:?}", processed), expected);
+
+    let fail = CommandPostprocessor::new("false");
+    assert!(fail.process("roads", 0, 0, 0, processed).is_err());
+}
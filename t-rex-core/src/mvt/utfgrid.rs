@@ -0,0 +1,148 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! UTFGrid interaction tiles (https://github.com/mapbox/utfgrid-spec/tree/master/1.3)
+
+use mvt::geojson::{decode_parts, feature_properties, TilePoint};
+use mvt::vector_tile;
+use serde_json::{Map, Value};
+
+/// Grid character of key index
+fn encode_id(id: u32) -> char {
+    let mut code = id + 32;
+    if code >= 34 {
+        code += 1;
+    }
+    if code >= 92 {
+        code += 1;
+    }
+    ::std::char::from_u32(code).unwrap_or(' ')
+}
+
+/// Even-odd test of point in rings
+fn in_rings(rings: &[Vec<TilePoint>], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        for (i, p2) in ring.iter().enumerate().skip(1) {
+            let p1 = &ring[i - 1];
+            let (x1, y1, x2, y2) = (p1.0 as f64, p1.1 as f64, p2.0 as f64, p2.1 as f64);
+            if (y1 > y) != (y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1 {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+struct Raster {
+    size: usize,
+    /// Tile units per grid cell
+    cell: f64,
+    cells: Vec<u32>,
+}
+
+impl Raster {
+    fn set(&mut self, x: f64, y: f64, id: u32) {
+        let (col, row) = ((x / self.cell).floor(), (y / self.cell).floor());
+        if col >= 0.0 && row >= 0.0 && (col as usize) < self.size && (row as usize) < self.size {
+            self.cells[row as usize * self.size + col as usize] = id;
+        }
+    }
+    fn draw_line(&mut self, line: &[TilePoint], id: u32) {
+        for (i, p2) in line.iter().enumerate() {
+            let p1 = if i == 0 { p2 } else { &line[i - 1] };
+            let (dx, dy) = ((p2.0 - p1.0) as f64, (p2.1 - p1.1) as f64);
+            // Sample the segment in steps of half a cell
+            let steps = ((dx.abs().max(dy.abs()) * 2.0 / self.cell).ceil() as u32).max(1);
+            for step in 0..steps + 1 {
+                let t = step as f64 / steps as f64;
+                self.set(p1.0 as f64 + t * dx, p1.1 as f64 + t * dy, id);
+            }
+        }
+    }
+    fn fill_polygon(&mut self, rings: &[Vec<TilePoint>], id: u32) {
+        let points = || rings.iter().flat_map(|ring| ring.iter());
+        let (minx, maxx) = match (points().map(|p| p.0).min(), points().map(|p| p.0).max()) {
+            (Some(minx), Some(maxx)) => (minx as f64, maxx as f64),
+            _ => return,
+        };
+        let miny = points().map(|p| p.1).min().unwrap() as f64;
+        let maxy = points().map(|p| p.1).max().unwrap() as f64;
+        let first_col = (minx / self.cell).floor().max(0.0) as usize;
+        let first_row = (miny / self.cell).floor().max(0.0) as usize;
+        for row in first_row..self.size {
+            let y = (row as f64 + 0.5) * self.cell;
+            if y > maxy {
+                break;
+            }
+            for col in first_col..self.size {
+                let x = (col as f64 + 0.5) * self.cell;
+                if x > maxx {
+                    break;
+                }
+                if in_rings(rings, x, y) {
+                    self.cells[row * self.size + col] = id;
+                }
+            }
+        }
+        // Keep small polygons visible
+        for ring in rings {
+            self.draw_line(ring, id);
+        }
+    }
+}
+
+/// UTFGrid of `size` x `size` cells for the features of all tile layers.
+///
+/// Features are identified by the value of attribute `key`, features without
+/// this attribute are ignored. The attributes of each feature are returned in `data`.
+pub fn tile_to_utfgrid(mvt_tile: &vector_tile::Tile, key: &str, size: usize) -> Value {
+    let mut keys = vec![String::new()];
+    let mut data = Map::new();
+    let mut raster = Raster {
+        size,
+        cell: 1.0,
+        cells: vec![0; size * size],
+    };
+    for mvt_layer in mvt_tile.get_layers() {
+        raster.cell = mvt_layer.get_extent() as f64 / size as f64;
+        for feature in mvt_layer.get_features() {
+            let properties = feature_properties(mvt_layer, feature);
+            let key_value = match properties.get(key) {
+                Some(&Value::String(ref v)) => v.clone(),
+                Some(&Value::Null) | None => continue,
+                Some(v) => v.to_string(),
+            };
+            let id = match keys.iter().position(|k| k == &key_value) {
+                Some(idx) => idx as u32,
+                None => {
+                    keys.push(key_value.clone());
+                    data.insert(key_value, Value::Object(properties));
+                    (keys.len() - 1) as u32
+                }
+            };
+            let parts = decode_parts(feature.get_geometry());
+            match feature.get_field_type() {
+                vector_tile::Tile_GeomType::POINT | vector_tile::Tile_GeomType::LINESTRING => {
+                    for part in &parts {
+                        raster.draw_line(part, id);
+                    }
+                }
+                vector_tile::Tile_GeomType::POLYGON => raster.fill_polygon(&parts, id),
+                vector_tile::Tile_GeomType::UNKNOWN => {}
+            }
+        }
+    }
+    let grid: Vec<String> = raster
+        .cells
+        .chunks(size)
+        .map(|row| row.iter().map(|&id| encode_id(id)).collect())
+        .collect();
+    json!({
+        "grid": grid,
+        "keys": keys,
+        "data": data,
+    })
+}
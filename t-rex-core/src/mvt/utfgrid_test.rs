@@ -0,0 +1,84 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use core::layer::Layer;
+use mvt::tile::Tile;
+use mvt::utfgrid::tile_to_utfgrid;
+use postgis::ewkb;
+
+fn name_attr(name: &str) -> Vec<FeatureAttr> {
+    vec![FeatureAttr {
+        key: "name".to_string(),
+        value: FeatureAttrValType::String(name.to_string()),
+    }]
+}
+
+#[test]
+fn test_tile_to_utfgrid() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, true);
+    let mut mvt_layer = tile.new_layer(&Layer::new("polygons"));
+    // Within the lower left quarter of the tile
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: name_attr("Aargau"),
+        geometry: GeometryType::Polygon(ewkb::Polygon {
+            rings: vec![ewkb::LineString {
+                points: [
+                    (100., 100.),
+                    (1900., 100.),
+                    (1900., 1900.),
+                    (100., 1900.),
+                    (100., 100.),
+                ]
+                .iter()
+                .map(|&(x, y)| geom::Point::new(x, y, None))
+                .collect(),
+                srid: None,
+            }],
+            srid: None,
+        }),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    // Features without key are ignored
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: Vec::new(),
+        geometry: GeometryType::Point(geom::Point::new(3000.0, 3000.0, None)),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+
+    let mut mvt_layer = tile.new_layer(&Layer::new("points"));
+    let feature = FeatureStruct {
+        fid: None,
+        attributes: name_attr("Bern"),
+        geometry: GeometryType::Point(geom::Point::new(3500.0, 1000.0, None)),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+
+    let utfgrid = tile_to_utfgrid(&tile.mvt_tile, "name", 4);
+    assert_eq!(utfgrid["keys"], json!(["", "Aargau", "Bern"]));
+    assert_eq!(
+        utfgrid["data"],
+        json!({"Aargau": {"name": "Aargau"}, "Bern": {"name": "Bern"}})
+    );
+    // Tile y axis points down
+    assert_eq!(utfgrid["grid"], json!(["    ", "    ", "!!  ", "!! #"]));
+
+    let utfgrid = tile_to_utfgrid(&tile.mvt_tile, "name", 64);
+    let grid = utfgrid["grid"].as_array().unwrap();
+    assert_eq!(grid.len(), 64);
+    assert!(grid.iter().all(|row| row.as_str().unwrap().len() == 64));
+}
@@ -7,12 +7,17 @@ use core::config::Config;
 use core::config::{TilesetCfg, TilesetCacheCfg};
 use core::grid::Extent;
 use core::layer::Layer;
+use serde_json::{self, Value};
+use std::fs::File;
+use std::time::SystemTime;
 
 #[derive(Debug)]
 pub struct CacheLimits {
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
     pub no_cache: bool,
+    /// Seconds after which cached tiles are re-rendered
+    pub ttl: Option<u64>,
 }
 
 impl<'a> Config<'a, TilesetCacheCfg> for CacheLimits {
@@ -20,7 +25,8 @@ impl<'a> Config<'a, TilesetCacheCfg> for CacheLimits {
         Ok(CacheLimits {
             minzoom: cfg.minzoom.clone(),
             maxzoom: cfg.maxzoom.clone(),
-            no_cache: cfg.no_cache.unwrap_or(false)
+            no_cache: cfg.no_cache.unwrap_or(false),
+            ttl: cfg.ttl,
         })
     }
     fn gen_config() -> String {
@@ -28,6 +34,9 @@ impl<'a> Config<'a, TilesetCacheCfg> for CacheLimits {
     }
 }
 
+/// Tileset names colliding with web service resources like `/stats.json`
+const RESERVED_NAMES: &[&str] = &["fontstacks", "index", "stats"];
+
 /// Collection of layers in one MVT
 pub struct Tileset {
     pub name: String,
@@ -38,7 +47,15 @@ pub struct Tileset {
     pub center: Option<(f64, f64)>,
     pub start_zoom: Option<u8>,
     pub layers: Vec<Layer>,
-    pub cache_limits: Option<CacheLimits>
+    pub cache_limits: Option<CacheLimits>,
+    /// Cache-Control max-age of tile responses
+    pub cache_control_max_age: Option<u32>,
+    /// Logical tile size in pixels (None: grid tile size)
+    pub pixel_size: Option<u16>,
+    /// Attribute used as key of UTFGrid interaction tiles
+    pub utfgrid_key: Option<String>,
+    /// User provided Mapbox GL style
+    pub style_json: Option<Value>,
 }
 
 pub static WORLD_EXTENT: Extent = Extent {
@@ -90,6 +107,24 @@ impl Tileset {
             None => true
         }
     }
+    pub fn cache_ttl(&self) -> Option<u64> {
+        self.cache_limits.as_ref().and_then(|cl| cl.ttl)
+    }
+    /// Check whether a cached tile modified at `modified` has to be re-rendered
+    pub fn is_expired(&self, modified: SystemTime) -> bool {
+        match self.cache_ttl() {
+            Some(ttl) => modified
+                .elapsed()
+                .map(|age| age.as_secs() >= ttl)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+}
+
+fn read_style_file(path: &str) -> Result<Value, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(file).map_err(|e| e.to_string())
 }
 
 impl<'a> Config<'a, TilesetCfg> for Tileset {
@@ -97,8 +132,35 @@ impl<'a> Config<'a, TilesetCfg> for Tileset {
         let layers = tileset_cfg
             .layers
             .iter()
-            .map(|layer| Layer::from_config(layer).unwrap())
+            .map(|layer_cfg| {
+                let mut layer = Layer::from_config(layer_cfg).unwrap();
+                if let (None, Some(tile_size)) = (layer_cfg.tile_size, tileset_cfg.tile_size) {
+                    layer.tile_size = tile_size;
+                }
+                layer
+            })
             .collect();
+        if RESERVED_NAMES.contains(&tileset_cfg.name.as_str()) {
+            return Err(format!(
+                "Tileset '{}': Name reserved for web service resources",
+                tileset_cfg.name
+            ));
+        }
+        if tileset_cfg.pixel_size == Some(0) {
+            return Err(format!(
+                "Tileset '{}': Invalid pixel_size 0",
+                tileset_cfg.name
+            ));
+        }
+        let style_json = match tileset_cfg.style_file {
+            Some(ref path) => Some(read_style_file(path).map_err(|e| {
+                format!(
+                    "Tileset '{}': Invalid style file '{}' - {}",
+                    tileset_cfg.name, path, e
+                )
+            })?),
+            None => None,
+        };
         let cache_limits: Option<CacheLimits> = match tileset_cfg.cache_limits {
             Some(ref cfg) => match CacheLimits::from_config(&cfg) {
                 Ok(cl) => Some(cl),
@@ -115,7 +177,11 @@ impl<'a> Config<'a, TilesetCfg> for Tileset {
             center: tileset_cfg.center.clone(),
             start_zoom: tileset_cfg.start_zoom.clone(),
             layers: layers,
-            cache_limits: cache_limits
+            cache_limits: cache_limits,
+            cache_control_max_age: tileset_cfg.cache_control_max_age,
+            pixel_size: tileset_cfg.pixel_size,
+            utfgrid_key: tileset_cfg.utfgrid_key.clone(),
+            style_json: style_json,
         })
     }
     fn gen_config() -> String {
@@ -152,7 +218,11 @@ fn test_zoom() {
             maxy: 82.48332,
         }),
         layers: vec![layer],
-        cache_limits: None
+        cache_limits: None,
+        cache_control_max_age: None,
+        pixel_size: None,
+        utfgrid_key: None,
+        style_json: None,
     };
 
     assert_eq!(tileset.minzoom(), 0);
@@ -167,3 +237,36 @@ fn test_zoom() {
     tileset.minzoom = Some(2);
     assert_eq!(tileset.minzoom(), 2);
 }
+
+#[test]
+fn test_cache_ttl() {
+    use std::time::Duration;
+
+    let mut tileset = Tileset {
+        name: "points".to_string(),
+        minzoom: None,
+        maxzoom: None,
+        center: None,
+        start_zoom: None,
+        attribution: None,
+        extent: None,
+        layers: vec![],
+        cache_limits: None,
+        cache_control_max_age: None,
+        pixel_size: None,
+        utfgrid_key: None,
+        style_json: None,
+    };
+    let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    assert!(!tileset.is_expired(hour_ago));
+
+    tileset.cache_limits = Some(CacheLimits {
+        minzoom: None,
+        maxzoom: None,
+        no_cache: false,
+        ttl: Some(600),
+    });
+    assert!(tileset.is_expired(hour_ago));
+    assert!(!tileset.is_expired(SystemTime::now()));
+    assert!(tileset.is_cachable_at(10));
+}
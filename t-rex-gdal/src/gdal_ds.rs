@@ -4,6 +4,7 @@
 //
 
 use core::config::DatasourceCfg;
+use core::error;
 use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
 use core::geom::{self, GeometryType};
 use core::grid::Extent;
@@ -13,14 +14,21 @@ use core::Config;
 use datasource::DatasourceInput;
 use gdal;
 use gdal::spatial_ref::{CoordTransform, SpatialRef};
-use gdal::vector::{Dataset, FieldValue, Geometry, OGRwkbGeometryType};
-use gdal_sys;
+use gdal::vector::{Dataset, Geometry, OGRwkbGeometryType};
+use gdal_sys::{self, OGRDataSourceH, OGRFeatureH, OGRFieldSubType, OGRFieldType};
+use std::cell::{RefCell, RefMut};
 use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::ops::{Deref, DerefMut};
 use std::path::Path;
+use std::ptr;
+use std::sync::Once;
 
 pub struct GdalDatasource {
     pub path: String,
-    // We don't store the Dataset, because we need mut access for getting layers
+    // Dataset handle opened on first use. Datasources are not shared between threads,
+    // each webserver worker has its own connected instance.
+    dataset: RefCell<Option<OgrDataset>>,
     // CoordTransform for all layers
     geom_transform: BTreeMap<String, Option<CoordTransform>>,
     // CoordTransform for all layers
@@ -31,14 +39,22 @@ impl GdalDatasource {
     pub fn new(path: &str) -> GdalDatasource {
         GdalDatasource {
             path: path.to_string(),
+            dataset: RefCell::new(None),
             geom_transform: BTreeMap::new(),
             bbox_transform: BTreeMap::new(),
         }
     }
+    /// Dataset handle, opened on first use
+    fn dataset(&self) -> Result<RefMut<OgrDataset>, gdal::errors::Error> {
+        let mut dataset = self.dataset.borrow_mut();
+        if dataset.is_none() {
+            *dataset = Some(OgrDataset::open(&self.path)?);
+        }
+        Ok(RefMut::map(dataset, |ds| ds.as_mut().unwrap()))
+    }
 }
 
 fn ogr_type_name(ogr_type: OGRwkbGeometryType::Type) -> String {
-    use std::ffi::CStr;
     let rv = unsafe { gdal_sys::OGRGeometryTypeToName(ogr_type) };
     //_string(rv)
     let c_str = unsafe { CStr::from_ptr(rv) };
@@ -46,7 +62,7 @@ fn ogr_type_name(ogr_type: OGRwkbGeometryType::Type) -> String {
 }
 
 fn geom_type_name(ogr_type: OGRwkbGeometryType::Type) -> Option<String> {
-    match ogr_type {
+    match unsafe { gdal_sys::OGR_GT_Flatten(ogr_type) } {
         OGRwkbGeometryType::wkbPoint | OGRwkbGeometryType::wkbMultiPoint => {
             Some("POINT".to_string())
         }
@@ -61,25 +77,25 @@ fn geom_type_name(ogr_type: OGRwkbGeometryType::Type) -> Option<String> {
 }
 
 trait ToGeo {
-    fn to_geo(&self, srid: Option<i32>) -> GeometryType;
+    fn to_geo(&self, srid: Option<i32>) -> Result<GeometryType, String>;
 }
 
 impl ToGeo for Geometry {
     /// Convert OGR geomtry to t-rex EWKB geometry type (XY only)
-    fn to_geo(&self, srid: Option<i32>) -> GeometryType {
-        let geometry_type = self.geometry_type();
+    fn to_geo(&self, srid: Option<i32>) -> Result<GeometryType, String> {
+        // Z and M coordinates are dropped
+        let geometry_type = unsafe { gdal_sys::OGR_GT_Flatten(self.geometry_type()) };
 
         let ring = |n: usize| {
             let ring = unsafe { self._get_geometry(n) };
-            return match ring.to_geo(srid) {
-                GeometryType::LineString(r) => r,
-                _ => panic!("Expected to get a LineString"),
-            };
+            match ring.to_geo(srid)? {
+                GeometryType::LineString(r) => Ok(r),
+                _ => Err("Expected to get a LineString".to_string()),
+            }
         };
 
-        match geometry_type {
-            OGRwkbGeometryType::wkbPoint | OGRwkbGeometryType::wkbPoint25D => {
-                // GDAL 2.1: | OGRwkbGeometryType::wkbPointM | OGRwkbGeometryType::wkbPointZM
+        let geom = match geometry_type {
+            OGRwkbGeometryType::wkbPoint => {
                 let (x, y, _) = self.get_point(0);
                 GeometryType::Point(geom::Point {
                     x: x,
@@ -87,14 +103,14 @@ impl ToGeo for Geometry {
                     srid: srid,
                 })
             }
-            OGRwkbGeometryType::wkbMultiPoint | OGRwkbGeometryType::wkbMultiPoint25D => {
+            OGRwkbGeometryType::wkbMultiPoint => {
                 let point_count = self.geometry_count();
                 let coords = (0..point_count)
-                    .map(|n| match unsafe { self._get_geometry(n) }.to_geo(srid) {
-                        GeometryType::Point(p) => p,
-                        _ => panic!("Expected to get a Point"),
+                    .map(|n| match unsafe { self._get_geometry(n) }.to_geo(srid)? {
+                        GeometryType::Point(p) => Ok(p),
+                        _ => Err("Expected to get a Point".to_string()),
                     })
-                    .collect();
+                    .collect::<Result<_, String>>()?;
                 GeometryType::MultiPoint(geom::MultiPoint {
                     points: coords,
                     srid: srid,
@@ -115,53 +131,84 @@ impl ToGeo for Geometry {
                     srid: srid,
                 })
             }
-            OGRwkbGeometryType::wkbMultiLineString | OGRwkbGeometryType::wkbMultiLineString25D => {
+            OGRwkbGeometryType::wkbMultiLineString => {
                 let string_count = self.geometry_count();
                 let strings = (0..string_count)
-                    .map(|n| match unsafe { self._get_geometry(n) }.to_geo(srid) {
-                        GeometryType::LineString(s) => s,
-                        _ => panic!("Expected to get a LineString"),
+                    .map(|n| match unsafe { self._get_geometry(n) }.to_geo(srid)? {
+                        GeometryType::LineString(s) => Ok(s),
+                        _ => Err("Expected to get a LineString".to_string()),
                     })
-                    .collect();
+                    .collect::<Result<_, String>>()?;
                 GeometryType::MultiLineString(geom::MultiLineString {
                     lines: strings,
                     srid: srid,
                 })
             }
-            OGRwkbGeometryType::wkbPolygon | OGRwkbGeometryType::wkbPolygon25D => {
+            OGRwkbGeometryType::wkbPolygon => {
                 let ring_count = self.geometry_count();
-                let rings = (0..ring_count).map(|n| ring(n)).collect();
+                let rings = (0..ring_count)
+                    .map(|n| ring(n))
+                    .collect::<Result<_, String>>()?;
                 GeometryType::Polygon(geom::Polygon {
                     rings: rings,
                     srid: srid,
                 })
             }
-            OGRwkbGeometryType::wkbMultiPolygon | OGRwkbGeometryType::wkbMultiPolygon25D => {
+            OGRwkbGeometryType::wkbMultiPolygon => {
                 let string_count = self.geometry_count();
                 let strings = (0..string_count)
-                    .map(|n| match unsafe { self._get_geometry(n) }.to_geo(srid) {
-                        GeometryType::Polygon(s) => s,
-                        _ => panic!("Expected to get a Polygon"),
+                    .map(|n| match unsafe { self._get_geometry(n) }.to_geo(srid)? {
+                        GeometryType::Polygon(s) => Ok(s),
+                        _ => Err("Expected to get a Polygon".to_string()),
                     })
-                    .collect();
+                    .collect::<Result<_, String>>()?;
                 GeometryType::MultiPolygon(geom::MultiPolygon {
                     polygons: strings,
                     srid: srid,
                 })
             }
-            /* TODO:
             OGRwkbGeometryType::wkbGeometryCollection => {
-                let item_count = self.geometry_count();
-                let geometry_list = (0..item_count)
-                    .map(|n| unsafe { self._get_geometry(n) }.to_geo(srid))
-                    .collect();
-                GeometryType::GeometryCollection(geom::GeometryCollection {
-                                                     geometries: geometry_list,
-                                                 })
+                // MVT has no geometry collections: flatten into a multi geometry
+                // of the highest dimension and drop lower dimensional parts
+                let mut points = Vec::new();
+                let mut lines = Vec::new();
+                let mut polygons = Vec::new();
+                for n in 0..self.geometry_count() {
+                    match unsafe { self._get_geometry(n) }.to_geo(srid)? {
+                        GeometryType::Point(p) => points.push(p),
+                        GeometryType::MultiPoint(p) => points.extend(p.points),
+                        GeometryType::LineString(l) => lines.push(l),
+                        GeometryType::MultiLineString(l) => lines.extend(l.lines),
+                        GeometryType::Polygon(p) => polygons.push(p),
+                        GeometryType::MultiPolygon(p) => polygons.extend(p.polygons),
+                        GeometryType::GeometryCollection(_) => {} // already flattened
+                    }
+                }
+                if !polygons.is_empty() {
+                    GeometryType::MultiPolygon(geom::MultiPolygon {
+                        polygons: polygons,
+                        srid: srid,
+                    })
+                } else if !lines.is_empty() {
+                    GeometryType::MultiLineString(geom::MultiLineString {
+                        lines: lines,
+                        srid: srid,
+                    })
+                } else {
+                    GeometryType::MultiPoint(geom::MultiPoint {
+                        points: points,
+                        srid: srid,
+                    })
+                }
             }
-            */
-            geom_type => panic!("Unsupported geometry type {}", &ogr_type_name(geom_type)),
-        }
+            geom_type => {
+                return Err(format!(
+                    "Unsupported geometry type {}",
+                    &ogr_type_name(geom_type)
+                ))
+            }
+        };
+        Ok(geom)
     }
 }
 
@@ -227,64 +274,274 @@ fn geom_spatialref(
     }
 }
 
+/// Index of named geometry field
+fn geom_field_index(ogr_layer: &gdal::vector::Layer, field_name: &str) -> Option<i32> {
+    let c_name = CString::new(field_name).ok()?;
+    let idx = unsafe {
+        let c_defn = gdal_sys::OGR_L_GetLayerDefn(ogr_layer.c_layer());
+        gdal_sys::OGR_FD_GetGeomFieldIndex(c_defn, c_name.as_ptr())
+    };
+    if idx >= 0 {
+        Some(idx)
+    } else {
+        None
+    }
+}
+
+static REGISTER_DRIVERS: Once = Once::new();
+
+/// OGR dataset keeping its C handle for functions not wrapped by the gdal crate
+struct OgrDataset {
+    dataset: Dataset,
+    c_dataset: OGRDataSourceH,
+}
+
+impl OgrDataset {
+    fn open(path: &str) -> Result<OgrDataset, gdal::errors::Error> {
+        REGISTER_DRIVERS.call_once(|| unsafe { gdal_sys::OGRRegisterAll() });
+        let c_path = CString::new(path)?;
+        let c_dataset = unsafe { gdal_sys::OGROpen(c_path.as_ptr(), 0, ptr::null_mut()) };
+        if c_dataset.is_null() {
+            let msg = unsafe { CStr::from_ptr(gdal_sys::CPLGetLastErrorMsg()) };
+            return Err(gdal::errors::ErrorKind::NullPointer {
+                method_name: "OGROpen",
+                msg: msg.to_string_lossy().into_owned(),
+            }.into());
+        }
+        Ok(OgrDataset {
+            // Destroys the C handle when dropped
+            dataset: unsafe { Dataset::_with_c_dataset(c_dataset) },
+            c_dataset,
+        })
+    }
+}
+
+impl Deref for OgrDataset {
+    type Target = Dataset;
+    fn deref(&self) -> &Dataset {
+        &self.dataset
+    }
+}
+
+impl DerefMut for OgrDataset {
+    fn deref_mut(&mut self) -> &mut Dataset {
+        &mut self.dataset
+    }
+}
+
+/// Result layer of an OGR SQL query, released when dropped
+struct SqlResultLayer<'a> {
+    dataset: &'a OgrDataset,
+    layer: gdal::vector::Layer,
+}
+
+impl<'a> Drop for SqlResultLayer<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            gdal_sys::OGR_DS_ReleaseResultSet(self.dataset.c_dataset, self.layer.c_layer());
+        }
+    }
+}
+
+/// Execute OGR SQL statement (http://gdal.org/ogr_sql.html)
+fn execute_sql<'a>(dataset: &'a OgrDataset, sql: &str) -> Result<SqlResultLayer<'a>, String> {
+    let c_sql = CString::new(sql).map_err(|e| e.to_string())?;
+    let c_layer = unsafe {
+        gdal_sys::OGR_DS_ExecuteSQL(
+            dataset.c_dataset,
+            c_sql.as_ptr(),
+            ptr::null_mut(),
+            ptr::null(),
+        )
+    };
+    if c_layer.is_null() {
+        return Err(format!("Invalid SQL query '{}'", sql));
+    }
+    Ok(SqlResultLayer {
+        dataset,
+        layer: unsafe { gdal::vector::Layer::_with_c_layer(c_layer) },
+    })
+}
+
+/// Attribute field with index resolved once per layer
+struct FieldInfo {
+    index: i32,
+    name: String,
+    field_type: OGRFieldType::Type,
+    boolean: bool,
+}
+
+/// Supported attribute fields of OGR layer
+fn layer_fields(layer: &Layer, ogr_layer: &gdal::vector::Layer) -> Vec<FieldInfo> {
+    let mut fields = Vec::new();
+    let c_defn = unsafe { gdal_sys::OGR_L_GetLayerDefn(ogr_layer.c_layer()) };
+    for (index, field) in ogr_layer.defn().fields().enumerate() {
+        let field_type = field.field_type();
+        let supported = match field_type {
+            OGRFieldType::OFTString
+            | OGRFieldType::OFTInteger
+            | OGRFieldType::OFTInteger64
+            | OGRFieldType::OFTReal
+            | OGRFieldType::OFTDate
+            | OGRFieldType::OFTDateTime => true,
+            _ => false,
+        };
+        if supported {
+            let subtype = unsafe {
+                gdal_sys::OGR_Fld_GetSubType(gdal_sys::OGR_FD_GetFieldDefn(c_defn, index as i32))
+            };
+            fields.push(FieldInfo {
+                index: index as i32,
+                name: field.name(),
+                field_type,
+                boolean: subtype == OGRFieldSubType::OFSTBoolean,
+            });
+        } else {
+            warn!(
+                "Layer '{}' - skipping field '{}' of unsupported type {}",
+                layer.name,
+                field.name(),
+                field_type
+            );
+        }
+    }
+    fields
+}
+
 struct VectorFeature<'a> {
     layer: &'a Layer,
-    fields_defn: &'a Vec<gdal::vector::Field<'a>>,
+    fields: &'a Vec<FieldInfo>,
     grid_srid: i32,
     transform: Option<&'a CoordTransform>,
+    // Buffered tile extent for clipping
+    clip: Option<&'a Extent>,
+    // Simplification tolerance in grid units
+    tolerance: Option<f64>,
     feature: &'a gdal::vector::Feature<'a>,
+    // C handle of `feature` for field access by index
+    c_feature: OGRFeatureH,
+}
+
+impl<'a> VectorFeature<'a> {
+    fn field_value(&self, field: &FieldInfo) -> Option<FeatureAttrValType> {
+        let c_feature = self.c_feature;
+        if unsafe { gdal_sys::OGR_F_IsFieldSet(c_feature, field.index) } == 0 {
+            return None;
+        }
+        let val = match field.field_type {
+            OGRFieldType::OFTString => {
+                let rv = unsafe { gdal_sys::OGR_F_GetFieldAsString(c_feature, field.index) };
+                let c_str = unsafe { CStr::from_ptr(rv) };
+                FeatureAttrValType::String(c_str.to_string_lossy().into_owned())
+            }
+            OGRFieldType::OFTInteger if field.boolean => FeatureAttrValType::Bool(
+                unsafe { gdal_sys::OGR_F_GetFieldAsInteger(c_feature, field.index) } != 0,
+            ),
+            OGRFieldType::OFTInteger => FeatureAttrValType::Int(unsafe {
+                gdal_sys::OGR_F_GetFieldAsInteger(c_feature, field.index)
+            } as i64),
+            OGRFieldType::OFTInteger64 => FeatureAttrValType::Int(unsafe {
+                gdal_sys::OGR_F_GetFieldAsInteger64(c_feature, field.index)
+            }),
+            OGRFieldType::OFTReal => FeatureAttrValType::Double(unsafe {
+                gdal_sys::OGR_F_GetFieldAsDouble(c_feature, field.index)
+            }),
+            OGRFieldType::OFTDate | OGRFieldType::OFTDateTime => {
+                let (mut year, mut month, mut day) = (0, 0, 0);
+                let (mut hour, mut minute, mut second, mut tz) = (0, 0, 0, 0);
+                let ok = unsafe {
+                    gdal_sys::OGR_F_GetFieldAsDateTime(
+                        c_feature,
+                        field.index,
+                        &mut year,
+                        &mut month,
+                        &mut day,
+                        &mut hour,
+                        &mut minute,
+                        &mut second,
+                        &mut tz,
+                    )
+                };
+                if ok == 0 {
+                    return None;
+                }
+                // ISO 8601 representation
+                let value = if field.field_type == OGRFieldType::OFTDate {
+                    format!("{:04}-{:02}-{:02}", year, month, day)
+                } else {
+                    format!(
+                        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+                        year,
+                        month,
+                        day,
+                        hour,
+                        minute,
+                        second,
+                        tz_designator(tz)
+                    )
+                };
+                FeatureAttrValType::String(value)
+            }
+            _ => return None,
+        };
+        Some(val)
+    }
+}
+
+/// ISO 8601 time zone designator of an OGR TZFlag
+/// (0: unknown, 1: local time, 100: GMT, 100 +/- n: offset of n quarter hours)
+pub fn tz_designator(tz: i32) -> String {
+    match tz {
+        0 | 1 => "".to_string(),
+        100 => "Z".to_string(),
+        _ => {
+            let offset = (tz - 100) * 15;
+            let sign = if offset < 0 { '-' } else { '+' };
+            format!("{}{:02}:{:02}", sign, offset.abs() / 60, offset.abs() % 60)
+        }
+    }
 }
 
 impl<'a> Feature for VectorFeature<'a> {
     fn fid(&self) -> Option<u64> {
         self.layer.fid_field.as_ref().and_then(|fid| {
-            let field_value = self.feature.field(&fid);
-            match field_value {
-                Ok(FieldValue::IntegerValue(v)) => Some(v as u64),
-                _ => None,
-            }
+            let field = self.fields.iter().find(|field| &field.name == fid)?;
+            self.field_value(field).and_then(|val| val.as_fid())
         })
     }
     fn attributes(&self) -> Vec<FeatureAttr> {
         let mut attrs = Vec::new();
-        for (_i, field) in self.fields_defn.into_iter().enumerate() {
-            let field_value = self.feature.field(&field.name()); //TODO: get by index
-            let val = match field_value {
-                Ok(FieldValue::StringValue(v)) => Some(FeatureAttrValType::String(v)),
-                Ok(FieldValue::IntegerValue(v)) => Some(FeatureAttrValType::Int(v as i64)),
-                Ok(FieldValue::RealValue(v)) => Some(FeatureAttrValType::Double(v)),
-                Err(err) => {
-                    warn!(
-                        "Layer '{}' - skipping field '{}': {}",
-                        self.layer.name,
-                        field.name(),
-                        err
-                    );
-                    None
-                }
-            };
-            // match field.field_type {
-            //    OGRFieldType::OFTString => {
-            if let Some(val) = val {
-                let fattr = FeatureAttr {
-                    key: field.name(),
+        for field in self.fields {
+            if let Some(val) = self.field_value(field) {
+                attrs.push(FeatureAttr {
+                    key: field.name.clone(),
                     value: val,
-                };
-                attrs.push(fattr);
-            };
+                });
+            }
         }
         attrs
     }
     fn geometry(&self) -> Result<GeometryType, String> {
         let ogrgeom = if let Some(ref field) = self.layer.geometry_field {
-            self.feature.geometry_by_name(field).unwrap()
+            self.feature
+                .geometry_by_name(field)
+                .map_err(|e| format!("Geometry field '{}': {}", field, e))?
         } else {
             self.feature.geometry()
         };
         if let Some(ref transform) = self.transform {
-            ogrgeom.transform_inplace(transform).unwrap();
+            ogrgeom
+                .transform_inplace(transform)
+                .map_err(|e| format!("Transformation error: {}", e))?;
         };
-        Ok(ogrgeom.to_geo(Some(self.grid_srid)))
+        let mut geom = ogrgeom.to_geo(Some(self.grid_srid))?;
+        if let Some(extent) = self.clip {
+            geom.clip(extent);
+        }
+        if let Some(tolerance) = self.tolerance {
+            geom.simplify(tolerance);
+        }
+        Ok(geom)
     }
 }
 
@@ -293,28 +550,40 @@ impl DatasourceInput for GdalDatasource {
     fn connected(&self) -> GdalDatasource {
         GdalDatasource {
             path: self.path.clone(),
+            dataset: RefCell::new(None),
             geom_transform: BTreeMap::new(),
             bbox_transform: BTreeMap::new(),
         }
     }
     fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
         let mut layers: Vec<Layer> = Vec::new();
-        let mut dataset = Dataset::open(Path::new(&self.path)).unwrap();
+        let mut dataset = match Dataset::open(Path::new(&self.path)) {
+            Ok(dataset) => dataset,
+            Err(e) => {
+                error!("Can't open dataset '{}': {}", self.path, e);
+                return layers;
+            }
+        };
         for idx in 0..dataset.count() {
-            let gdal_layer = dataset.layer(idx).unwrap();
+            let gdal_layer = match dataset.layer(idx) {
+                Ok(gdal_layer) => gdal_layer,
+                Err(e) => {
+                    error!("Dataset '{}': {}", self.path, e);
+                    continue;
+                }
+            };
             let name = gdal_layer.name();
             // Create a layer for each geometry field
             for (n, field) in gdal_layer.defn().geom_fields().enumerate() {
-                let mut layer = Layer::new(&name);
-                layer.table_name = if n == 0 {
-                    Some(name.clone())
+                let mut layer = if n == 0 {
+                    Layer::new(&name)
                 } else {
-                    Some(format!("{}_{}", &name, n))
+                    Layer::new(&format!("{}_{}", &name, field.name()))
                 };
+                layer.table_name = Some(name.clone());
                 layer.geometry_field = Some(field.name());
                 layer.geometry_type = geom_type_name(field.field_type());
-                let srs = field.spatial_ref().unwrap();
-                if let Ok(epsg) = srs.auth_code() {
+                if let Ok(epsg) = field.spatial_ref().and_then(|srs| srs.auth_code()) {
                     layer.srid = Some(epsg)
                 }
                 layers.push(layer)
@@ -326,14 +595,36 @@ impl DatasourceInput for GdalDatasource {
     fn detect_data_columns(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
         Vec::new() //TODO
     }
+    fn detect_field_types(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        Vec::new() //TODO
+    }
     /// Projected extent
     fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
         transform_extent(extent, 4326, dest_srid).ok()
     }
     fn layer_extent(&self, layer: &Layer, grid_srid: i32) -> Option<Extent> {
-        let mut dataset = Dataset::open(Path::new(&self.path)).unwrap();
-        let layer_name = layer.table_name.as_ref().unwrap();
-        let ogr_layer = dataset.layer_by_name(layer_name).unwrap();
+        // Layers defined by an SQL query have no table extent
+        let layer_name = layer.table_name.as_ref()?;
+        let mut dataset = match Dataset::open(Path::new(&self.path)) {
+            Ok(dataset) => dataset,
+            Err(e) => {
+                error!(
+                    "Layer '{}': Can't open dataset '{}': {}",
+                    layer.name, self.path, e
+                );
+                return None;
+            }
+        };
+        let ogr_layer = match dataset.layer_by_name(layer_name) {
+            Ok(ogr_layer) => ogr_layer,
+            Err(_) => {
+                error!(
+                    "Layer '{}': Can't find dataset layer '{}'",
+                    layer.name, layer_name
+                );
+                return None;
+            }
+        };
         let extent = match ogr_layer.get_extent(true) {
             Err(e) => {
                 warn!("Layer '{}': Unable to get extent: {}", layer.name, e);
@@ -388,17 +679,54 @@ impl DatasourceInput for GdalDatasource {
             );
             // We continue, because GDAL also supports HTTP adresses
         }
-        let mut dataset = Dataset::open(Path::new(&self.path)).unwrap();
-        let layer_name = layer.table_name.as_ref().unwrap();
-        let ogr_layer = dataset.layer_by_name(layer_name);
-        if ogr_layer.is_err() {
-            error!(
-                "Layer '{}': Can't find dataset layer '{}'",
-                layer.name, layer_name
-            );
-            return;
+        let mut dataset = match OgrDataset::open(&self.path) {
+            Ok(dataset) => dataset,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        let sql_layer;
+        let ogr_layer = match layer.query(layer.minzoom()) {
+            Some(sql) => match execute_sql(&dataset, sql) {
+                Ok(result) => {
+                    sql_layer = result;
+                    &sql_layer.layer
+                }
+                Err(e) => {
+                    error!("Layer '{}': {}", layer.name, e);
+                    return;
+                }
+            },
+            None => {
+                let layer_name = match layer.table_name {
+                    Some(ref table_name) => table_name,
+                    None => {
+                        error!("Layer '{}': table_name or sql missing", layer.name);
+                        return;
+                    }
+                };
+                match dataset.layer_by_name(layer_name) {
+                    Ok(ogr_layer) => ogr_layer,
+                    Err(_) => {
+                        error!(
+                            "Layer '{}': Can't find dataset layer '{}'",
+                            layer.name, layer_name
+                        );
+                        return;
+                    }
+                }
+            }
+        };
+
+        if let Some(ref field) = layer.geometry_field {
+            if geom_field_index(ogr_layer, field).is_none() {
+                warn!(
+                    "Layer '{}': Geometry field '{}' not found",
+                    layer.name, field
+                );
+            }
         }
-        let ogr_layer = ogr_layer.unwrap();
 
         let grid_sref = match SpatialRef::from_epsg(grid_srid as u32) {
             Err(e) => {
@@ -407,40 +735,61 @@ impl DatasourceInput for GdalDatasource {
             }
             Ok(sref) => sref,
         };
-        let layer_sref = geom_spatialref(ogr_layer, layer.geometry_field.as_ref());
+        // Datasets without SRS information (e.g. Shapefile without .prj) use the configured SRID
+        let layer_sref = geom_spatialref(ogr_layer, layer.geometry_field.as_ref()).or_else(|| {
+            layer
+                .srid
+                .and_then(|srid| SpatialRef::from_epsg(srid as u32).ok())
+        });
+        if layer_sref.is_none() {
+            warn!(
+                "Layer '{}': Unknown spatial reference - no reprojection",
+                layer.name
+            );
+        }
+        let reproject = match layer_sref {
+            Some(ref sref) if !layer.no_transform => sref.auth_code().ok() != Some(grid_srid),
+            _ => false,
+        };
+        if reproject {
+            info!(
+                "Layer '{}': Reprojecting geometry to SRID {}",
+                layer.name, grid_srid
+            );
+        }
+
         let transform = match layer_sref {
-            Some(ref sref) if !layer.no_transform => {
-                info!(
-                    "Layer '{}': Reprojecting geometry to SRID {}",
-                    layer.name, grid_srid
-                );
-                CoordTransform::new(sref, &grid_sref).ok()
-            }
+            Some(ref sref) if reproject => match CoordTransform::new(sref, &grid_sref) {
+                Ok(transform) => Some(transform),
+                Err(e) => {
+                    error!(
+                        "Layer '{}': Coordinate transformation failed: {}",
+                        layer.name, e
+                    );
+                    None
+                }
+            },
             _ => None,
         };
         self.geom_transform.insert(layer.name.clone(), transform);
 
         let transform = match layer_sref {
-            Some(ref sref) if !layer.no_transform => CoordTransform::new(&grid_sref, sref).ok(),
+            Some(ref sref) if reproject => CoordTransform::new(&grid_sref, sref).ok(),
             _ => None,
         };
         self.bbox_transform.insert(layer.name.clone(), transform);
 
-        if layer.simplify {
-            if layer.geometry_type != Some("POINT".to_string()) {
-                warn!(
-                    "Layer '{}': Simplification not supported for GDAL layers",
-                    layer.name
-                );
-            }
+        if layer.simplify && layer.tolerance_value(1.0).is_none() {
+            warn!(
+                "Layer '{}': Unsupported simplification tolerance '{}' for GDAL layers",
+                layer.name, layer.tolerance
+            );
         }
-        if layer.buffer_size.is_some() {
-            if layer.geometry_type != Some("POINT".to_string()) {
-                warn!(
-                    "Layer '{}': Clipping with buffer_size not supported for GDAL layers",
-                    layer.name
-                );
-            }
+        if layer.sort_by.is_some() {
+            warn!(
+                "Layer '{}': Sorting with sort_by not supported for GDAL layers",
+                layer.name
+            );
         }
     }
     fn retrieve_features<F>(
@@ -450,14 +799,31 @@ impl DatasourceInput for GdalDatasource {
         zoom: u8,
         grid: &Grid,
         mut read: F,
-    ) -> u64
+    ) -> error::Result<u64>
     where
         F: FnMut(&Feature),
     {
-        let mut dataset = Dataset::open(Path::new(&self.path)).unwrap();
-        let layer_name = layer.table_name.as_ref().unwrap();
-        debug!("retrieve_features layer: {}", layer_name);
-        let ogr_layer = dataset.layer_by_name(layer_name).unwrap();
+        let mut dataset = self
+            .dataset()
+            .map_err(|e| error::Error::Datasource(format!("{}: {}", self.path, e)))?;
+        let sql_layer;
+        let ogr_layer = match layer.query(zoom) {
+            Some(sql) => {
+                debug!("retrieve_features layer {}: {}", layer.name, sql);
+                sql_layer = execute_sql(&dataset, sql)
+                    .map_err(|e| error::Error::Query(format!("Layer '{}': {}", layer.name, e)))?;
+                &sql_layer.layer
+            }
+            None => {
+                let layer_name = layer.table_name_at(zoom).ok_or_else(|| {
+                    error::Error::Datasource(format!("Layer '{}': table_name missing", layer.name))
+                })?;
+                debug!("retrieve_features layer: {}", layer_name);
+                dataset.layer_by_name(layer_name).map_err(|e| {
+                    error::Error::Datasource(format!("Layer '{}': {}", layer.name, e))
+                })?
+            }
+        };
 
         let mut bbox_extent = if let Some(pixels) = layer.buffer_size {
             let pixel_width = grid.pixel_width(zoom);
@@ -472,6 +838,12 @@ impl DatasourceInput for GdalDatasource {
             extent.clone()
         };
 
+        let clip_extent = if layer.buffer_size.is_some() {
+            Some(bbox_extent.clone())
+        } else {
+            None
+        };
+
         // Spatial filter must be in layer SRS
         let transformation = self.bbox_transform.get(&layer.name).unwrap();
         if let Some(ref tr) = transformation {
@@ -479,7 +851,10 @@ impl DatasourceInput for GdalDatasource {
                 Ok(extent) => bbox_extent = extent,
                 Err(e) => {
                     error!("Unable to transform {:?}: {}", bbox_extent, e);
-                    return 0;
+                    return Err(error::Error::Query(format!(
+                        "Layer '{}': Unable to transform {:?}: {}",
+                        layer.name, bbox_extent, e
+                    )));
                 }
             }
         };
@@ -489,19 +864,46 @@ impl DatasourceInput for GdalDatasource {
             bbox_extent.maxx,
             bbox_extent.maxy,
         ).unwrap();
-        ogr_layer.set_spatial_filter(&bbox);
+        // Filter on selected geometry field
+        let geom_field_idx = layer
+            .geometry_field
+            .as_ref()
+            .and_then(|field| geom_field_index(ogr_layer, field));
+        match geom_field_idx {
+            Some(idx) if idx > 0 => unsafe {
+                gdal_sys::OGR_L_SetSpatialFilterEx(ogr_layer.c_layer(), idx, bbox.c_geometry());
+            },
+            _ => ogr_layer.set_spatial_filter(&bbox),
+        }
 
         let transformation = self.geom_transform.get(&layer.name).unwrap();
-        let fields_defn = ogr_layer.defn().fields().collect::<Vec<_>>();
+        let fields = layer_fields(layer, ogr_layer);
+        let tolerance = if layer.simplify {
+            layer.tolerance_value(grid.pixel_width(zoom))
+        } else {
+            None
+        };
         let mut cnt = 0;
         let query_limit = layer.query_limit.unwrap_or(0);
-        for feature in ogr_layer.features() {
+        // Dataset layers are reused between requests
+        unsafe { gdal_sys::OGR_L_ResetReading(ogr_layer.c_layer()) };
+        loop {
+            let c_feature = unsafe { gdal_sys::OGR_L_GetNextFeature(ogr_layer.c_layer()) };
+            if c_feature.is_null() {
+                break;
+            }
+            // Destroys the C handle when dropped
+            let feature =
+                unsafe { gdal::vector::Feature::_with_c_feature(ogr_layer.defn(), c_feature) };
             let feat = VectorFeature {
                 layer: layer,
-                fields_defn: &fields_defn,
+                fields: &fields,
                 grid_srid: grid.srid,
                 transform: transformation.as_ref(),
+                clip: clip_extent.as_ref(),
+                tolerance,
                 feature: &feature,
+                c_feature,
             };
             read(&feat);
             cnt += 1;
@@ -513,7 +915,12 @@ impl DatasourceInput for GdalDatasource {
                 break;
             }
         }
-        cnt
+        Ok(cnt)
+    }
+    fn check_connection(&self) -> error::Result<()> {
+        self.dataset()
+            .map(|_| ())
+            .map_err(|e| error::Error::Datasource(format!("{}: {}", self.path, e)))
     }
 }
 
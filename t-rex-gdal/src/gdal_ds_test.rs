@@ -10,7 +10,7 @@ use core::layer::Layer;
 use datasource::DatasourceInput;
 use gdal;
 use gdal::vector::Dataset;
-use gdal_ds::GdalDatasource;
+use gdal_ds::{tz_designator, GdalDatasource};
 use std::path::Path;
 
 fn gdal_version() -> i32 {
@@ -50,9 +50,9 @@ fn test_detect_layers() {
     let layers = ds.detect_layers(true);
     println!("{:?}", layers);
     assert_eq!(layers.len(), 3);
-    assert_eq!(format!("{:?}", layers[0]), r#"Layer { name: "ne_10m_populated_places", datasource: None, geometry_field: Some("geom"), geometry_type: Some("POINT"), srid: Some(3857), no_transform: false, fid_field: None, table_name: Some("ne_10m_populated_places"), query_limit: None, query: [], minzoom: None, maxzoom: None, tile_size: 4096, simplify: false, tolerance: "", buffer_size: None, make_valid: false, style: None }"#);
-    assert_eq!(format!("{:?}", layers[1]), r#"Layer { name: "ne_10m_rivers_lake_centerlines", datasource: None, geometry_field: Some("geom"), geometry_type: Some("LINE"), srid: Some(3857), no_transform: false, fid_field: None, table_name: Some("ne_10m_rivers_lake_centerlines"), query_limit: None, query: [], minzoom: None, maxzoom: None, tile_size: 4096, simplify: false, tolerance: "", buffer_size: None, make_valid: false, style: None }"#);
-    assert_eq!(format!("{:?}", layers[2]), r#"Layer { name: "ne_110m_admin_0_countries", datasource: None, geometry_field: Some("geom"), geometry_type: Some("POLYGON"), srid: Some(3857), no_transform: false, fid_field: None, table_name: Some("ne_110m_admin_0_countries"), query_limit: None, query: [], minzoom: None, maxzoom: None, tile_size: 4096, simplify: false, tolerance: "", buffer_size: None, make_valid: false, style: None }"#);
+    assert_eq!(format!("{:?}", layers[0]), r#"Layer { name: "ne_10m_populated_places", datasource: None, geometry_field: Some("geom"), geometry_type: Some("POINT"), srid: Some(3857), no_transform: false, fid_field: None, table_name: Some("ne_10m_populated_places"), query_limit: None, sort_by: None, query: [], attributes: [], json_keys: None, minzoom: None, maxzoom: None, zoom_offset: 0, tile_size: 4096, simplify: false, tolerance: "", buffer_size: None, make_valid: false, validity: None, dedup: None, split_by: None, merge_into: None, merge_attribute: "layer", style: None }"#);
+    assert_eq!(format!("{:?}", layers[1]), r#"Layer { name: "ne_10m_rivers_lake_centerlines", datasource: None, geometry_field: Some("geom"), geometry_type: Some("LINE"), srid: Some(3857), no_transform: false, fid_field: None, table_name: Some("ne_10m_rivers_lake_centerlines"), query_limit: None, sort_by: None, query: [], attributes: [], json_keys: None, minzoom: None, maxzoom: None, zoom_offset: 0, tile_size: 4096, simplify: false, tolerance: "", buffer_size: None, make_valid: false, validity: None, dedup: None, split_by: None, merge_into: None, merge_attribute: "layer", style: None }"#);
+    assert_eq!(format!("{:?}", layers[2]), r#"Layer { name: "ne_110m_admin_0_countries", datasource: None, geometry_field: Some("geom"), geometry_type: Some("POLYGON"), srid: Some(3857), no_transform: false, fid_field: None, table_name: Some("ne_110m_admin_0_countries"), query_limit: None, sort_by: None, query: [], attributes: [], json_keys: None, minzoom: None, maxzoom: None, zoom_offset: 0, tile_size: 4096, simplify: false, tolerance: "", buffer_size: None, make_valid: false, validity: None, dedup: None, split_by: None, merge_into: None, merge_attribute: "layer", style: None }"#);
 }
 
 #[test]
@@ -95,8 +95,12 @@ fn test_gdal_retrieve_points() {
             assert_eq!(feat.fid(), Some(4));
         }
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(reccnt, 1);
+
+    // Reuse of opened dataset
+    let cnt = ds.retrieve_features(&layer, &extent, 10, &grid, |_| {});
+    assert_eq!(cnt.unwrap(), 1);
 }
 
 #[test]
@@ -143,7 +147,7 @@ fn test_coord_transformation() {
             );
         }
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(reccnt, 1);
 }
 
@@ -175,7 +179,7 @@ fn test_gdal_retrieve_multilines() {
     // without buffer
     ds.retrieve_features(&layer, &extent, 10, &grid, |_| {
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(reccnt, 0);
 
     // with buffer
@@ -183,7 +187,7 @@ fn test_gdal_retrieve_multilines() {
 
     ds.retrieve_features(&layer, &extent, 22, &grid, |_| {
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(reccnt, 0);
 
     let mut reccnt = 0;
@@ -207,7 +211,7 @@ fn test_gdal_retrieve_multilines() {
             }
         }
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(reccnt, 5);
 }
 
@@ -249,7 +253,7 @@ fn test_gdal_retrieve_multipolys() {
             assert_eq!(None, feat.fid());
         }
         reccnt += 1;
-    });
+    }).unwrap();
     assert_eq!(reccnt, 1);
 }
 
@@ -276,3 +280,156 @@ fn test_no_transform() {
         "Some(Extent { minx: 22.32694, miny: 9.61387, maxx: 25.45679, maxy: 11.56232 })";
     assert_eq!(format!("{:.5?}", ext), extent_fake);
 }
+
+#[test]
+fn test_retrieve_errors() {
+    use core::Error;
+
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent(0, 0, 0);
+    let mut layer = Layer::new("missing");
+    layer.table_name = Some(String::from("missing"));
+
+    let ds = GdalDatasource::new("../data/missing.gpkg");
+    match ds.retrieve_features(&layer, &extent, 0, &grid, |_| {}) {
+        Err(Error::Datasource(_)) => {}
+        res => panic!("Unexpected result {:?}", res),
+    }
+
+    let ds = GdalDatasource::new("../data/natural_earth.gpkg");
+    match ds.retrieve_features(&layer, &extent, 0, &grid, |_| {}) {
+        Err(Error::Datasource(msg)) => assert!(msg.starts_with("Layer 'missing'")),
+        res => panic!("Unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_gdal_retrieve_sql() {
+    use core::layer::LayerQuery;
+    use core::Error;
+
+    let mut layer = Layer::new("points");
+    layer.srid = Some(3857);
+    layer.query = vec![LayerQuery {
+        minzoom: None,
+        maxzoom: None,
+        table_name: None,
+        sql: Some(String::from(
+            "SELECT geom, NAME, POP_MAX FROM ne_10m_populated_places WHERE SCALERANK <= 4",
+        )),
+    }];
+    let grid = Grid::web_mercator();
+    // Switzerland
+    let extent = Extent {
+        minx: 620000.0,
+        miny: 5700000.0,
+        maxx: 1200000.0,
+        maxy: 6100000.0,
+    };
+
+    let mut ds = GdalDatasource::new("../data/natural_earth.gpkg");
+    ds.prepare_queries(&layer, grid.srid);
+    let mut names = Vec::new();
+    ds.retrieve_features(&layer, &extent, 8, &grid, |feat| {
+        assert_eq!(2, feat.attributes().len());
+        assert_eq!(feat.attributes()[0].key, "NAME");
+        names.push(feat.attributes()[0].value.clone());
+    }).unwrap();
+    assert!(names.contains(&FeatureAttrValType::String("Bern".to_string())));
+    assert!(!names.contains(&FeatureAttrValType::String("Delemont".to_string())));
+    assert_eq!(ds.layer_extent(&layer, 3857), None);
+
+    layer.query[0].sql = Some(String::from("SELECT * FROM missing"));
+    match ds.retrieve_features(&layer, &extent, 8, &grid, |_| {}) {
+        Err(Error::Query(msg)) => assert!(msg.starts_with("Layer 'points'")),
+        res => panic!("Unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_gdal_field_types() {
+    let mut layer = Layer::new("field_types");
+    layer.table_name = Some(String::from("field_types"));
+    layer.srid = Some(3857);
+    layer.fid_field = Some(String::from("osm_id"));
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent(0, 0, 0);
+
+    let mut ds = GdalDatasource::new("../data/field_types.csv");
+    ds.prepare_queries(&layer, grid.srid);
+    let mut reccnt = 0;
+    ds.retrieve_features(&layer, &extent, 0, &grid, |feat| {
+        let attrs = feat.attributes();
+        let value = |key: &str| {
+            attrs
+                .iter()
+                .find(|attr| attr.key == key)
+                .map(|attr| attr.value.clone())
+        };
+        assert_eq!(value("osm_id"), Some(FeatureAttrValType::Int(4294967296)));
+        assert_eq!(
+            value("opened"),
+            Some(FeatureAttrValType::String("1848-11-28".to_string()))
+        );
+        assert_eq!(
+            value("updated"),
+            Some(FeatureAttrValType::String("2018-05-04T12:30:15+02:00".to_string()))
+        );
+        assert_eq!(value("active"), Some(FeatureAttrValType::Bool(true)));
+        assert_eq!(feat.fid(), Some(4294967296));
+        reccnt += 1;
+    }).unwrap();
+    assert_eq!(reccnt, 1);
+}
+
+#[test]
+fn test_gdal_retrieve_geometry_collections() {
+    let mut layer = Layer::new("geometry_collection");
+    layer.table_name = Some(String::from("geometry_collection"));
+    layer.srid = Some(3857);
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent(0, 0, 0);
+
+    let mut ds = GdalDatasource::new("../data/geometry_collection.csv");
+    ds.prepare_queries(&layer, grid.srid);
+    let mut geoms = Vec::new();
+    ds.retrieve_features(&layer, &extent, 0, &grid, |feat| {
+        geoms.push(format!("{:?}", feat.geometry()));
+    }).unwrap();
+    assert_eq!(geoms.len(), 2);
+    assert!(geoms[0].starts_with("Ok(MultiPolygon(MultiPolygonT { polygons: [PolygonT"));
+    assert_eq!(geoms[0].matches("PolygonT { rings").count(), 2);
+    assert!(geoms[1].starts_with("Ok(MultiPoint(MultiPointT { points: [Point { x: 1.0, y: 2.0"));
+    assert_eq!(geoms[1].matches("Point { x").count(), 3);
+}
+
+#[test]
+fn test_gdal_retrieve_z_geometries() {
+    let mut layer = Layer::new("geometry_z");
+    layer.table_name = Some(String::from("geometry_z"));
+    layer.srid = Some(3857);
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent(0, 0, 0);
+
+    let mut ds = GdalDatasource::new("../data/geometry_z.csv");
+    ds.prepare_queries(&layer, grid.srid);
+    let mut geoms = Vec::new();
+    ds.retrieve_features(&layer, &extent, 0, &grid, |feat| {
+        geoms.push(format!("{:?}", feat.geometry()));
+    }).unwrap();
+    assert_eq!(
+        geoms[0],
+        "Ok(Point(Point { x: 1.0, y: 2.0, srid: Some(3857) }))"
+    );
+    assert_eq!(geoms[1], "Ok(LineString(LineStringT { points: [Point { x: 0.0, y: 0.0, srid: Some(3857) }, Point { x: 10.0, y: 10.0, srid: Some(3857) }], srid: Some(3857) }))");
+    assert!(geoms[2].starts_with("Ok(MultiPolygon(MultiPolygonT { polygons: [PolygonT"));
+}
+
+#[test]
+fn test_tz_designator() {
+    assert_eq!(tz_designator(0), "");
+    assert_eq!(tz_designator(1), "");
+    assert_eq!(tz_designator(100), "Z");
+    assert_eq!(tz_designator(108), "+02:00");
+    assert_eq!(tz_designator(78), "-05:30");
+}
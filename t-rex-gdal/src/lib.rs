@@ -1,3 +1,5 @@
+//! GDAL/OGR datasource for the t-rex vector tile server.
+
 extern crate gdal;
 extern crate gdal_sys;
 #[macro_use]
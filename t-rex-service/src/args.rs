@@ -0,0 +1,69 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Command line arguments with environment variable fallback
+//!
+//! Every argument `--some-arg` can also be set with the environment variable
+//! `TREX_SOME_ARG`, e.g. `TREX_DBCONN=postgresql://user@host/db TREX_BIND=0.0.0.0 t_rex serve`.
+
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::env;
+
+/// Environment variable name for argument
+pub fn env_var_name(arg: &str) -> String {
+    format!("TREX_{}", arg.to_uppercase().replace('-', "_"))
+}
+
+/// Value of command line argument or its `TREX_*` environment variable
+pub fn arg_value(args: &ArgMatches, name: &str) -> Option<String> {
+    let key = env_var_name(name);
+    let vars = env::var(&key).into_iter().map(|val| (key.clone(), val));
+    arg_value_from_env(args, name, &vars.collect())
+}
+
+/// Value of command line argument or its `TREX_*` entry in `vars`
+fn arg_value_from_env(
+    args: &ArgMatches,
+    name: &str,
+    vars: &HashMap<String, String>,
+) -> Option<String> {
+    match args.value_of(name) {
+        Some(val) => Some(val.to_string()),
+        None => vars
+            .get(&env_var_name(name))
+            .filter(|val| !val.is_empty())
+            .cloned(),
+    }
+}
+
+#[test]
+fn test_env_args() {
+    use clap::App;
+
+    assert_eq!(env_var_name("dbconn"), "TREX_DBCONN");
+    assert_eq!(env_var_name("no-transform"), "TREX_NO_TRANSFORM");
+
+    let args = App::new("test")
+        .args_from_usage(
+            "--bind=[IPADDRESS] 'Bind web server to this address'
+                          --clip=[true|false] 'Clip geometries'
+                          --detect-geometry-types=[true|false] 'Detect geometry types'",
+        )
+        .get_matches_from(vec!["", "--bind", "127.0.0.1"]);
+    let mut vars = HashMap::new();
+    vars.insert("TREX_BIND".to_string(), "0.0.0.0".to_string());
+    vars.insert("TREX_DETECT_GEOMETRY_TYPES".to_string(), "false".to_string());
+    vars.insert("TREX_CLIP".to_string(), "".to_string());
+    assert_eq!(
+        arg_value_from_env(&args, "bind", &vars),
+        Some("127.0.0.1".to_string())
+    );
+    assert_eq!(
+        arg_value_from_env(&args, "detect-geometry-types", &vars),
+        Some("false".to_string())
+    );
+    assert_eq!(arg_value_from_env(&args, "clip", &vars), None);
+}
@@ -3,15 +3,21 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use args::arg_value;
 use clap::ArgMatches;
 use core::config::{ApplicationCfg, DatasourceCfg};
+use core::error;
 use core::feature::Feature;
 use core::grid::{Extent, Grid};
 use core::layer::Layer;
 use core::Config;
 #[cfg(not(feature = "with-gdal"))]
 use datasource::DummyDatasource as GdalDatasource;
-use datasource::{DatasourceInput, PostgisInput};
+#[cfg(not(feature = "with-geopackage"))]
+use datasource::DummyDatasource as GpkgDatasource;
+#[cfg(feature = "with-geopackage")]
+use datasource::GpkgDatasource;
+use datasource::{DatasourceInput, PluginDatasource, PostgisInput};
 #[cfg(feature = "with-gdal")]
 use gdal_ds::GdalDatasource;
 use std::collections::HashMap;
@@ -19,6 +25,8 @@ use std::collections::HashMap;
 pub enum Datasource {
     Postgis(PostgisInput),
     Gdal(GdalDatasource),
+    GeoPackage(GpkgDatasource),
+    Plugin(PluginDatasource),
 }
 
 impl DatasourceInput for Datasource {
@@ -26,36 +34,56 @@ impl DatasourceInput for Datasource {
         match self {
             &Datasource::Postgis(ref ds) => Datasource::Postgis(ds.connected()),
             &Datasource::Gdal(ref ds) => Datasource::Gdal(ds.connected()),
+            &Datasource::GeoPackage(ref ds) => Datasource::GeoPackage(ds.connected()),
+            &Datasource::Plugin(ref ds) => Datasource::Plugin(ds.connected()),
         }
     }
     fn detect_layers(&self, detect_geometry_types: bool) -> Vec<Layer> {
         match self {
             &Datasource::Postgis(ref ds) => ds.detect_layers(detect_geometry_types),
             &Datasource::Gdal(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::GeoPackage(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::Plugin(ref ds) => ds.detect_layers(detect_geometry_types),
         }
     }
     fn detect_data_columns(&self, layer: &Layer, sql: Option<&String>) -> Vec<(String, String)> {
         match self {
             &Datasource::Postgis(ref ds) => ds.detect_data_columns(layer, sql),
             &Datasource::Gdal(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::GeoPackage(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::Plugin(ref ds) => ds.detect_data_columns(layer, sql),
+        }
+    }
+    fn detect_field_types(&self, layer: &Layer, sql: Option<&String>) -> Vec<(String, String)> {
+        match self {
+            &Datasource::Postgis(ref ds) => ds.detect_field_types(layer, sql),
+            &Datasource::Gdal(ref ds) => ds.detect_field_types(layer, sql),
+            &Datasource::GeoPackage(ref ds) => ds.detect_field_types(layer, sql),
+            &Datasource::Plugin(ref ds) => ds.detect_field_types(layer, sql),
         }
     }
     fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
         match self {
             &Datasource::Postgis(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
             &Datasource::Gdal(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::GeoPackage(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::Plugin(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
         }
     }
     fn layer_extent(&self, layer: &Layer, grid_srid: i32) -> Option<Extent> {
         match self {
             &Datasource::Postgis(ref ds) => ds.layer_extent(layer, grid_srid),
             &Datasource::Gdal(ref ds) => ds.layer_extent(layer, grid_srid),
+            &Datasource::GeoPackage(ref ds) => ds.layer_extent(layer, grid_srid),
+            &Datasource::Plugin(ref ds) => ds.layer_extent(layer, grid_srid),
         }
     }
     fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
         match self {
             &mut Datasource::Postgis(ref mut ds) => ds.prepare_queries(layer, grid_srid),
             &mut Datasource::Gdal(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::GeoPackage(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::Plugin(ref mut ds) => ds.prepare_queries(layer, grid_srid),
         }
     }
     fn retrieve_features<F>(
@@ -65,13 +93,46 @@ impl DatasourceInput for Datasource {
         zoom: u8,
         grid: &Grid,
         read: F,
-    ) -> u64
+    ) -> error::Result<u64>
     where
         F: FnMut(&Feature),
     {
         match self {
             &Datasource::Postgis(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
             &Datasource::Gdal(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+            &Datasource::GeoPackage(ref ds) => {
+                ds.retrieve_features(layer, extent, zoom, grid, read)
+            }
+            &Datasource::Plugin(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+        }
+    }
+    fn check_connection(&self) -> error::Result<()> {
+        match self {
+            &Datasource::Postgis(ref ds) => ds.check_connection(),
+            &Datasource::Gdal(ref ds) => ds.check_connection(),
+            &Datasource::GeoPackage(ref ds) => ds.check_connection(),
+            &Datasource::Plugin(ref ds) => ds.check_connection(),
+        }
+    }
+    fn modified_extents(
+        &self,
+        layer: &Layer,
+        since: &str,
+        grid_srid: i32,
+    ) -> error::Result<Vec<Extent>> {
+        match self {
+            &Datasource::Postgis(ref ds) => ds.modified_extents(layer, since, grid_srid),
+            &Datasource::Gdal(ref ds) => ds.modified_extents(layer, since, grid_srid),
+            &Datasource::GeoPackage(ref ds) => ds.modified_extents(layer, since, grid_srid),
+            &Datasource::Plugin(ref ds) => ds.modified_extents(layer, since, grid_srid),
+        }
+    }
+    fn split_values(&self, layer: &Layer) -> Vec<String> {
+        match self {
+            &Datasource::Postgis(ref ds) => ds.split_values(layer),
+            &Datasource::Gdal(ref ds) => ds.split_values(layer),
+            &Datasource::GeoPackage(ref ds) => ds.split_values(layer),
+            &Datasource::Plugin(ref ds) => ds.split_values(layer),
         }
     }
 }
@@ -82,6 +143,10 @@ impl<'a> Config<'a, DatasourceCfg> for Datasource {
             PostgisInput::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Postgis(ds)))
         } else if ds_cfg.path.is_some() {
             GdalDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Gdal(ds)))
+        } else if ds_cfg.gpkg.is_some() {
+            GpkgDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::GeoPackage(ds)))
+        } else if ds_cfg.plugin.is_some() {
+            PluginDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Plugin(ds)))
         } else {
             Err(format!("Unsupported datasource"))
         }
@@ -97,6 +162,18 @@ impl<'a> Config<'a, DatasourceCfg> for Datasource {
         match self {
             &Datasource::Postgis(ref ds) => ds.gen_runtime_config(),
             &Datasource::Gdal(ref ds) => ds.gen_runtime_config(),
+            &Datasource::GeoPackage(ref ds) => ds.gen_runtime_config(),
+            &Datasource::Plugin(ref ds) => ds.gen_runtime_config(),
+        }
+    }
+}
+
+impl Datasource {
+    /// Connected datasource, sharing the connection pool of an already connected PostGIS datasource
+    pub fn connected_shared(&self, shared: Option<&PostgisInput>) -> Datasource {
+        match (self, shared) {
+            (&Datasource::Postgis(_), Some(ds)) => Datasource::Postgis(ds.clone()),
+            _ => self.connected(),
         }
     }
 }
@@ -152,15 +229,15 @@ impl Datasources {
     }
     pub fn from_args(args: &ArgMatches) -> Self {
         let mut datasources = Datasources::new();
-        if let Some(dbconn) = args.value_of("dbconn") {
+        if let Some(dbconn) = arg_value(args, "dbconn") {
             datasources.add(
                 &"dbconn".to_string(),
-                Datasource::Postgis(PostgisInput::new(dbconn)),
+                Datasource::Postgis(PostgisInput::new(&dbconn)),
             );
         }
-        if let Some(datasource) = args.value_of("datasource") {
+        if let Some(datasource) = arg_value(args, "datasource") {
             #[cfg(feature = "with-gdal")]
-            let ds = Some(Datasource::Gdal(GdalDatasource::new(datasource)));
+            let ds = Some(Datasource::Gdal(GdalDatasource::new(&datasource)));
             #[cfg(not(feature = "with-gdal"))]
             let ds = {
                 error!("GDAL datasource not supported in this build");
@@ -174,6 +251,26 @@ impl Datasources {
         datasources.setup();
         datasources
     }
+    /// Connected PostGIS datasources by name
+    pub fn postgis_pools(&self) -> HashMap<String, PostgisInput> {
+        self.datasources
+            .iter()
+            .filter_map(|(name, ds)| match ds {
+                &Datasource::Postgis(ref ds) => Some((name.clone(), ds.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Connect all datasources, sharing the connection pools of `pools` with the same name
+    pub fn connected(&self, pools: &HashMap<String, PostgisInput>) -> Datasources {
+        let mut datasources = Datasources::new();
+        datasources.default = self.default.clone();
+        for (name, ds) in &self.datasources {
+            datasources.add(name, ds.connected_shared(pools.get(name)));
+        }
+        datasources.setup();
+        datasources
+    }
     /// Finish initialization
     pub fn setup(&mut self) {
         // TODO: default should be first in config, not first in HashMap
@@ -221,6 +318,19 @@ fn test_datasource_from_config() {
     );
 }
 
+#[test]
+fn test_plugin_datasource_from_config() {
+    let toml = r#"
+        #[[datasource]]
+        plugin = "python3 plugin.py"
+        "#;
+    let plugin = match ds_from_config(toml).unwrap() {
+        Datasource::Plugin(plugin) => plugin,
+        _ => panic!(),
+    };
+    assert_eq!(plugin.command, "python3 plugin.py");
+}
+
 #[test]
 fn test_datasource_config_errors() {
     assert_eq!(
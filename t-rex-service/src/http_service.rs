@@ -0,0 +1,150 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::Error;
+use futures::future::{ok, FutureResult};
+use futures::{Async, Poll};
+use http::{header, Method, Request, Response, StatusCode};
+use mvt::tile::TileCompression;
+use mvt_service::MvtService;
+use std::str::FromStr;
+use std::sync::Arc;
+use tower_service::Service;
+
+/// Routes served by `TileService`, relative to its mount point
+#[derive(PartialEq, Debug)]
+pub enum Route {
+    /// `/index.json`
+    Index,
+    /// `/{tileset}.json`
+    TileJson(String),
+    /// `/{tileset}/{z}/{x}/{y}.pbf`
+    Tile(String, u8, u32, u32),
+}
+
+impl Route {
+    pub fn from_path(path: &str) -> Option<Route> {
+        let parts = path.trim_left_matches('/').split('/').collect::<Vec<_>>();
+        match parts.len() {
+            1 if parts[0] == "index.json" => Some(Route::Index),
+            1 if parts[0].ends_with(".json") => {
+                let tileset = parts[0].trim_right_matches(".json");
+                if tileset.is_empty() {
+                    None
+                } else {
+                    Some(Route::TileJson(tileset.to_string()))
+                }
+            }
+            4 if parts[3].ends_with(".pbf") => {
+                let z = u8::from_str(parts[1]).ok()?;
+                let x = u32::from_str(parts[2]).ok()?;
+                let y = u32::from_str(parts[3].trim_right_matches(".pbf")).ok()?;
+                Some(Route::Tile(parts[0].to_string(), z, x, y))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// MvtService exposed as `tower::Service`
+///
+/// Serves tiles and metadata like `t_rex serve`. Requests have to be
+/// stripped of the mount point prefix.
+#[derive(Clone)]
+pub struct TileService {
+    pub service: Arc<MvtService>,
+    /// Base URL used for tile URLs in TileJSON
+    pub baseurl: String,
+}
+
+impl TileService {
+    pub fn new(service: MvtService, baseurl: &str) -> TileService {
+        TileService {
+            service: Arc::new(service),
+            baseurl: baseurl.to_string(),
+        }
+    }
+    fn json_response(json: Option<String>) -> Response<Vec<u8>> {
+        match json {
+            Some(json) => Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(json.into_bytes())
+                .unwrap(),
+            None => TileService::status_response(StatusCode::NOT_FOUND),
+        }
+    }
+    fn status_response(status: StatusCode) -> Response<Vec<u8>> {
+        Response::builder().status(status).body(Vec::new()).unwrap()
+    }
+    /// Handle request
+    pub fn response<B>(&self, req: &Request<B>) -> Response<Vec<u8>> {
+        if req.method() != Method::GET {
+            return TileService::status_response(StatusCode::METHOD_NOT_ALLOWED);
+        }
+        match Route::from_path(req.uri().path()) {
+            Some(Route::Index) => TileService::json_response(
+                self.service
+                    .get_mvt_metadata()
+                    .ok()
+                    .map(|json| json.to_string()),
+            ),
+            Some(Route::TileJson(ref tileset)) => {
+                let json = self.service.get_tileset(tileset).and_then(|_| {
+                    self.service
+                        .get_tilejson(&self.baseurl, tileset)
+                        .ok()
+                        .map(|json| json.to_string())
+                });
+                TileService::json_response(json)
+            }
+            Some(Route::Tile(ref tileset, z, x, y)) => {
+                let accepted = req
+                    .headers()
+                    .get(header::ACCEPT_ENCODING)
+                    .and_then(|val| val.to_str().ok())
+                    .map(TileCompression::from_accept_encoding)
+                    .unwrap_or_default();
+                match self
+                    .service
+                    .tile_cached_compressed(tileset, x, y, z, &accepted, None)
+                {
+                    Ok(Some((tile, compression))) => {
+                        let mut resp = Response::builder();
+                        resp.header(header::CONTENT_TYPE, "application/x-protobuf");
+                        if let Some(encoding) = compression.content_encoding() {
+                            resp.header(header::CONTENT_ENCODING, encoding);
+                        }
+                        resp.body(tile).unwrap()
+                    }
+                    Ok(None) => TileService::status_response(StatusCode::NO_CONTENT),
+                    Err(Error::UnknownTileset(_)) => {
+                        TileService::status_response(StatusCode::NOT_FOUND)
+                    }
+                    Err(Error::InvalidTile(_)) => {
+                        TileService::status_response(StatusCode::BAD_REQUEST)
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        TileService::status_response(StatusCode::INTERNAL_SERVER_ERROR)
+                    }
+                }
+            }
+            None => TileService::status_response(StatusCode::NOT_FOUND),
+        }
+    }
+}
+
+impl<B> Service<Request<B>> for TileService {
+    type Response = Response<Vec<u8>>;
+    type Error = Error;
+    type Future = FutureResult<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        ok(self.response(&req))
+    }
+}
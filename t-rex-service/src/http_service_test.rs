@@ -0,0 +1,23 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use http_service::Route;
+
+#[test]
+fn test_routes() {
+    assert_eq!(Route::from_path("/index.json"), Some(Route::Index));
+    assert_eq!(
+        Route::from_path("/osm.json"),
+        Some(Route::TileJson("osm".to_string()))
+    );
+    assert_eq!(
+        Route::from_path("/osm/6/33/22.pbf"),
+        Some(Route::Tile("osm".to_string(), 6, 33, 22))
+    );
+    assert_eq!(Route::from_path("/osm/6/33/x.pbf"), None);
+    assert_eq!(Route::from_path("/osm/6/33/22.png"), None);
+    assert_eq!(Route::from_path("/.json"), None);
+    assert_eq!(Route::from_path("/"), None);
+}
@@ -3,8 +3,22 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+//! Vector tile service of the t-rex vector tile server.
+//!
+//! `MvtService` renders tiles of the configured tilesets and can be embedded
+//! into other applications:
+//!
+//! ```rust,ignore
+//! let service = MvtService::from_config_file("config.toml")?;
+//! let tile = service.tile_cached("osm", 33, 22, 6, false, None)?;
+//! ```
+
 extern crate clap;
 extern crate elementtree;
+#[cfg(feature = "with-tower")]
+extern crate futures;
+#[cfg(feature = "with-tower")]
+extern crate http;
 #[macro_use]
 extern crate log;
 extern crate pbr;
@@ -14,10 +28,16 @@ extern crate serde;
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
+extern crate serde_yaml;
 
 extern crate t_rex_core;
 #[cfg(feature = "with-gdal")]
 extern crate t_rex_gdal;
+extern crate toml;
+#[cfg(feature = "with-tower")]
+extern crate tower_service;
+#[cfg(feature = "with-tracing")]
+extern crate tracing;
 
 use t_rex_core::cache;
 use t_rex_core::core;
@@ -27,10 +47,21 @@ use t_rex_core::service;
 #[cfg(feature = "with-gdal")]
 use t_rex_gdal::gdal_ds;
 
+pub mod args;
+mod config_import;
 pub mod datasource_type;
+#[cfg(feature = "with-tower")]
+pub mod http_service;
+#[cfg(all(test, feature = "with-tower"))]
+mod http_service_test;
+mod mapnik_reader;
 pub mod metadata;
 pub mod mvt_service;
 #[cfg(test)]
 mod mvt_service_test;
+pub mod ogcapi;
 mod qgs_reader;
+pub mod seed_state;
+pub use config_import::import_config;
+pub use mapnik_reader::mapnik_config;
 pub use qgs_reader::read_qgs;
@@ -0,0 +1,360 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use config_import::gen_import_config;
+use core::grid::{extent_from_merc, Extent};
+use core::layer::{Layer, LayerQuery};
+#[cfg(test)]
+use core::Config;
+use datasource::PostgisInput;
+use datasource_type::{Datasource, Datasources};
+use elementtree::Element;
+use qgs_reader::{pg_uri, read_xml};
+use service::tileset::Tileset;
+use std::collections::HashMap;
+use std::f64;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Scale denominator of zoom level 0 in Web Mercator (0.28mm pixel size)
+const SCALE_DENOMINATOR_Z0: f64 = 559082264.028;
+
+/// First zoom level with a scale denominator below `scale`
+fn minzoom_from_scale(scale: f64) -> u8 {
+    (SCALE_DENOMINATOR_Z0 / scale)
+        .log2()
+        .ceil()
+        .max(0.0)
+        .min(22.0) as u8
+}
+
+/// Last zoom level with a scale denominator of at least `scale`
+fn maxzoom_from_scale(scale: f64) -> u8 {
+    (SCALE_DENOMINATOR_Z0 / scale)
+        .log2()
+        .floor()
+        .max(0.0)
+        .min(22.0) as u8
+}
+
+fn parse_scale(scale: Option<&str>) -> Option<f64> {
+    scale.and_then(|s| f64::from_str(s.trim()).ok())
+}
+
+/// Visible scale denominator range (min, max) of a layer
+fn scale_range(layer: &Element, styles: &HashMap<&str, &Element>) -> (Option<f64>, Option<f64>) {
+    // Mapnik 2 used `minzoom` and `maxzoom` for scale denominators
+    let min = parse_scale(
+        layer
+            .get_attr("minimum-scale-denominator")
+            .or(layer.get_attr("minzoom")),
+    );
+    let max = parse_scale(
+        layer
+            .get_attr("maximum-scale-denominator")
+            .or(layer.get_attr("maxzoom")),
+    );
+    if min.is_some() || max.is_some() {
+        return (min, max);
+    }
+    // Union of style rule ranges
+    let mut rule_min = f64::INFINITY;
+    let mut rule_max: f64 = 0.0;
+    let mut has_rules = false;
+    for stylename in layer.find_all("StyleName") {
+        if let Some(style) = styles.get(stylename.text().trim()) {
+            for rule in style.find_all("Rule") {
+                has_rules = true;
+                let min = parse_scale(rule.find("MinScaleDenominator").map(|e| e.text()));
+                let max = parse_scale(rule.find("MaxScaleDenominator").map(|e| e.text()));
+                rule_min = rule_min.min(min.unwrap_or(0.0));
+                rule_max = rule_max.max(max.unwrap_or(f64::INFINITY));
+            }
+        }
+    }
+    if !has_rules {
+        return (None, None);
+    }
+    (
+        if rule_min > 0.0 { Some(rule_min) } else { None },
+        if rule_max.is_finite() {
+            Some(rule_max)
+        } else {
+            None
+        },
+    )
+}
+
+fn parameters(ds: &Element) -> HashMap<String, String> {
+    ds.find_all("Parameter")
+        .filter_map(|param| {
+            param
+                .get_attr("name")
+                .map(|name| (name.to_string(), param.text().trim().to_string()))
+        })
+        .collect()
+}
+
+/// SRID of a Proj.4 definition
+fn srid_from_srs(srs: &str) -> Option<i32> {
+    if let Some(pos) = srs.find("+init=epsg:") {
+        let code = srs[pos + 11..].split_whitespace().next().unwrap_or("");
+        return i32::from_str(code).ok();
+    }
+    if srs.contains("+proj=merc") && srs.contains("+a=6378137") && srs.contains("+b=6378137") {
+        Some(3857)
+    } else if srs.contains("+proj=longlat") && srs.contains("WGS84") {
+        Some(4326)
+    } else {
+        None
+    }
+}
+
+/// SQL of a subquery like `(SELECT ...) AS data`
+fn subquery_sql(table: &str) -> Option<String> {
+    let table = table.trim();
+    if table.starts_with('(') {
+        table.rfind(')').map(|end| table[1..end].trim().to_string())
+    } else {
+        None
+    }
+}
+
+fn extent_union(ext1: Option<Extent>, ext2: Extent) -> Extent {
+    match ext1 {
+        Some(ext) => Extent {
+            minx: ext.minx.min(ext2.minx),
+            miny: ext.miny.min(ext2.miny),
+            maxx: ext.maxx.max(ext2.maxx),
+            maxy: ext.maxy.max(ext2.maxy),
+        },
+        None => ext2,
+    }
+}
+
+/// WGS84 extent of Mapnik `extent` parameter
+fn wgs84_extent(extent: &str, srid: Option<i32>) -> Option<Extent> {
+    let coords: Vec<f64> = extent
+        .split(|c| c == ',' || c == ' ')
+        .filter_map(|v| f64::from_str(v).ok())
+        .collect();
+    if coords.len() != 4 {
+        return None;
+    }
+    let ext = Extent {
+        minx: coords[0],
+        miny: coords[1],
+        maxx: coords[2],
+        maxy: coords[3],
+    };
+    match srid {
+        Some(4326) => Some(ext),
+        Some(3857) => Some(extent_from_merc(&ext)),
+        _ => None,
+    }
+}
+
+/// Read PostGIS layers of a Mapnik XML stylesheet
+pub fn read_mapnik(fname: &str) -> (Datasources, Tileset) {
+    let root = read_xml(fname).expect("Invalid Mapnik XML file");
+    let map_srs = root.get_attr("srs").unwrap_or("");
+    let styles: HashMap<&str, &Element> = root
+        .find_all("Style")
+        .filter_map(|style| style.get_attr("name").map(|name| (name, style)))
+        .collect();
+    // Datasource templates referenced with `base`
+    let templates: HashMap<&str, HashMap<String, String>> = root
+        .find_all("Datasource")
+        .filter_map(|ds| ds.get_attr("name").map(|name| (name, parameters(ds))))
+        .collect();
+    let mapnik_name = Path::new(fname).file_stem().unwrap().to_str().unwrap();
+    let mut datasources = Datasources::new();
+    let mut ds_names: HashMap<String, String> = HashMap::new();
+    let mut tileset = Tileset {
+        name: mapnik_name.to_string(),
+        minzoom: None,
+        maxzoom: None,
+        attribution: None,
+        extent: None,
+        center: None,
+        start_zoom: None,
+        layers: Vec::new(),
+        cache_limits: None,
+        cache_control_max_age: None,
+        pixel_size: None,
+        utfgrid_key: None,
+        style_json: None,
+    };
+    let mut extent = None;
+    for mapnik_layer in root.find_all("Layer") {
+        let name = mapnik_layer
+            .get_attr("name")
+            .expect("Missing attribute 'name'");
+        let ds = match mapnik_layer.find("Datasource") {
+            Some(ds) => ds,
+            None => continue,
+        };
+        let mut params = ds
+            .get_attr("base")
+            .and_then(|base| templates.get(base))
+            .cloned()
+            .unwrap_or(HashMap::new());
+        params.extend(parameters(ds));
+        if params.get("type").map(|t| t.as_str()) != Some("postgis") {
+            info!("Layer '{}': Skipping non-PostGIS layer", name);
+            continue;
+        }
+        let table = match params.get("table") {
+            Some(table) => table.clone(),
+            None => {
+                warn!("Layer '{}': Missing parameter 'table'", name);
+                continue;
+            }
+        };
+
+        let mut layer = Layer::new(name);
+        match subquery_sql(&table) {
+            Some(sql) => layer.query.push(LayerQuery {
+                minzoom: None,
+                maxzoom: None,
+                table_name: None,
+                sql: Some(sql),
+            }),
+            None => layer.table_name = Some(table),
+        }
+        layer.geometry_field = params.get("geometry_field").cloned();
+        layer.fid_field = params.get("key_field").cloned();
+        layer.srid = match params.get("srid") {
+            Some(srid) if srid == "900913" => Some(3857),
+            Some(srid) => i32::from_str(srid).ok(),
+            None => srid_from_srs(mapnik_layer.get_attr("srs").unwrap_or(map_srs)),
+        };
+        let (min_scale, max_scale) = scale_range(mapnik_layer, &styles);
+        layer.minzoom =
+            max_scale
+                .map(minzoom_from_scale)
+                .and_then(|z| if z > 0 { Some(z) } else { None });
+        layer.maxzoom =
+            min_scale
+                .map(maxzoom_from_scale)
+                .and_then(|z| if z < 22 { Some(z) } else { None });
+        if let Some(ext) = params
+            .get("extent")
+            .and_then(|ext| wgs84_extent(ext, layer.srid))
+        {
+            extent = Some(extent_union(extent, ext));
+        }
+
+        // One datasource per connection
+        let conn_params: HashMap<&str, &str> = params
+            .iter()
+            .filter(|&(k, _)| ["host", "port", "user", "password", "dbname"].contains(&k.as_str()))
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let dbconn = pg_uri(&conn_params);
+        if !ds_names.contains_key(&dbconn) {
+            let dbname = params
+                .get("dbname")
+                .cloned()
+                .unwrap_or("postgis".to_string());
+            let mut ds_name = dbname.clone();
+            let mut no = 1;
+            while datasources.datasources.contains_key(&ds_name) {
+                no += 1;
+                ds_name = format!("{}{}", dbname, no);
+            }
+            datasources.add(&ds_name, Datasource::Postgis(PostgisInput::new(&dbconn)));
+            ds_names.insert(dbconn.clone(), ds_name);
+        }
+        layer.datasource = ds_names.get(&dbconn).cloned();
+        tileset.layers.push(layer);
+    }
+    tileset.extent = extent;
+    datasources.setup();
+    (datasources, tileset)
+}
+
+/// t-rex configuration of the PostGIS layers of a Mapnik XML stylesheet
+pub fn mapnik_config(fname: &str) -> String {
+    let (datasources, tileset) = read_mapnik(fname);
+    gen_import_config(&datasources, &vec![tileset])
+}
+
+#[test]
+fn test_zoom_from_scale() {
+    assert_eq!(minzoom_from_scale(750000.0), 10);
+    assert_eq!(minzoom_from_scale(1e12), 0);
+    assert_eq!(maxzoom_from_scale(25000.0), 14);
+    assert_eq!(maxzoom_from_scale(1.0), 22);
+}
+
+#[test]
+fn test_srid_from_srs() {
+    assert_eq!(srid_from_srs("+init=epsg:2056"), Some(2056));
+    assert_eq!(
+        srid_from_srs("+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over"),
+        Some(3857)
+    );
+    assert_eq!(
+        srid_from_srs("+proj=longlat +ellps=WGS84 +datum=WGS84 +no_defs"),
+        Some(4326)
+    );
+    assert_eq!(srid_from_srs(""), None);
+}
+
+#[test]
+fn test_subquery_sql() {
+    assert_eq!(subquery_sql("planet_osm_line"), None);
+    assert_eq!(
+        subquery_sql("(SELECT way, name FROM planet_osm_line WHERE highway IS NOT NULL) AS roads"),
+        Some("SELECT way, name FROM planet_osm_line WHERE highway IS NOT NULL".to_string())
+    );
+}
+
+#[test]
+fn test_read_mapnik() {
+    let (dss, ts) = read_mapnik("src/test/mapnik.xml");
+    assert_eq!(ts.name, "mapnik");
+    assert_eq!(dss.datasources.len(), 1);
+    assert_eq!(
+        dss.datasources["osm"].gen_runtime_config(),
+        r#"
+[[datasource]]
+dbconn = "postgresql://osm@localhost:5432/osm"
+"#
+    );
+    // Shapefile layer is skipped
+    assert_eq!(ts.layers.len(), 2);
+
+    let ref roads = ts.layers[0];
+    assert_eq!(roads.name, "roads");
+    assert_eq!(roads.datasource, Some("osm".to_string()));
+    assert_eq!(roads.table_name, None);
+    assert_eq!(
+        roads.query(12),
+        Some(
+            &"SELECT way, highway, name FROM planet_osm_line WHERE highway IS NOT NULL".to_string()
+        )
+    );
+    assert_eq!(roads.geometry_field, Some("way".to_string()));
+    assert_eq!(roads.fid_field, Some("osm_id".to_string()));
+    assert_eq!(roads.srid, Some(3857));
+    // Union of style rules
+    assert_eq!(roads.minzoom, Some(10));
+    assert_eq!(roads.maxzoom, None);
+
+    let ref buildings = ts.layers[1];
+    assert_eq!(buildings.table_name, Some("planet_osm_polygon".to_string()));
+    assert_eq!(buildings.minzoom, Some(14));
+    assert_eq!(buildings.maxzoom, Some(18));
+
+    let ext = ts.extent.unwrap();
+    assert!((ext.minx + 180.0).abs() < 1e-6);
+    assert!((ext.maxx - 180.0).abs() < 1e-6);
+
+    let config = mapnik_config("src/test/mapnik.xml");
+    assert!(config.contains("[[tileset]]\nname = \"mapnik\"\n"));
+    assert!(config.contains("minzoom = 14\nmaxzoom = 18\n"));
+}
@@ -3,16 +3,19 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use core::grid::{Grid, TileScheme};
+use core::layer::Layer;
+use core::Error;
 use datasource::DatasourceInput;
 use mvt_service::MvtService;
 use serde_json;
 use std::cmp;
 
-type JsonResult = Result<serde_json::Value, serde_json::error::Error>;
+type JsonResult = Result<serde_json::Value, Error>;
 
 impl MvtService {
     /// Service metadata for backend web application
-    pub fn get_mvt_metadata(&self) -> JsonResult {
+    pub fn get_mvt_metadata(&self) -> Result<serde_json::Value, serde_json::error::Error> {
         #[derive(Serialize)]
         struct MvtInfo {
             tilesets: Vec<TilesetInfo>,
@@ -68,10 +71,14 @@ impl MvtService {
     fn get_tilejson_metadata(&self, tileset: &str) -> JsonResult {
         let ts = self
             .get_tileset(tileset)
-            .expect(&format!("Tileset '{}' not found", tileset));
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
         let ext = ts.get_extent();
         let center = ts.get_center();
         let zoom = ts.get_start_zoom();
+        let scheme = match self.grid.tile_scheme {
+            TileScheme::Xyz => "xyz",
+            TileScheme::Tms => "tms",
+        };
         Ok(json!({
             "id": tileset,
             "name": tileset,
@@ -79,7 +86,7 @@ impl MvtService {
             "attribution": ts.attribution(),
             "format": "pbf",
             "version": "2.0.0",
-            "scheme": "xyz",
+            "scheme": scheme,
             "bounds": [ext.minx,
                        ext.miny,
                        ext.maxx,
@@ -92,17 +99,18 @@ impl MvtService {
             // Optional. Default: 30. >= 0, <= 30. (Mapbox Style default: 22)
             "maxzoom": ts.maxzoom(),
             "center": [center.0, center.1, zoom],
+            "tileSize": ts.pixel_size.unwrap_or(self.grid.tile_size()),
             "basename": tileset
         }))
     }
     fn get_tilejson_layers(&self, tileset: &str) -> JsonResult {
         let ts = self
             .get_tileset(tileset)
-            .expect(&format!("Tileset '{}' not found", tileset));
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
         let layers = self.get_tileset_layers(tileset);
         let layers_metadata: Vec<serde_json::Value> = layers
             .iter()
-            .map(|layer| {
+            .flat_map(|layer| {
                 let meta = layer.metadata();
                 let query = layer.query(layer.maxzoom(22));
                 let mut meta_json = json!({
@@ -125,45 +133,94 @@ impl MvtService {
                         .unwrap()
                         .insert(field.clone(), json!(""));
                 }
-                meta_json
+                let mut layer_jsons: Vec<serde_json::Value> = self
+                    .split_layer_names(layer)
+                    .into_iter()
+                    .map(|name| {
+                        let mut split_json = meta_json.clone();
+                        split_json["id"] = json!(name);
+                        split_json["name"] = json!(name);
+                        split_json
+                    })
+                    .collect();
+                layer_jsons.insert(0, meta_json);
+                layer_jsons
             })
             .collect();
         Ok(json!(layers_metadata))
     }
     // MVT layers in TileJSON manifest
     // https://github.com/mapbox/tilejson-spec/tree/3.0-vector_layers/3.0#315-vector_layers
+    // Field descriptions contain the attribute type (String, Number, Boolean)
     fn get_tilejson_vector_layers(&self, tileset: &str) -> JsonResult {
         let ts = self
             .get_tileset(tileset)
-            .expect(&format!("Tileset '{}' not found", tileset));
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
         let layers = self.get_tileset_layers(tileset);
-        let vector_layers: Vec<serde_json::Value> = layers
-            .iter()
-            .map(|layer| {
-                let meta = layer.metadata();
-                let query = layer.query(layer.maxzoom(22));
-                let mut layer_json = json!({
-                    "id": meta.get("id").unwrap(),
-                    "description": meta.get("description").unwrap(), // Optional
-                    // lowest zoom level whose tiles this layer appears in.
-                    // must be greater than or equal to the tileset's minzoom
-                    "minzoom": cmp::max(ts.minzoom(), layer.minzoom()),
-                    // highest zoom level whose tiles this layer appears in.
-                    // must  be less than or equal to the tileset's maxzoom
-                    "maxzoom": cmp::min(ts.maxzoom(), layer.maxzoom(22)),
-                    "fields": {}
-                });
-                //insert fields
-                let fields = self.ds(&layer).unwrap().detect_data_columns(&layer, query);
-                for (ref field, _) in fields {
-                    layer_json["fields"]
-                        .as_object_mut()
-                        .unwrap()
-                        .insert(field.clone(), json!(""));
+        let mut vector_layers: Vec<serde_json::Value> = Vec::new();
+        for layer in layers {
+            let meta = layer.metadata();
+            let query = layer.query(layer.maxzoom(22));
+            let minzoom = cmp::max(ts.minzoom(), layer.minzoom());
+            let maxzoom = cmp::min(ts.maxzoom(), layer.maxzoom(22));
+            let mut layer_json = json!({
+                "id": layer.mvt_name(),
+                "description": meta.get("description").unwrap(), // Optional
+                // lowest zoom level whose tiles this layer appears in.
+                // must be greater than or equal to the tileset's minzoom
+                "minzoom": minzoom,
+                // highest zoom level whose tiles this layer appears in.
+                // must  be less than or equal to the tileset's maxzoom
+                "maxzoom": maxzoom,
+                "fields": {}
+            });
+            //insert fields
+            let fields = self.ds(&layer).unwrap().detect_field_types(&layer, query);
+            for (field, field_type) in fields {
+                layer_json["fields"]
+                    .as_object_mut()
+                    .unwrap()
+                    .insert(field, json!(field_type));
+            }
+            if layer.merge_into.is_some() {
+                layer_json["fields"]
+                    .as_object_mut()
+                    .unwrap()
+                    .insert(layer.merge_attribute.clone(), json!("String"));
+            }
+            // Layers of split_by values
+            let split_names = self.split_layer_names(layer);
+            if let Some(ref split_by) = layer.split_by {
+                if split_names.is_empty() {
+                    layer_json["description"] = json!(format!(
+                        "Features are split into layers {}_<{}> at runtime",
+                        layer.mvt_name(),
+                        split_by
+                    ));
                 }
-                layer_json
-            })
-            .collect();
+            }
+            for name in split_names {
+                if !vector_layers.iter().any(|l| l["id"] == json!(name)) {
+                    let mut split_json = layer_json.clone();
+                    split_json["id"] = json!(name);
+                    vector_layers.push(split_json);
+                }
+            }
+            // Layers merged into the same MVT layer share one entry
+            if let Some(merged) = vector_layers
+                .iter_mut()
+                .find(|l| l["id"] == layer_json["id"])
+            {
+                let fields = layer_json["fields"].as_object().unwrap().clone();
+                merged["fields"].as_object_mut().unwrap().extend(fields);
+                let merged_minzoom = merged["minzoom"].as_u64().unwrap_or(0);
+                merged["minzoom"] = json!(cmp::min(merged_minzoom, minzoom as u64));
+                let merged_maxzoom = merged["maxzoom"].as_u64().unwrap_or(22);
+                merged["maxzoom"] = json!(cmp::max(merged_maxzoom, maxzoom as u64));
+                continue;
+            }
+            vector_layers.push(layer_json);
+        }
         Ok(json!(vector_layers))
     }
     /// TileJSON metadata (https://github.com/mapbox/tilejson-spec)
@@ -172,12 +229,24 @@ impl MvtService {
         let vector_layers = self.get_tilejson_vector_layers(tileset)?;
         let url = json!([format!("{}/{}/{{z}}/{{x}}/{{y}}.pbf", baseurl, tileset)]);
         let obj = metadata.as_object_mut().unwrap();
+        obj.insert("tilejson".to_string(), json!("3.0.0"));
         obj.insert("tiles".to_string(), url);
         obj.insert("vector_layers".to_string(), vector_layers);
+        let ts = self.get_tileset(tileset);
+        if ts.map_or(false, |ts| ts.utfgrid_key.is_some()) {
+            let grids = format!("{}/{}/{{z}}/{{x}}/{{y}}.grid.json", baseurl, tileset);
+            obj.insert("grids".to_string(), json!([grids]));
+        }
         Ok(json!(obj))
     }
     /// MapboxGL Style JSON (https://www.mapbox.com/mapbox-gl-style-spec/)
     pub fn get_stylejson(&self, baseurl: &str, tileset: &str) -> JsonResult {
+        let ts = self
+            .get_tileset(tileset)
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
+        if let Some(ref style) = ts.style_json {
+            return Ok(complete_user_style(baseurl, tileset, style));
+        }
         let mut stylejson = json!({
             "version": 8,
             "name": "t-rex",
@@ -204,7 +273,7 @@ impl MvtService {
         let layers = self.get_tileset_layers(tileset);
         let mut layer_styles: Vec<serde_json::Value> = layers
             .iter()
-            .map(|layer| {
+            .flat_map(|layer| {
                 let mut layerjson = if let Some(ref style) = layer.style {
                     serde_json::from_str(&style).unwrap()
                 } else {
@@ -221,34 +290,86 @@ impl MvtService {
                 layerjson
                     .as_object_mut()
                     .unwrap()
-                    .insert("source-layer".to_string(), json!(layer.name));
+                    .insert("source-layer".to_string(), json!(layer.mvt_name()));
                 // Note: source-layer referencing other layers not supported
+                if layer.merge_into.is_some() {
+                    // Select features of this layer in merged MVT layer
+                    layerjson
+                        .as_object_mut()
+                        .unwrap()
+                        .entry("filter".to_string())
+                        .or_insert(json!(["==", layer.merge_attribute, layer.name]));
+                }
 
-                // minzoom:
-                // The minimum zoom level for the layer. At zoom levels less than the minzoom, the layer will be hidden.
-                // Optional number between 0 and 24 inclusive.
-                // maxzoom:
-                // The maximum zoom level for the layer. At zoom levels equal to or greater than the maxzoom, the layer will be hidden.
-                // Optional number between 0 and 24 inclusive.
-                // Note: We could use source data min-/maxzoom as default to prevent overzooming
-                // or we could add style.minzoom, style.maxzoom elements
-
-                // Default paint type
-                let default_type = if let Some(ref geomtype) = layer.geometry_type {
-                    match &geomtype as &str {
-                        "POINT" => "circle",
-                        _ => "line",
-                    }
-                } else {
-                    "line"
-                }.to_string();
+                // Zoom range from layer configuration.
+                // GL maxzoom is exclusive: the layer is hidden at zoom levels >= maxzoom
+                let minzoom = layer.minzoom();
+                if minzoom > 0 {
+                    layerjson
+                        .as_object_mut()
+                        .unwrap()
+                        .entry("minzoom".to_string())
+                        .or_insert(json!(minzoom));
+                }
+                let maxzoom = layer.maxzoom(22);
+                if maxzoom < 22 {
+                    layerjson
+                        .as_object_mut()
+                        .unwrap()
+                        .entry("maxzoom".to_string())
+                        .or_insert(json!(maxzoom + 1));
+                }
+
+                // Default type and paint properties by geometry type
+                let geometry_type = layer.geometry_type.as_ref().map(|t| t.as_str());
+                let (default_type, default_paint) = match geometry_type {
+                    Some("POINT") | Some("MULTIPOINT") => (
+                        "circle",
+                        json!({
+                            "circle-color": "rgba(230, 90, 60, 1)",
+                            "circle-radius": 3
+                        }),
+                    ),
+                    Some("POLYGON") | Some("MULTIPOLYGON") => (
+                        "fill",
+                        json!({
+                            "fill-color": "rgba(180, 200, 220, 0.5)",
+                            "fill-outline-color": "rgba(90, 110, 130, 1)"
+                        }),
+                    ),
+                    _ => (
+                        "line",
+                        json!({
+                            "line-color": "rgba(90, 110, 130, 1)",
+                            "line-width": 1
+                        }),
+                    ),
+                };
+                if layer.style.is_none() {
+                    layerjson
+                        .as_object_mut()
+                        .unwrap()
+                        .insert("paint".to_string(), default_paint);
+                }
                 layerjson
                     .as_object_mut()
                     .unwrap()
                     .entry("type".to_string())
                     .or_insert(json!(default_type));
 
-                layerjson
+                // Same style for layers of split_by values
+                let mut layerjsons: Vec<serde_json::Value> = self
+                    .split_layer_names(layer)
+                    .into_iter()
+                    .map(|name| {
+                        let mut split_json = layerjson.clone();
+                        split_json["id"] = json!(name);
+                        split_json["source-layer"] = json!(name);
+                        split_json
+                    })
+                    .collect();
+                layerjsons.insert(0, layerjson);
+                layerjsons
             })
             .collect();
         layer_styles.insert(0, background_layer);
@@ -258,11 +379,24 @@ impl MvtService {
         Ok(json!(obj))
     }
 
+    /// Names of the MVT layers of a `split_by` layer, if the attribute values are known
+    fn split_layer_names(&self, layer: &Layer) -> Vec<String> {
+        if layer.split_by.is_none() {
+            return Vec::new();
+        }
+        self.ds(layer)
+            .map_or(Vec::new(), |ds| ds.split_values(layer))
+            .iter()
+            .map(|value| layer.split_layer_name(value))
+            .collect()
+    }
     /// MBTiles metadata.json (https://github.com/mapbox/mbtiles-spec/blob/master/1.3/spec.md)
     pub fn get_mbtiles_metadata(&self, tileset: &str) -> JsonResult {
         let mut metadata = self.get_tilejson_metadata(tileset)?;
-        metadata["bounds"] = json!(metadata["bounds"].to_string());
-        metadata["center"] = json!(metadata["center"].to_string());
+        // bounds and center are comma-separated lists of numbers
+        metadata["bounds"] = json!(join_numbers(&metadata["bounds"]));
+        metadata["center"] = json!(join_numbers(&metadata["center"]));
+        metadata["type"] = json!("overlay");
         let layers = self.get_tilejson_layers(tileset)?;
         let vector_layers = self.get_tilejson_vector_layers(tileset)?;
         let metadata_vector_layers = json!({
@@ -276,6 +410,248 @@ impl MvtService {
         );
         Ok(json!(obj))
     }
+    /// PMTiles JSON metadata (https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md)
+    pub fn get_pmtiles_metadata(&self, tileset: &str) -> JsonResult {
+        let mut metadata = self.get_tilejson_metadata(tileset)?;
+        metadata["type"] = json!("overlay");
+        metadata["vector_layers"] = self.get_tilejson_vector_layers(tileset)?;
+        Ok(metadata)
+    }
+    /// Rows of MBTiles metadata table
+    pub fn get_mbtiles_metadata_table(
+        &self,
+        tileset: &str,
+    ) -> Result<Vec<(String, String)>, Error> {
+        let metadata = self.get_mbtiles_metadata(tileset)?;
+        let keys = [
+            "name",
+            "description",
+            "version",
+            "attribution",
+            "type",
+            "format",
+            "bounds",
+            "center",
+            "minzoom",
+            "maxzoom",
+            "json",
+        ];
+        let rows = keys
+            .iter()
+            .filter_map(|key| {
+                let value = match metadata.get(*key) {
+                    Some(&serde_json::Value::String(ref s)) => s.clone(),
+                    Some(&serde_json::Value::Null) | None => return None,
+                    Some(v) => v.to_string(),
+                };
+                Some((key.to_string(), value))
+            })
+            .collect();
+        Ok(rows)
+    }
+    /// QGIS layer definition file (QLR) for vector tile layer (QGIS >= 3.14)
+    pub fn get_qgis_layer_definition(&self, baseurl: &str, tileset: &str) -> Option<String> {
+        let ts = self.get_tileset(tileset)?;
+        let tileurl = format!("{}/{}/%7Bz%7D/%7Bx%7D/%7By%7D.pbf", baseurl, tileset);
+        let styleurl = format!("{}/{}.style.json", baseurl, tileset);
+        let datasource = format!(
+            "styleUrl={}&type=xyz&url={}&zmax={}&zmin={}",
+            styleurl,
+            tileurl,
+            ts.maxzoom(),
+            ts.minzoom()
+        );
+        let layer_id = format!("t_rex_{}", tileset);
+        let qlr = format!(
+            r#"<!DOCTYPE qgis-layer-definition>
+<qlr>
+  <layer-tree-group checked="Qt::Checked" expanded="1" name="">
+    <customproperties/>
+    <layer-tree-layer checked="Qt::Checked" expanded="1" id="{id}" name="{name}" providerKey="vectortile" source="{source}">
+      <customproperties/>
+    </layer-tree-layer>
+  </layer-tree-group>
+  <maplayers>
+    <maplayer type="vector-tile" hasScaleBasedVisibilityFlag="0">
+      <id>{id}</id>
+      <datasource>{source}</datasource>
+      <layername>{name}</layername>
+      <srs>
+        <spatialrefsys>
+          <authid>EPSG:{srid}</authid>
+        </spatialrefsys>
+      </srs>
+      <provider>vectortile</provider>
+    </maplayer>
+  </maplayers>
+</qlr>
+"#,
+            id = xml_escape(&layer_id),
+            name = xml_escape(tileset),
+            source = xml_escape(&datasource),
+            srid = self.grid.srid
+        );
+        Some(qlr)
+    }
+    /// WMTS capabilities document with RESTful tile URLs (OGC 07-057r7)
+    pub fn get_wmts_capabilities(&self, baseurl: &str) -> String {
+        // One TileMatrixSet per logical tile size
+        let mut grids: Vec<Grid> = vec![self.grid.clone()];
+        for ts in &self.tilesets {
+            if let Some(size) = ts.pixel_size {
+                if !grids.iter().any(|g| g.tile_size() == size) {
+                    grids.push(self.grid.with_tile_size(size));
+                }
+            }
+        }
+        let mut layers = String::new();
+        for ts in &self.tilesets {
+            let size = ts.pixel_size.unwrap_or(self.grid.tile_size());
+            let grid = grids.iter().find(|g| g.tile_size() == size).unwrap();
+            let ext = ts.get_extent();
+            layers.push_str(&format!(
+                r#"    <Layer>
+      <ows:Title>{name}</ows:Title>
+      <ows:WGS84BoundingBox>
+        <ows:LowerCorner>{minx} {miny}</ows:LowerCorner>
+        <ows:UpperCorner>{maxx} {maxy}</ows:UpperCorner>
+      </ows:WGS84BoundingBox>
+      <ows:Identifier>{name}</ows:Identifier>
+      <Style isDefault="true">
+        <ows:Identifier>default</ows:Identifier>
+      </Style>
+      <Format>application/vnd.mapbox-vector-tile</Format>
+      <TileMatrixSetLink>
+        <TileMatrixSet>{matrix_set}</TileMatrixSet>
+      </TileMatrixSetLink>
+      <ResourceURL format="application/vnd.mapbox-vector-tile" resourceType="tile" template="{url}"/>
+    </Layer>
+"#,
+                name = xml_escape(&ts.name),
+                minx = ext.minx,
+                miny = ext.miny,
+                maxx = ext.maxx,
+                maxy = ext.maxy,
+                matrix_set = tile_matrix_set_id(grid),
+                url = xml_escape(&format!(
+                    "{}/wmts/{}/{{TileMatrix}}/{{TileRow}}/{{TileCol}}.pbf",
+                    baseurl, ts.name
+                )),
+            ));
+        }
+
+        let mut matrix_sets = String::new();
+        for grid in &grids {
+            matrix_sets.push_str(&format!(
+                r#"    <TileMatrixSet>
+      <ows:Identifier>{}</ows:Identifier>
+      <ows:SupportedCRS>urn:ogc:def:crs:EPSG::{}</ows:SupportedCRS>
+"#,
+                tile_matrix_set_id(grid),
+                grid.srid
+            ));
+            for zoom in 0..grid.nlevels() {
+                let top_left = grid.tile_extent(0, grid.ytile_from_wmts(0, zoom), zoom);
+                // EPSG:4326 has latitude/longitude axis order
+                let corner = if grid.srid == 4326 {
+                    format!("{} {}", top_left.maxy, top_left.minx)
+                } else {
+                    format!("{} {}", top_left.minx, top_left.maxy)
+                };
+                let (width, height) = grid.matrix_size(zoom);
+                matrix_sets.push_str(&format!(
+                    r#"      <TileMatrix>
+        <ows:Identifier>{zoom}</ows:Identifier>
+        <ScaleDenominator>{scale}</ScaleDenominator>
+        <TopLeftCorner>{corner}</TopLeftCorner>
+        <TileWidth>{size}</TileWidth>
+        <TileHeight>{size}</TileHeight>
+        <MatrixWidth>{width}</MatrixWidth>
+        <MatrixHeight>{height}</MatrixHeight>
+      </TileMatrix>
+"#,
+                    zoom = zoom,
+                    scale = grid.scale_denominator(zoom),
+                    corner = corner,
+                    size = grid.tile_size(),
+                    width = width,
+                    height = height
+                ));
+            }
+            matrix_sets.push_str("    </TileMatrixSet>\n");
+        }
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Capabilities xmlns="http://www.opengis.net/wmts/1.0" xmlns:ows="http://www.opengis.net/ows/1.1" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.0.0">
+  <ows:ServiceIdentification>
+    <ows:Title>t-rex</ows:Title>
+    <ows:ServiceType>OGC WMTS</ows:ServiceType>
+    <ows:ServiceTypeVersion>1.0.0</ows:ServiceTypeVersion>
+  </ows:ServiceIdentification>
+  <Contents>
+{layers}{matrix_sets}  </Contents>
+  <ServiceMetadataURL xlink:href="{url}"/>
+</Capabilities>
+"#,
+            layers = layers,
+            matrix_sets = matrix_sets,
+            url = xml_escape(&format!("{}/wmts/1.0.0/WMTSCapabilities.xml", baseurl))
+        )
+    }
+}
+
+/// User provided style with defaults for glyphs and tileset source URL
+fn complete_user_style(
+    baseurl: &str,
+    tileset: &str,
+    style: &serde_json::Value,
+) -> serde_json::Value {
+    let mut stylejson = style.clone();
+    if let Some(obj) = stylejson.as_object_mut() {
+        obj.entry("glyphs".to_string()).or_insert(json!(format!(
+            "{}/fonts/{{fontstack}}/{{range}}.pbf",
+            baseurl
+        )));
+        let sources = obj.entry("sources".to_string()).or_insert(json!({}));
+        if let Some(sources) = sources.as_object_mut() {
+            let source = sources
+                .entry(tileset.to_string())
+                .or_insert(json!({"type": "vector"}));
+            if source.get("url").is_none() && source.get("tiles").is_none() {
+                source["url"] = json!(format!("{}/{}.json", baseurl, tileset));
+            }
+        }
+    }
+    stylejson
+}
+
+/// Identifier of the tile matrix set of a grid, e.g. `WebMercatorQuad`
+pub(crate) fn tile_matrix_set_id(grid: &Grid) -> String {
+    match (grid.srid, grid.tile_size()) {
+        (3857, 256) if grid.extent == Grid::web_mercator().extent => "WebMercatorQuad".to_string(),
+        (4326, 256) if grid.extent == Grid::wgs84().extent => "WorldCRS84Quad".to_string(),
+        (srid, size) => format!("EPSG{}_{}", srid, size),
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn join_numbers(values: &serde_json::Value) -> String {
+    values
+        .as_array()
+        .map(|vals| {
+            vals.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -365,6 +741,7 @@ fn test_tilejson() {
   "minzoom": 0,
   "name": "osm",
   "scheme": "xyz",
+  "tilejson": "3.0.0",
   "tiles": [
     "http://127.0.0.1/osm/{z}/{x}/{y}.pbf"
   ],
@@ -439,8 +816,40 @@ fn test_stylejson() {
     assert!(json.contains(expected));
 
     let expected = r#"
-      "id": "buildings","#;
+      "id": "buildings",
+      "paint": {
+        "fill-color": "rgba(180, 200, 220, 0.5)",
+        "fill-outline-color": "rgba(90, 110, 130, 1)"
+      },
+      "source": "osm",
+      "source-layer": "buildings",
+      "type": "fill""#;
     assert!(json.contains(expected));
+
+    assert_eq!(
+        service.get_stylejson("http://127.0.0.1", "unknown"),
+        Err(Error::UnknownTileset("unknown".to_string()))
+    );
+}
+
+#[test]
+fn test_user_stylejson() {
+    let style = json!({"version": 8, "sources": {"osm": {"type": "vector"}}, "layers": []});
+    let stylejson = complete_user_style("http://127.0.0.1", "osm", &style);
+    assert_eq!(
+        stylejson["sources"]["osm"]["url"],
+        "http://127.0.0.1/osm.json"
+    );
+    assert_eq!(
+        stylejson["glyphs"],
+        "http://127.0.0.1/fonts/{fontstack}/{range}.pbf"
+    );
+    assert_eq!(stylejson["layers"], json!([]));
+
+    let tiles = json!(["http://example.com/{z}/{x}/{y}.pbf"]);
+    let style = json!({"sources": {"osm": {"type": "vector", "tiles": tiles}}});
+    let stylejson = complete_user_style("http://127.0.0.1", "osm", &style);
+    assert!(stylejson["sources"]["osm"].get("url").is_none());
 }
 
 #[test]
@@ -463,17 +872,60 @@ fn test_mbtiles_metadata() {
     let expected = r#"{
   "attribution": "",
   "basename": "osm",
-  "bounds": "[-180.0,-90.0,180.0,90.0]",
-  "center": "[0.0,0.0,2]",
+  "bounds": "-180.0,-90.0,180.0,90.0",
+  "center": "0.0,0.0,2",
   "description": "osm",
   "format": "pbf",
   "id": "osm",
-  "json": "{\"Layer\":[{\"description\":\"\",\"fields\":{},\"id\":\"points\",\"name\":\"points\",\"properties\":{\"buffer-size\":0,\"maxzoom\":22,\"minzoom\":0},\"srs\":\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0.0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over\"},{\"description\":\"\",\"fields\":{},\"id\":\"buildings\",\"name\":\"buildings\",\"properties\":{\"buffer-size\":10,\"maxzoom\":22,\"minzoom\":0},\"srs\":\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0.0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over\"},{\"description\":\"\",\"fields\":{},\"id\":\"admin_0_countries\",\"name\":\"admin_0_countries\",\"properties\":{\"buffer-size\":1,\"maxzoom\":22,\"minzoom\":0},\"srs\":\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0.0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over\"}],\"vector_layers\":[{\"description\":\"\",\"fields\":{},\"id\":\"points\",\"maxzoom\":22,\"minzoom\":0},{\"description\":\"\",\"fields\":{},\"id\":\"buildings\",\"maxzoom\":22,\"minzoom\":0},{\"description\":\"\",\"fields\":{},\"id\":\"admin_0_countries\",\"maxzoom\":22,\"minzoom\":0}]}",
+  "json": "{\"Layer\":[{\"description\":\"\",\"fields\":{},\"id\":\"points\",\"name\":\"points\",\"properties\":{\"buffer-size\":10,\"maxzoom\":22,\"minzoom\":0},\"srs\":\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0.0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over\"},{\"description\":\"\",\"fields\":{},\"id\":\"buildings\",\"name\":\"buildings\",\"properties\":{\"buffer-size\":10,\"maxzoom\":22,\"minzoom\":0},\"srs\":\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0.0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over\"},{\"description\":\"\",\"fields\":{},\"id\":\"admin_0_countries\",\"name\":\"admin_0_countries\",\"properties\":{\"buffer-size\":1,\"maxzoom\":22,\"minzoom\":0},\"srs\":\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0.0 +lon_0=0.0 +x_0=0.0 +y_0=0.0 +k=1.0 +units=m +nadgrids=@null +wktext +no_defs +over\"}],\"vector_layers\":[{\"description\":\"\",\"fields\":{},\"id\":\"points\",\"maxzoom\":22,\"minzoom\":0},{\"description\":\"\",\"fields\":{},\"id\":\"buildings\",\"maxzoom\":22,\"minzoom\":0},{\"description\":\"\",\"fields\":{},\"id\":\"admin_0_countries\",\"maxzoom\":22,\"minzoom\":0}]}",
   "maxzoom": 22,
   "minzoom": 0,
   "name": "osm",
   "scheme": "xyz",
+  "type": "overlay",
   "version": "2.0.0"
 }"#;
     assert_eq!(metadata, expected);
 }
+
+#[test]
+fn test_qgis_layer_definition() {
+    use core::read_config;
+
+    let config = read_config("src/test/example.toml").unwrap();
+    let service = MvtService::from_config(&config).unwrap();
+    let qlr = service
+        .get_qgis_layer_definition("http://127.0.0.1", "osm")
+        .unwrap();
+    println!("{}", qlr);
+    let expected = r#"<datasource>styleUrl=http://127.0.0.1/osm.style.json&amp;type=xyz&amp;url=http://127.0.0.1/osm/%7Bz%7D/%7Bx%7D/%7By%7D.pbf&amp;zmax=22&amp;zmin=0</datasource>"#;
+    assert!(qlr.contains(expected));
+    assert!(qlr.contains("<authid>EPSG:3857</authid>"));
+    assert!(service
+        .get_qgis_layer_definition("http://127.0.0.1", "unknown")
+        .is_none());
+}
+
+#[test]
+fn test_wmts_capabilities() {
+    use core::read_config;
+
+    let config = read_config("src/test/example.toml").unwrap();
+    let service = MvtService::from_config(&config).unwrap();
+    let caps = service.get_wmts_capabilities("http://127.0.0.1");
+    println!("{}", caps);
+    assert!(caps.contains("<ows:Identifier>osm</ows:Identifier>"));
+    assert!(caps.contains(r#"<ResourceURL format="application/vnd.mapbox-vector-tile" resourceType="tile" template="http://127.0.0.1/wmts/osm/{TileMatrix}/{TileRow}/{TileCol}.pbf"/>"#));
+    assert!(caps.contains("<TileMatrixSet>WebMercatorQuad</TileMatrixSet>"));
+    assert!(caps.contains("<ows:SupportedCRS>urn:ogc:def:crs:EPSG::3857</ows:SupportedCRS>"));
+    let matrix = r#"      <TileMatrix>
+        <ows:Identifier>0</ows:Identifier>
+        <ScaleDenominator>559082264.0287179</ScaleDenominator>
+        <TopLeftCorner>-20037508.342789248 20037508.342789248</TopLeftCorner>
+        <TileWidth>256</TileWidth>
+        <TileHeight>256</TileHeight>
+        <MatrixWidth>1</MatrixWidth>
+        <MatrixHeight>1</MatrixHeight>
+      </TileMatrix>"#;
+    assert!(caps.contains(matrix));
+}
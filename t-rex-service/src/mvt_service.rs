@@ -3,24 +3,36 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use cache::{Cache, Tilecache};
-use core::grid::{extent_to_merc, Extent, ExtentInt, Grid};
-use core::layer::Layer;
+#[cfg(feature = "with-mbtiles")]
+use cache::Mbtiles;
+use cache::{Cache, MemoryStore, Pmtiles, Tilecache};
+use core::feature::ExtendedFeature;
+use core::grid::{extent_to_merc, merc_to_lonlat, Extent, ExtentInt, Grid};
+use core::layer::{DedupKey, Layer, ValidityPolicy};
+use core::metrics;
 use core::stats::Statistics;
 use core::ApplicationCfg;
-use core::Config;
-use datasource::DatasourceInput;
+use core::{read_config, Config, Error};
+use datasource::{DatasourceInput, PostgisInput};
 use datasource_type::Datasource;
 use datasource_type::Datasources;
-use mvt::tile::Tile;
+use mvt::geojson::tile_to_geojson;
+use mvt::postprocess::{CommandPostprocessor, TilePostprocessor};
+use mvt::tile::{Tile, TileCompression, WindingOrder};
+use mvt::utfgrid::tile_to_utfgrid;
 use mvt::vector_tile;
 use pbr::ProgressBar;
 use percent_encoding::percent_decode;
+use seed_state::SeedState;
 use serde_json;
 use service::tileset::{Tileset, WORLD_EXTENT};
 use std::cmp;
-use std::io::{stderr, Stderr, Stdout};
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, stderr, Stderr, Stdout};
+use std::time::{Duration, Instant};
+
+/// Maximal number of tiles purged one by one in a zoom level
+const MAX_PURGE_TILES: u64 = 10000;
 
 /// Mapbox Vector Tile Service
 pub struct MvtService {
@@ -28,29 +40,158 @@ pub struct MvtService {
     pub grid: Grid,
     pub tilesets: Vec<Tileset>,
     pub cache: Tilecache,
+    /// Polygon ring orientation of generated tiles
+    pub winding_order: WindingOrder,
+    /// Services of tilesets published on additional grids
+    pub grid_services: Vec<(String, MvtService)>,
+    /// Name of additional grid, included in cache paths (`tileset/grid/z/x/y.pbf`)
+    pub grid_name: Option<String>,
+    /// Transformation of rendered tiles before caching and serving
+    pub postprocessor: Option<Box<TilePostprocessor>>,
+    /// Rendering duration in milliseconds above which a warning with timings is logged
+    pub slow_tile_ms: Option<u64>,
+    /// Compression of cached tiles
+    pub compression: TileCompression,
+}
+
+/// Adds the time until dropped to `total`
+struct DurationGuard<'a> {
+    start: Instant,
+    total: &'a mut Duration,
+}
+
+impl<'a> Drop for DurationGuard<'a> {
+    fn drop(&mut self) {
+        *self.total += self.start.elapsed();
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + duration.subsec_millis() as u64
+}
+
+/// Time spent in datasource queries, feature processing and encoding of a tile
+#[derive(Default)]
+pub(crate) struct TileTimings {
+    /// Layer name, query and processing duration
+    pub layers: Vec<(String, Duration, Duration)>,
+    pub encoding: Duration,
+}
+
+impl TileTimings {
+    pub fn total(&self) -> Duration {
+        self.layers
+            .iter()
+            .fold(self.encoding, |total, &(_, query, processing)| {
+                total + query + processing
+            })
+    }
+    /// Timings in milliseconds, e.g. `query 120 ms, processing 30 ms, encoding 5 ms (points: 100/20 ms)`
+    pub fn summary(&self) -> String {
+        let (query, processing) = self.layers.iter().fold(
+            (Duration::default(), Duration::default()),
+            |(q, p), &(_, query, processing)| (q + query, p + processing),
+        );
+        let layers: Vec<String> = self
+            .layers
+            .iter()
+            .map(|&(ref name, query, processing)| {
+                format!("{}: {}/{} ms", name, millis(query), millis(processing))
+            })
+            .collect();
+        format!(
+            "query {} ms, processing {} ms, encoding {} ms ({})",
+            millis(query),
+            millis(processing),
+            millis(self.encoding),
+            layers.join(", ")
+        )
+    }
 }
 
 impl MvtService {
+    /// Create a ready-to-use service from a TOML configuration file
+    ///
+    /// Connects the datasources, prepares the feature queries and
+    /// initializes the cache, as done by `t_rex serve`.
+    pub fn from_config_file(path: &str) -> Result<MvtService, String> {
+        let config: ApplicationCfg = read_config(path)?;
+        let mut service = MvtService::from_config(&config)?;
+        service.connect();
+        service.prepare_feature_queries();
+        service.init_cache();
+        Ok(service)
+    }
     /// Connect all datasources
     // Needed before calling methods on PostGIS datasources like prepare_feature_queries or get_mbtiles_metadata
     // TODO: connect automatically when needed
     pub fn connect(&mut self) {
-        let mut datasources = Datasources::new();
-        datasources.default = self.datasources.default.clone();
-        for (name, ds) in &self.datasources.datasources {
-            datasources.add(&name, ds.connected());
+        self.connect_shared(&HashMap::new());
+    }
+    /// Connect all datasources, sharing the connection pools of PostGIS datasources with the same name
+    pub fn connect_shared(&mut self, pools: &HashMap<String, PostgisInput>) {
+        self.datasources = self.datasources.connected(pools);
+        for &mut (_, ref mut service) in &mut self.grid_services {
+            service.connect_shared(pools);
+        }
+    }
+    /// Use `store` for the memory cache, e.g. to share it between webserver workers
+    pub fn share_memory_store(&mut self, store: &MemoryStore) {
+        self.cache.share_memory_store(store);
+        for &mut (_, ref mut service) in &mut self.grid_services {
+            service.share_memory_store(store);
+        }
+    }
+    /// Service for tilesets on an additional grid
+    pub fn grid_service(&self, grid: &str) -> Option<&MvtService> {
+        self.grid_services
+            .iter()
+            .find(|&&(ref name, _)| name == grid)
+            .map(|&(_, ref service)| service)
+    }
+    /// Connection check of all datasources, sorted by name
+    pub fn check_datasources(&self) -> Vec<(String, Result<(), Error>)> {
+        let mut checks: Vec<_> = self
+            .datasources
+            .datasources
+            .iter()
+            .map(|(name, ds)| (name.clone(), ds.check_connection()))
+            .collect();
+        checks.sort_by(|a, b| a.0.cmp(&b.0));
+        checks
+    }
+    /// Cache path of tileset, including the name of an additional grid
+    fn tileset_cache_path(&self, tileset: &str) -> String {
+        match self.grid_name {
+            Some(ref grid) => format!("{}/{}", tileset, grid),
+            None => tileset.to_string(),
         }
-        datasources.setup();
-        self.datasources = datasources;
+    }
+    /// Cache path of tile at x, y, z in tile scheme of grid
+    pub fn tile_cache_path(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> String {
+        format!(
+            "{}/{}/{}/{}.pbf",
+            self.tileset_cache_path(tileset),
+            zoom,
+            xtile,
+            ytile
+        )
     }
     pub(crate) fn ds(&self, layer: &Layer) -> Option<&Datasource> {
         self.datasources.datasource(&layer.datasource)
     }
-    pub(crate) fn get_tileset(&self, name: &str) -> Option<&Tileset> {
+    pub fn get_tileset(&self, name: &str) -> Option<&Tileset> {
         // URL decode tileset names from http requests
         let dec_name = percent_decode(name.as_bytes()).decode_utf8().unwrap();
         self.tilesets.iter().find(|t| t.name == dec_name)
     }
+    /// Grid with the logical tile size of the tileset, if it differs from the service grid
+    fn tileset_grid(&self, tileset: &str) -> Option<Grid> {
+        match self.get_tileset(tileset).and_then(|ts| ts.pixel_size) {
+            Some(size) if size != self.grid.tile_size() => Some(self.grid.with_tile_size(size)),
+            _ => None,
+        }
+    }
     /// Get layers (as reference) of given tileset
     pub(crate) fn get_tileset_layers(&self, name: &str) -> Vec<&Layer> {
         match self.get_tileset(name) {
@@ -69,56 +210,255 @@ impl MvtService {
                 ds.prepare_queries(&layer, self.grid.srid);
             }
         }
+        for &mut (_, ref mut service) in &mut self.grid_services {
+            service.prepare_feature_queries();
+        }
     }
     /// Create vector tile from input at x, y, z in TMS adressing scheme
     pub fn tile(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        stats: Option<&mut Statistics>,
+    ) -> Result<vector_tile::Tile, Error> {
+        self.tile_timed(
+            tileset,
+            xtile,
+            ytile,
+            zoom,
+            stats,
+            &mut TileTimings::default(),
+        )
+    }
+    /// Create vector tile and collect query and processing times of each layer
+    fn tile_timed(
         &self,
         tileset: &str,
         xtile: u32,
         ytile: u32,
         zoom: u8,
         mut stats: Option<&mut Statistics>,
-    ) -> vector_tile::Tile {
+        timings: &mut TileTimings,
+    ) -> Result<vector_tile::Tile, Error> {
+        #[cfg(feature = "with-tracing")]
+        let _span = tracing::info_span!("tile", tileset, zoom, xtile, ytile).entered();
         let extent = self.grid.tile_extent(xtile, ytile, zoom);
+        // Tile extents are the same, but pixel widths depend on the tile size
+        let tileset_grid = self.tileset_grid(tileset);
+        let grid = tileset_grid.as_ref().unwrap_or(&self.grid);
         debug!(
             "{}/{}/{}/{} retrieving with {:?}",
             tileset, zoom, xtile, ytile, extent
         );
         let mut tile = Tile::new(&extent, true);
+        tile.winding_order = self.winding_order;
+        // A layer may write into several MVT layers (`split_by`) and several
+        // layers may write into the same MVT layer (`merge_into`)
+        let mut mvt_layers: Vec<vector_tile::Tile_Layer> = Vec::new();
         for layer in self.get_tileset_layers(tileset) {
             if zoom >= layer.minzoom() && zoom <= layer.maxzoom(30) {
-                let mut mvt_layer = tile.new_layer(layer);
+                #[cfg(feature = "with-tracing")]
+                let _span = tracing::info_span!("layer", layer = &*layer.name).entered();
+                let query_zoom = layer.query_zoom(zoom, self.grid.maxzoom());
+                let fields = layer.attribute_fields(zoom);
+                let mut invalid_count = 0;
+                let mut fids = HashSet::new();
+                let mut geometries = HashSet::new();
+                let ds = self.ds(&layer).ok_or_else(|| {
+                    Error::Datasource(format!("Layer '{}': datasource not found", layer.name))
+                })?;
                 let now = Instant::now();
-                let num_features = self.ds(&layer).unwrap().retrieve_features(
+                let mut processing = Duration::default();
+                let num_features = ds.retrieve_features(
                     &layer,
                     &extent,
-                    zoom,
-                    &self.grid,
+                    query_zoom,
+                    grid,
                     |feat| {
-                        tile.add_feature(&mut mvt_layer, feat);
+                        let _processing = DurationGuard {
+                            start: Instant::now(),
+                            total: &mut processing,
+                        };
+                        let mut geometry = None;
+                        if let Some(policy) = layer.validity {
+                            if let Ok(mut geom) = feat.geometry() {
+                                if let Err(e) = geom.validate() {
+                                    invalid_count += 1;
+                                    match policy {
+                                        ValidityPolicy::Skip => {
+                                            debug!("Layer '{}': Skipping feature: {}", layer.name, e);
+                                            return;
+                                        }
+                                        ValidityPolicy::Warn => {
+                                            warn!("Layer '{}': Invalid geometry: {}", layer.name, e);
+                                        }
+                                        ValidityPolicy::Repair => {
+                                            geom.repair();
+                                            if geom.validate().is_err() || geom.is_empty() {
+                                                debug!("Layer '{}': Skipping feature: {}", layer.name, e);
+                                                return;
+                                            }
+                                        }
+                                    }
+                                }
+                                geometry = Some(geom);
+                            }
+                        }
+                        // Geometry encoded for deduplication, reused for the tile feature
+                        let mut encoded = None;
+                        match layer.dedup {
+                            Some(DedupKey::Fid) => {
+                                if let Some(fid) = feat.fid() {
+                                    if !fids.insert(fid) {
+                                        return;
+                                    }
+                                }
+                            }
+                            Some(DedupKey::Geometry) => {
+                                let geom = match geometry {
+                                    Some(ref geom) => Ok(geom.clone()),
+                                    None => feat.geometry(),
+                                };
+                                if let Ok(geom) = geom {
+                                    let field_type = geom.mvt_field_type();
+                                    let is_empty = geom.is_empty();
+                                    let commands = tile.encode_geom(geom, layer.tile_size).vec();
+                                    if !geometries.insert(commands.clone()) {
+                                        return;
+                                    }
+                                    if !is_empty {
+                                        encoded = Some((field_type, commands));
+                                    }
+                                }
+                            }
+                            None => {}
+                        }
+                        let name = layer.mvt_layer_name(feat);
+                        let idx = match mvt_layers.iter().position(|l| l.get_name() == name) {
+                            Some(idx) => idx,
+                            None => {
+                                let mut mvt_layer = tile.new_layer(layer);
+                                mvt_layer.set_name(name);
+                                mvt_layers.push(mvt_layer);
+                                mvt_layers.len() - 1
+                            }
+                        };
+                        let feature = ExtendedFeature {
+                            feature: feat,
+                            attributes: layer.merge_discriminator().into_iter().collect(),
+                            fields: fields,
+                            geometry: geometry,
+                        };
+                        match encoded {
+                            Some(geometry) => {
+                                tile.add_encoded_feature(&mut mvt_layers[idx], &feature, Some(geometry))
+                            }
+                            None => tile.add_feature(&mut mvt_layers[idx], &feature),
+                        }
                     },
-                );
+                )?;
                 let elapsed = now.elapsed();
+                let query = elapsed.checked_sub(processing).unwrap_or_default();
+                timings.layers.push((layer.name.clone(), query, processing));
+                metrics::observe_duration(
+                    "trex_layer_query_duration_seconds",
+                    &[("tileset", tileset), ("layer", layer.name.as_str())],
+                    query,
+                );
                 if let Some(ref mut stats) = stats {
                     stats.add(
                         format!("tile_ms.{}.{}.{}", tileset, layer.name, zoom),
                         elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64,
                     );
+                    stats.add(
+                        format!("query_ms.{}.{}.{}", tileset, layer.name, zoom),
+                        millis(query),
+                    );
+                    stats.add(
+                        format!("processing_ms.{}.{}.{}", tileset, layer.name, zoom),
+                        millis(processing),
+                    );
                     stats.add(
                         format!("feature_count.{}.{}.{}", tileset, layer.name, zoom),
                         num_features as u64,
                     );
+                    if layer.validity.is_some() {
+                        stats.add(
+                            format!("invalid_geometries.{}.{}.{}", tileset, layer.name, zoom),
+                            invalid_count,
+                        );
+                    }
                 }
                 debug!(
-                    "{}/{}/{}/{} layer {}: {} features",
-                    tileset, zoom, xtile, ytile, layer.name, num_features
+                    "{}/{}/{}/{} layer {}: {} features (query {} ms, processing {} ms)",
+                    tileset,
+                    zoom,
+                    xtile,
+                    ytile,
+                    layer.name,
+                    num_features,
+                    millis(query),
+                    millis(processing)
                 );
-                if num_features > 0 {
-                    tile.add_layer(mvt_layer);
-                }
             }
         }
-        tile.mvt_tile
+        for mvt_layer in mvt_layers {
+            // Omit empty layers
+            if mvt_layer.get_features().len() > 0 {
+                tile.add_layer(mvt_layer);
+            }
+        }
+        match self.postprocessor {
+            Some(ref postprocessor) => postprocessor
+                .process(tileset, xtile, ytile, zoom, tile.mvt_tile)
+                .map_err(Error::Postprocess),
+            None => Ok(tile.mvt_tile),
+        }
+    }
+    /// Check whether the cached tile at `path` is older than the cache TTL of the tileset
+    fn cache_expired(&self, ts: &Tileset, path: &str) -> bool {
+        if ts.cache_ttl().is_none() {
+            return false;
+        }
+        self.cache
+            .metadata(path)
+            .and_then(|md| md.modified)
+            .map(|modified| ts.is_expired(modified))
+            .unwrap_or(false)
+    }
+    fn log_timings(&self, path: &str, timings: &TileTimings) {
+        let total = millis(timings.total());
+        match self.slow_tile_ms {
+            Some(threshold) if total >= threshold => {
+                warn!("Slow tile {}: {} ms - {}", path, total, timings.summary())
+            }
+            _ => debug!("Tile {}: {} ms - {}", path, total, timings.summary()),
+        }
+    }
+    /// Content hash of vector tile at x, y, z in tile scheme of grid.
+    /// With `canonical`, the hash doesn't depend on the order of layers, features and attributes.
+    pub fn tile_hash(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        canonical: bool,
+    ) -> Result<String, Error> {
+        if self.get_tileset(tileset).is_none() {
+            return Err(Error::UnknownTileset(tileset.to_string()));
+        }
+        if !self.grid.contains_tile(xtile, ytile, zoom) {
+            return Err(Error::InvalidTile(format!("{}/{}/{}", zoom, xtile, ytile)));
+        }
+        let y = self.grid.ytile_tms(ytile, zoom);
+        let mut mvt_tile = self.tile(tileset, xtile, y, zoom, None)?;
+        if canonical {
+            mvt_tile = Tile::canonical(&mvt_tile);
+        }
+        Ok(Tile::content_hash(&Tile::tile_bytevec(&mvt_tile)))
     }
     /// Fetch or create vector tile from input at x, y, z
     pub fn tile_cached(
@@ -129,44 +469,114 @@ impl MvtService {
         zoom: u8,
         gzip: bool,
         stats: Option<&mut Statistics>,
-    ) -> Option<Vec<u8>> {
-        // Reverse y for XYZ scheme (TODO: protocol instead of CRS dependent?)
-        let y = if self.grid.srid == 3857 {
-            self.grid.ytile_from_xyz(ytile, zoom)
-        } else {
-            ytile
-        };
-        let path = format!("{}/{}/{}/{}.pbf", tileset, zoom, xtile, ytile);
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let accepted: &[TileCompression] = if gzip { &[TileCompression::Gzip] } else { &[] };
+        self.tile_cached_compressed(tileset, xtile, ytile, zoom, accepted, stats)
+            .map(|tile| tile.map(|(data, _)| data))
+    }
+    /// Fetch or create vector tile from input at x, y, z in one of the `accepted` compressions
+    /// (uncompressed if none is accepted). Returns the tile content and its compression.
+    pub fn tile_cached_compressed(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        accepted: &[TileCompression],
+        mut stats: Option<&mut Statistics>,
+    ) -> Result<Option<(Vec<u8>, TileCompression)>, Error> {
+        #[cfg(feature = "with-tracing")]
+        let _span = tracing::info_span!("tile_cached", tileset, zoom, xtile, ytile).entered();
+        // Reverse y for XYZ scheme
+        let y = self.grid.ytile_tms(ytile, zoom);
+        let path = self.tile_cache_path(tileset, zoom, xtile, ytile);
 
         let ts = self
             .get_tileset(tileset)
-            .expect(&format!("Tileset '{}' not found", tileset));
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
+        if !self.grid.contains_tile(xtile, ytile, zoom) {
+            return Err(Error::InvalidTile(format!("{}/{}/{}", zoom, xtile, ytile)));
+        }
 
         if zoom < ts.minzoom() || zoom > ts.maxzoom() {
-            return None;
+            return Ok(None);
         }
 
         let mut tile: Option<Vec<u8>> = None;
         if ts.is_cachable_at(zoom) {
-            self.cache.read(&path, |f| {
-                let mut data = Vec::new();
-                let _ = f.read_to_end(&mut data);
-                tile = Some(data);
-            });
+            if self.cache_expired(ts, &path) {
+                debug!("Cache : tile {} expired", path);
+            } else {
+                self.cache.read(&path, &mut |f| {
+                    let mut data = Vec::new();
+                    let _ = f.read_to_end(&mut data);
+                    tile = Some(data);
+                });
+            }
+            let result = if tile.is_some() { "hit" } else { "miss" };
+            metrics::inc_counter(
+                "trex_tile_cache_requests_total",
+                &[("tileset", tileset), ("result", result)],
+            );
         } else {
             debug!("Cache : read ignored for tileset {} at zoom {}", ts.name, zoom);
         }
 
         // Return tile from cache
         if let Some(tilegz) = tile {
-            return Some(Tile::tile_content(tilegz, gzip));
+            metrics::observe(
+                "trex_tile_size_bytes",
+                &[("tileset", tileset)],
+                tilegz.len() as f64,
+            );
+            return Tile::tile_content(tilegz, self.compression, accepted)
+                .map(Some)
+                .map_err(|e| Error::Cache(format!("{}: {}", path, e)));
         }
 
         // Request tile and write into cache
-        let mvt_tile = self.tile(tileset, xtile, y, zoom, stats);
+        let render_start = Instant::now();
+        let mut timings = TileTimings::default();
+        let mvt_tile = self.tile_timed(
+            tileset,
+            xtile,
+            y,
+            zoom,
+            stats.as_mut().map(|s| &mut **s),
+            &mut timings,
+        )?;
         // Spec: A Vector Tile SHOULD contain at least one layer.
         if mvt_tile.get_layers().len() > 0 {
-            let tilegz = Tile::tile_bytevec_gz(&mvt_tile);
+            let now = Instant::now();
+            let tilegz = {
+                #[cfg(feature = "with-tracing")]
+                let _span = tracing::info_span!("encode").entered();
+                Tile::tile_bytevec_compressed(&mvt_tile, self.compression)
+                    .map_err(|e| Error::Cache(format!("{}: {}", path, e)))?
+            };
+            timings.encoding = now.elapsed();
+            self.log_timings(&path, &timings);
+            metrics::observe_duration(
+                "trex_tile_render_duration_seconds",
+                &[("tileset", tileset)],
+                render_start.elapsed(),
+            );
+            metrics::observe(
+                "trex_tile_size_bytes",
+                &[("tileset", tileset)],
+                tilegz.len() as f64,
+            );
+            if let Some(ref mut stats) = stats {
+                let elapsed = now.elapsed();
+                stats.add(
+                    format!("encode_ms.{}.total.{}", tileset, zoom),
+                    elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64,
+                );
+                stats.add(
+                    format!("tile_bytes.{}.total.{}", tileset, zoom),
+                    tilegz.len() as u64,
+                );
+            }
             if ts.is_cachable_at(zoom) {
                 if let Err(ioerr) = self.cache.write(&path, &tilegz) {
                     error!("Error writing {}: {}", path, ioerr);
@@ -174,24 +584,86 @@ impl MvtService {
             } else {
                 debug!("Cache : write ignored for tileset {} at zoom {}", ts.name, zoom);
             }
-            Some(Tile::tile_content(tilegz, gzip))
+            Tile::tile_content(tilegz, self.compression, accepted)
+                .map(Some)
+                .map_err(|e| Error::Cache(format!("{}: {}", path, e)))
         } else {
             // We don't save empty tiles
             // When serving from file cache return 204 No Content
             // Nginx: try_files $uri = 204;
             debug!("{} - Skipping empty tile", path);
-            None
+            self.log_timings(&path, &timings);
+            Ok(None)
+        }
+    }
+    /// Features of vector tile at x, y, z in tile scheme of grid as GeoJSON.
+    ///
+    /// Coordinates are in WGS84 for Web Mercator and WGS84 grids, in grid SRS otherwise.
+    pub fn tile_geojson(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+    ) -> Result<Option<serde_json::Value>, Error> {
+        let ts = self
+            .get_tileset(tileset)
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
+        if !self.grid.contains_tile(xtile, ytile, zoom) {
+            return Err(Error::InvalidTile(format!("{}/{}/{}", zoom, xtile, ytile)));
+        }
+        if zoom < ts.minzoom() || zoom > ts.maxzoom() {
+            return Ok(None);
+        }
+        let y = self.grid.ytile_tms(ytile, zoom);
+        let mvt_tile = self.tile(tileset, xtile, y, zoom, None)?;
+        let extent = self.grid.tile_extent(xtile, y, zoom);
+        let geojson = if self.grid.srid == 3857 {
+            tile_to_geojson(&mvt_tile, &extent, merc_to_lonlat)
+        } else {
+            tile_to_geojson(&mvt_tile, &extent, |x, y| (x, y))
+        };
+        Ok(Some(geojson))
+    }
+    /// UTFGrid of vector tile at x, y, z in tile scheme of grid, keyed by the
+    /// `utfgrid_key` attribute of the tileset.
+    ///
+    /// The grid has one cell per 4x4 pixels of the logical tile size.
+    pub fn tile_utfgrid(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+    ) -> Result<Option<serde_json::Value>, Error> {
+        let ts = self
+            .get_tileset(tileset)
+            .ok_or_else(|| Error::UnknownTileset(tileset.to_string()))?;
+        let key = match ts.utfgrid_key {
+            Some(ref key) => key,
+            None => return Ok(None),
+        };
+        if !self.grid.contains_tile(xtile, ytile, zoom) {
+            return Err(Error::InvalidTile(format!("{}/{}/{}", zoom, xtile, ytile)));
+        }
+        if zoom < ts.minzoom() || zoom > ts.maxzoom() {
+            return Ok(None);
         }
+        let y = self.grid.ytile_tms(ytile, zoom);
+        let mvt_tile = self.tile(tileset, xtile, y, zoom, None)?;
+        let size = ts.pixel_size.unwrap_or(self.grid.tile_size()) / 4;
+        Ok(Some(tile_to_utfgrid(&mvt_tile, key, size as usize)))
     }
-    fn progress_bar(&self, msg: &str, limits: &ExtentInt) -> ProgressBar<Stdout> {
+    /// Progress bar for the share of `nodes` generator nodes of a level
+    fn progress_bar(&self, msg: &str, limits: &ExtentInt, nodes: u64) -> ProgressBar<Stdout> {
         let tiles =
             (limits.maxx as u64 - limits.minx as u64) * (limits.maxy as u64 - limits.miny as u64);
-        let mut pb = ProgressBar::new(tiles);
+        let mut pb = ProgressBar::new((tiles + nodes - 1) / nodes);
         pb.message(msg);
         //pb.set_max_refresh_rate(Some(Duration::from_millis(200)));
         pb.show_speed = false;
         pb.show_percent = false;
-        pb.show_time_left = false;
+        pb.show_time_left = true;
         pb
     }
     /// Projected extent in grid SRS from WGS84
@@ -209,17 +681,21 @@ impl MvtService {
                 ))
         }
     }
-    /// Populate tile cache
+    /// Populate tile cache.
+    /// `extent` is in WGS84 or in grid SRS, if `extent_srid` is the SRID of the grid.
+    /// Levels completed according to `seed_state` are skipped.
     pub fn generate(
         &self,
         tileset_name: Option<&str>,
         minzoom: Option<u8>,
         maxzoom: Option<u8>,
         extent: Option<Extent>,
-        nodes: Option<u8>,
-        nodeno: Option<u8>,
+        extent_srid: Option<i32>,
+        nodes: Option<u32>,
+        nodeno: Option<u32>,
         progress: bool,
         overwrite: bool,
+        mut seed_state: Option<SeedState>,
     ) -> Statistics {
         self.init_cache();
         let mut stats = Statistics::new();
@@ -235,14 +711,21 @@ impl MvtService {
             }
 
             // Convert extent to grid SRS
-            let extent = extent.as_ref().or(tileset.extent.as_ref());
-            debug!("wgs84 extent: {:?}", extent);
             let ext_proj = match extent {
-                // (-180 -90) throws error when projecting
-                Some(ext_wgs84) if *ext_wgs84 != WORLD_EXTENT => self.extent_from_wgs84(ext_wgs84),
+                Some(ref ext_grid) if extent_srid == Some(self.grid.srid) => ext_grid.clone(),
                 _ => {
-                    warn!("Building cache for the full globe, please fill in the tileset extent");
-                    self.grid.tile_extent(0, 0, 0)
+                    let extent = extent.as_ref().or(tileset.extent.as_ref());
+                    debug!("wgs84 extent: {:?}", extent);
+                    match extent {
+                        // (-180 -90) throws error when projecting
+                        Some(ext_wgs84) if *ext_wgs84 != WORLD_EXTENT => {
+                            self.extent_from_wgs84(ext_wgs84)
+                        }
+                        _ => {
+                            warn!("Building cache for the full globe, please fill in the tileset extent");
+                            self.grid.tile_extent(0, 0, 0)
+                        }
+                    }
                 }
             };
             debug!("tile limits: {:?}", ext_proj);
@@ -265,39 +748,62 @@ impl MvtService {
                 warn!("Skipping zoom levels >{}", ts_maxzoom);
             }
             for zoom in ts_minzoom..=ts_maxzoom {
+                if !tileset.is_cachable_at(zoom) {
+                    info!(
+                        "Skipping level {} excluded by cache_limits of tileset '{}'",
+                        zoom, tileset.name
+                    );
+                    continue;
+                }
                 let ref limit = limits[zoom as usize];
                 debug!("level {}: {:?}", zoom, limit);
-                let mut pb = self.progress_bar(&format!("Level {}: ", zoom), &limit);
+                let level_tiles =
+                    (limit.maxx - limit.minx) as u64 * (limit.maxy - limit.miny) as u64;
+                let completed = seed_state
+                    .as_ref()
+                    .map_or(0, |state| state.completed(&tileset.name, zoom));
+                if completed > 0 && completed >= level_tiles {
+                    info!(
+                        "Skipping completed level {} of tileset '{}'",
+                        zoom, tileset.name
+                    );
+                    tileno += level_tiles;
+                    continue;
+                }
+                if completed > 0 {
+                    info!(
+                        "Resuming level {} of tileset '{}' after {} of {} tiles",
+                        zoom, tileset.name, completed, level_tiles
+                    );
+                }
+                let mut pb = self.progress_bar(&format!("Level {}: ", zoom), &limit, nodes);
                 if progress {
                     pb.tick();
                 }
+                // Tile number within level
+                let mut levelno: u64 = 0;
                 for xtile in limit.minx..limit.maxx {
                     for ytile in limit.miny..limit.maxy {
-                        let skip = tileno % nodes != nodeno;
+                        levelno += 1;
+                        let skip = tileno % nodes != nodeno || levelno <= completed;
                         tileno += 1;
                         if skip {
                             continue;
                         }
 
-                        // store in xyz schema. TODO: make configurable
-                        let y = self.grid.ytile_from_xyz(ytile, zoom);
-                        let path = format!("{}/{}/{}/{}.pbf", &tileset.name, zoom, xtile, y);
+                        // store in tile scheme of grid
+                        let y = self.grid.ytile_tms(ytile, zoom);
+                        let path = self.tile_cache_path(&tileset.name, zoom, xtile, y);
 
-                        if overwrite || !self.cache.exists(&path) {
-                            // Entry doesn't exist, or we're ignoring it, so generate it
-                            let mvt_tile = self.tile(
-                                &tileset.name,
-                                xtile as u32,
-                                ytile as u32,
-                                zoom,
-                                Some(&mut stats),
-                            );
-                            if mvt_tile.get_layers().len() > 0 {
-                                let tilegz = Tile::tile_bytevec_gz(&mvt_tile);
-                                if let Err(ioerr) = self.cache.write(&path, &tilegz) {
-                                    error!("Error writing {}: {}", path, ioerr);
-                                }
-                            }
+                        if overwrite
+                            || !self.cache.exists(&path)
+                            || self.cache_expired(tileset, &path)
+                        {
+                            // Entry doesn't exist, is expired or we're ignoring it, so generate it
+                            self.generate_tile(tileset, xtile, ytile, zoom, &mut stats);
+                        }
+                        if let Some(ref mut state) = seed_state {
+                            state.set_completed(&tileset.name, zoom, levelno);
                         }
 
                         if progress {
@@ -305,13 +811,247 @@ impl MvtService {
                         }
                     }
                 }
+                if let Some(ref mut state) = seed_state {
+                    state.set_completed(&tileset.name, zoom, level_tiles);
+                    state.save();
+                }
             }
         }
         if progress {
             println!("");
         }
+        if let Some(state) = seed_state {
+            state.remove();
+        }
+        #[cfg(feature = "with-mbtiles")]
+        {
+            if let Tilecache::Mbtiles(ref mbtiles) = self.cache {
+                self.write_mbtiles_metadata(mbtiles, tileset_name);
+            }
+        }
+        if let Tilecache::Pmtiles(ref pmtiles) = self.cache {
+            self.write_pmtiles_archive(pmtiles, tileset_name);
+        }
         stats
     }
+    /// Render tile with limits coordinates and write it into the cache.
+    /// Cached tiles without features are removed.
+    fn generate_tile(
+        &self,
+        tileset: &Tileset,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        stats: &mut Statistics,
+    ) {
+        // store in tile scheme of grid
+        let y = self.grid.ytile_tms(ytile, zoom);
+        let path = self.tile_cache_path(&tileset.name, zoom, xtile, y);
+        match self.tile(&tileset.name, xtile, ytile, zoom, Some(stats)) {
+            Ok(ref mvt_tile) if mvt_tile.get_layers().len() > 0 => {
+                let written = Tile::tile_bytevec_compressed(&mvt_tile, self.compression)
+                    .and_then(|tilegz| self.cache.write(&path, &tilegz));
+                if let Err(ioerr) = written {
+                    error!("Error writing {}: {}", path, ioerr);
+                }
+            }
+            Ok(_) => {
+                if self.cache.exists(&path) {
+                    if let Err(ioerr) = self.cache.purge(&path) {
+                        error!("Error removing {}: {}", path, ioerr);
+                    }
+                }
+            }
+            Err(e) => error!("Error generating {}: {}", path, e),
+        }
+    }
+    /// Regenerate cached tiles containing features modified since `since` (ISO 8601 timestamp).
+    /// Features are selected by the `modified_field` of the layers.
+    /// Tiles at the previous position of moved or deleted features are not regenerated.
+    pub fn generate_modified(
+        &self,
+        tileset_name: Option<&str>,
+        minzoom: Option<u8>,
+        maxzoom: Option<u8>,
+        since: &str,
+        progress: bool,
+    ) -> Result<Statistics, Error> {
+        self.init_cache();
+        let mut stats = Statistics::new();
+        for tileset in &self.tilesets {
+            if tileset_name.is_some() && tileset_name.unwrap() != &tileset.name {
+                continue;
+            }
+            let ts_minzoom = cmp::max(tileset.minzoom(), minzoom.unwrap_or(0));
+            let ts_maxzoom = cmp::min(
+                cmp::min(tileset.maxzoom(), maxzoom.unwrap_or(99)),
+                self.grid.maxzoom(),
+            );
+            // Tiles in limits coordinates
+            let mut tiles: HashSet<(u8, u32, u32)> = HashSet::new();
+            for layer in &tileset.layers {
+                if layer.modified_field.is_none() {
+                    continue;
+                }
+                let ds = self.ds(layer).ok_or_else(|| {
+                    Error::Datasource(format!("Layer '{}': Datasource not found", layer.name))
+                })?;
+                let extents = ds.modified_extents(layer, since, self.grid.srid)?;
+                info!(
+                    "Layer '{}': {} features modified since {}",
+                    layer.name,
+                    extents.len(),
+                    since
+                );
+                // Include neighbour tiles containing the feature in their buffer
+                let tolerance = if layer.buffer_size.unwrap_or(0) > 0 {
+                    1
+                } else {
+                    0
+                };
+                let layer_minzoom = cmp::max(ts_minzoom, layer.minzoom());
+                let layer_maxzoom = cmp::min(ts_maxzoom, layer.maxzoom(22));
+                for extent in extents {
+                    let limits = self.grid.tile_limits(extent, tolerance);
+                    for zoom in layer_minzoom..=layer_maxzoom {
+                        if !tileset.is_cachable_at(zoom) {
+                            continue;
+                        }
+                        let ref limit = limits[zoom as usize];
+                        // Points on tile borders result in empty limits
+                        for xtile in limit.minx..cmp::max(limit.maxx, limit.minx + 1) {
+                            for ytile in limit.miny..cmp::max(limit.maxy, limit.miny + 1) {
+                                tiles.insert((zoom, xtile, ytile));
+                            }
+                        }
+                    }
+                }
+            }
+            if tiles.is_empty() {
+                continue;
+            }
+            if progress {
+                println!(
+                    "Generating {} modified tiles of tileset '{}'...",
+                    tiles.len(),
+                    tileset.name
+                );
+            }
+            let mut tiles: Vec<_> = tiles.into_iter().collect();
+            tiles.sort();
+            let mut pb = ProgressBar::new(tiles.len() as u64);
+            pb.message("Tile ");
+            pb.show_speed = false;
+            pb.show_percent = false;
+            for (zoom, xtile, ytile) in tiles {
+                self.generate_tile(tileset, xtile, ytile, zoom, &mut stats);
+                if progress {
+                    pb.inc();
+                }
+            }
+            if progress {
+                println!("");
+            }
+        }
+        Ok(stats)
+    }
+    /// Fill metadata table of MBTiles file
+    #[cfg(feature = "with-mbtiles")]
+    fn write_mbtiles_metadata(&self, mbtiles: &Mbtiles, tileset_name: Option<&str>) {
+        let tileset = match tileset_name.or(self.tilesets.first().map(|ts| &ts.name as &str)) {
+            Some(tileset) => tileset,
+            None => return,
+        };
+        if tileset_name.is_none() && self.tilesets.len() > 1 {
+            warn!("MBTiles file contains tiles of several tilesets");
+        }
+        match self.get_mbtiles_metadata_table(tileset) {
+            Ok(rows) => {
+                if let Err(e) = mbtiles.write_metadata(&rows) {
+                    error!("Error writing MBTiles metadata: {}", e);
+                }
+            }
+            Err(e) => error!("Error creating MBTiles metadata: {}", e),
+        }
+    }
+    /// Write PMTiles archive with generated tiles and metadata
+    fn write_pmtiles_archive(&self, pmtiles: &Pmtiles, tileset_name: Option<&str>) {
+        let tileset = match tileset_name.or(self.tilesets.first().map(|ts| &ts.name as &str)) {
+            Some(tileset) => tileset,
+            None => return,
+        };
+        info!("Writing PMTiles file {}", pmtiles.path);
+        match self.get_pmtiles_metadata(tileset) {
+            Ok(metadata) => {
+                if let Err(e) = pmtiles.finalize(&metadata) {
+                    error!("Error writing PMTiles file: {}", e);
+                }
+            }
+            Err(e) => error!("Error creating PMTiles metadata: {}", e),
+        }
+    }
+    /// Remove cached tiles of a tileset.
+    /// Purges the zoom levels between `minzoom` and `maxzoom` (Default: tileset maxzoom) or only
+    /// the tiles intersecting `extent` (WGS84), if given. Levels with more than
+    /// `MAX_PURGE_TILES` tiles in the extent are purged completely.
+    pub fn invalidate(
+        &self,
+        tileset_name: &str,
+        minzoom: Option<u8>,
+        maxzoom: Option<u8>,
+        extent: Option<&Extent>,
+    ) -> Result<(), Error> {
+        let tileset = self
+            .get_tileset(tileset_name)
+            .ok_or_else(|| Error::UnknownTileset(tileset_name.to_string()))?;
+        let cache_error = |e: io::Error| Error::Cache(e.to_string());
+        let extent = extent.filter(|ext| **ext != WORLD_EXTENT);
+        if minzoom.is_none() && maxzoom.is_none() && extent.is_none() {
+            info!("Purging cache of tileset '{}'", tileset.name);
+            self.cache
+                .purge(&self.tileset_cache_path(&tileset.name))
+                .map_err(cache_error)?;
+        } else {
+            let limits = extent.map(|ext| self.grid.tile_limits(self.extent_from_wgs84(ext), 0));
+            let maxzoom = cmp::min(maxzoom.unwrap_or(tileset.maxzoom()), self.grid.maxzoom());
+            for zoom in minzoom.unwrap_or(0)..=maxzoom {
+                // Purging a whole level is faster than checking many single tiles
+                let limit = limits
+                    .as_ref()
+                    .map(|limits| &limits[zoom as usize])
+                    .filter(|limit| {
+                        (limit.maxx - limit.minx) as u64 * (limit.maxy - limit.miny) as u64
+                            <= MAX_PURGE_TILES
+                    });
+                match limit {
+                    Some(limit) => {
+                        debug!("Purging tiles of level {}: {:?}", zoom, limit);
+                        for xtile in limit.minx..limit.maxx {
+                            for ytile in limit.miny..limit.maxy {
+                                // stored in tile scheme of grid
+                                let y = self.grid.ytile_tms(ytile, zoom);
+                                let path = self.tile_cache_path(&tileset.name, zoom, xtile, y);
+                                if self.cache.exists(&path) {
+                                    self.cache.purge(&path).map_err(cache_error)?;
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        debug!("Purging level {} of tileset '{}'", zoom, tileset.name);
+                        let path = format!("{}/{}", self.tileset_cache_path(&tileset.name), zoom);
+                        self.cache.purge(&path).map_err(cache_error)?;
+                    }
+                }
+            }
+        }
+        for &(_, ref service) in &self.grid_services {
+            if service.get_tileset(tileset_name).is_some() {
+                service.invalidate(tileset_name, minzoom, maxzoom, extent)?;
+            }
+        }
+        Ok(())
+    }
     pub fn init_cache(&self) {
         info!("{}", &self.cache.info());
         for tileset in &self.tilesets {
@@ -395,17 +1135,19 @@ impl MvtService {
                     debug!("level {}: {:?}", zoom, limit);
                     let xtile = limit.minx;
                     let ytile = limit.miny;
-                    let mvt_tile = self.tile(
+                    match self.tile(
                         &tileset.name,
                         xtile as u32,
                         ytile as u32,
                         zoom,
                         Some(&mut stats),
-                    );
-                    stats.add(
-                        format!("tile_bytes.{}.total.{}", &tileset.name, zoom),
-                        Tile::size(&mvt_tile) as u64,
-                    );
+                    ) {
+                        Ok(mvt_tile) => stats.add(
+                            format!("tile_bytes.{}.total.{}", &tileset.name, zoom),
+                            Tile::size(&mvt_tile) as u64,
+                        ),
+                        Err(e) => error!("Error at level {}: {}", zoom, e),
+                    }
                     if progress {
                         pb.inc();
                     }
@@ -417,6 +1159,80 @@ impl MvtService {
         }
         stats
     }
+    /// Create generalized tables for PostGIS layers
+    ///
+    /// Returns the TOML query sections mapping the zoom ranges to the new tables for each layer.
+    pub fn generalize(
+        &self,
+        tileset_name: Option<&str>,
+        maxzooms: &[u8],
+    ) -> Vec<(String, String)> {
+        let mut mappings = Vec::new();
+        for tileset in &self.tilesets {
+            if tileset_name.is_some() && tileset_name.unwrap() != &tileset.name {
+                continue;
+            }
+            for layer in &tileset.layers {
+                let pg = match self.ds(layer) {
+                    Some(&Datasource::Postgis(ref pg)) => pg,
+                    _ => {
+                        info!("Layer '{}': Skipping non-PostGIS layer", layer.name);
+                        continue;
+                    }
+                };
+                if layer.table_name.is_none() || !layer.query.is_empty() {
+                    info!("Layer '{}': Skipping layer with custom queries", layer.name);
+                    continue;
+                }
+                if layer.geometry_type == Some("POINT".to_string()) {
+                    continue;
+                }
+                if layer.srid.is_some() && layer.srid != Some(self.grid.srid) {
+                    warn!(
+                        "Layer '{}': Generalization requires data in grid SRS",
+                        layer.name
+                    );
+                    continue;
+                }
+                let table = layer.table_name.as_ref().unwrap().replace('"', "");
+                let mut toml = String::new();
+                let mut minzoom = layer.minzoom();
+                for &maxzoom in maxzooms {
+                    if maxzoom < minzoom || maxzoom >= layer.maxzoom(22) {
+                        continue;
+                    }
+                    let gen_table = format!("{}_gen_z{}", table, maxzoom);
+                    let pixel_width = self.grid.pixel_width(maxzoom);
+                    info!(
+                        "Layer '{}': Creating table {} for zoom levels {}-{}",
+                        layer.name, gen_table, minzoom, maxzoom
+                    );
+                    match pg.create_generalized_table(
+                        layer,
+                        &gen_table,
+                        pixel_width / 2.0,
+                        pixel_width * pixel_width,
+                    ) {
+                        Ok(count) => {
+                            info!("Layer '{}': {} features in {}", layer.name, count, gen_table);
+                            toml.push_str(&format!(
+                                "[[tileset.layer.query]]\nminzoom = {}\nmaxzoom = {}\ntable_name = \"{}\"\n",
+                                minzoom, maxzoom, gen_table
+                            ));
+                        }
+                        Err(e) => {
+                            error!("Layer '{}': {}", layer.name, e);
+                        }
+                    }
+                    minzoom = maxzoom + 1;
+                }
+                if !toml.is_empty() {
+                    mappings.push((layer.name.clone(), toml));
+                }
+            }
+        }
+        mappings
+    }
     fn gen_layer_runtime_config(&self, layer: &Layer, grid_srid: i32) -> String {
         let ds = self.ds(layer).unwrap();
         let mut lines = vec!["\n[[tileset]]".to_string()];
@@ -445,14 +1261,22 @@ impl MvtService {
         if let &Datasource::Postgis(ref pg) = ds {
             if layer.query(0).is_none() {
                 let query = pg.build_query_sql(layer, 3857, None, true).unwrap();
-                // Remove quotes from column names for better readability
-                cfg.push_str(&format!("#sql = \"\"\"{}\"\"\"\n", query.replace('"', "")))
+                cfg.push_str(&format!("#sql = '''{}'''\n", query))
             }
         }
         cfg
     }
 }
 
+fn postprocessor(config: &ApplicationCfg) -> Option<Box<TilePostprocessor>> {
+    config
+        .service
+        .mvt
+        .postprocess
+        .as_ref()
+        .map(|cmd| Box::new(CommandPostprocessor::new(cmd)) as Box<TilePostprocessor>)
+}
+
 impl<'a> Config<'a, ApplicationCfg> for MvtService {
     fn from_config(config: &ApplicationCfg) -> Result<Self, String> {
         let datasources = Datasources::from_config(config)?;
@@ -460,15 +1284,72 @@ impl<'a> Config<'a, ApplicationCfg> for MvtService {
         let tilesets = config
             .tilesets
             .iter()
-            .map(|ts_cfg| Tileset::from_config(ts_cfg).unwrap())
-            .collect();
+            .map(|ts_cfg| Tileset::from_config(ts_cfg))
+            .collect::<Result<Vec<_>, _>>()?;
         let cache = Tilecache::from_config(&config)?;
-        Ok(MvtService {
+        if !cache.tracks_modification() {
+            if let Some(ts) = tilesets.iter().find(|ts| ts.cache_ttl().is_some()) {
+                return Err(format!(
+                    "Tileset '{}': cache TTL not supported by {}",
+                    ts.name,
+                    cache.info()
+                ));
+            }
+        }
+        let winding_order = match config.service.mvt.winding_order {
+            Some(ref order) => order.parse()?,
+            None => WindingOrder::default(),
+        };
+        let compression = match config.cache.as_ref().and_then(|c| c.compression.as_ref()) {
+            Some(compression) => compression.parse()?,
+            None => TileCompression::default(),
+        };
+        let mut grid_services = Vec::new();
+        for grid_cfg in &config.grid.additional {
+            let tilesets = config
+                .tilesets
+                .iter()
+                .filter(|ts_cfg| ts_cfg.grids.contains(&grid_cfg.name))
+                .map(|ts_cfg| Tileset::from_config(ts_cfg))
+                .collect::<Result<Vec<_>, _>>()?;
+            let service = MvtService {
+                datasources: Datasources::from_config(config)?,
+                grid: Grid::from_named_config(grid_cfg)?,
+                tilesets: tilesets,
+                cache: Tilecache::from_config(&config)?,
+                winding_order: winding_order,
+                grid_services: Vec::new(),
+                grid_name: Some(grid_cfg.name.clone()),
+                postprocessor: postprocessor(config),
+                slow_tile_ms: config.service.mvt.slow_tile_ms,
+                compression: compression,
+            };
+            grid_services.push((grid_cfg.name.clone(), service));
+        }
+        for ts_cfg in &config.tilesets {
+            for name in &ts_cfg.grids {
+                if !config.grid.additional.iter().any(|g| &g.name == name) {
+                    return Err(format!("Tileset '{}': Unknown grid '{}'", ts_cfg.name, name));
+                }
+            }
+        }
+        let mut service = MvtService {
             datasources: datasources,
             grid: grid,
             tilesets: tilesets,
             cache: cache,
-        })
+            winding_order: winding_order,
+            grid_services: grid_services,
+            grid_name: None,
+            postprocessor: postprocessor(config),
+            slow_tile_ms: config.service.mvt.slow_tile_ms,
+            compression: compression,
+        };
+        // Tilesets on additional grids use the same memory cache
+        if let Some(store) = Tilecache::memory_store_from_config(config) {
+            service.share_memory_store(&store);
+        }
+        Ok(service)
     }
     fn gen_config() -> String {
         let mut config = String::new();
@@ -494,7 +1375,7 @@ impl<'a> Config<'a, ApplicationCfg> for MvtService {
     }
 }
 
-const TOML_SERVICES: &'static str = r#"# t-rex configuration
+pub(crate) const TOML_SERVICES: &'static str = r#"# t-rex configuration
 
 [service.mvt]
 viewer = true
@@ -0,0 +1,320 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! OGC API - Tiles (https://docs.ogc.org/is/20-057/20-057.html)
+
+use metadata::tile_matrix_set_id;
+use mvt_service::MvtService;
+use serde_json;
+
+const CONFORMANCE: &[&str] = &[
+    "http://www.opengis.net/spec/ogcapi-common-1/1.0/conf/core",
+    "http://www.opengis.net/spec/ogcapi-common-1/1.0/conf/landing-page",
+    "http://www.opengis.net/spec/ogcapi-common-1/1.0/conf/json",
+    "http://www.opengis.net/spec/ogcapi-common-2/1.0/conf/collections",
+    "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/core",
+    "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/tileset",
+    "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/tilesets-list",
+    "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/geodata-tilesets",
+    "http://www.opengis.net/spec/ogcapi-tiles-1/1.0/conf/mvt",
+    "http://www.opengis.net/spec/tms/2.0/conf/json-tilematrixset",
+];
+
+const MVT_MIME: &str = "application/vnd.mapbox-vector-tile";
+
+fn link(href: String, rel: &str, mime: &str, title: &str) -> serde_json::Value {
+    json!({"href": href, "rel": rel, "type": mime, "title": title})
+}
+
+impl MvtService {
+    fn ogcapi_crs(&self) -> String {
+        format!("http://www.opengis.net/def/crs/EPSG/0/{}", self.grid.srid)
+    }
+    /// OGC API landing page
+    pub fn get_ogcapi_landing_page(&self, baseurl: &str) -> serde_json::Value {
+        json!({
+            "title": "t-rex",
+            "description": "t-rex vector tile server",
+            "links": [
+                link(format!("{}/ogcapi", baseurl), "self", "application/json", "This document"),
+                link(
+                    format!("{}/ogcapi/conformance", baseurl),
+                    "conformance",
+                    "application/json",
+                    "Conformance classes"
+                ),
+                link(
+                    format!("{}/ogcapi/collections", baseurl),
+                    "data",
+                    "application/json",
+                    "Tilesets as collections"
+                ),
+                link(
+                    format!("{}/ogcapi/tileMatrixSets", baseurl),
+                    "http://www.opengis.net/def/rel/ogc/1.0/tiling-schemes",
+                    "application/json",
+                    "Tile matrix sets"
+                ),
+            ]
+        })
+    }
+    /// OGC API conformance declaration
+    pub fn get_ogcapi_conformance(&self) -> serde_json::Value {
+        json!({ "conformsTo": CONFORMANCE })
+    }
+    /// List of tile matrix sets (grid of service)
+    pub fn get_ogcapi_tile_matrix_sets(&self, baseurl: &str) -> serde_json::Value {
+        let id = tile_matrix_set_id(&self.grid);
+        json!({
+            "tileMatrixSets": [{
+                "id": id,
+                "links": [link(
+                    format!("{}/ogcapi/tileMatrixSets/{}", baseurl, id),
+                    "self",
+                    "application/json",
+                    &id
+                )]
+            }]
+        })
+    }
+    /// Tile matrix set definition (OGC 17-083r4)
+    pub fn get_ogcapi_tile_matrix_set(&self, tms_id: &str) -> Option<serde_json::Value> {
+        let grid = &self.grid;
+        if tms_id != tile_matrix_set_id(grid) {
+            return None;
+        }
+        let tile_matrices: Vec<_> = (0..grid.nlevels())
+            .map(|zoom| {
+                let top_left = grid.tile_extent(0, grid.ytile_from_wmts(0, zoom), zoom);
+                // EPSG:4326 has latitude/longitude axis order
+                let origin = if grid.srid == 4326 {
+                    [top_left.maxy, top_left.minx]
+                } else {
+                    [top_left.minx, top_left.maxy]
+                };
+                let (width, height) = grid.matrix_size(zoom);
+                json!({
+                    "id": zoom.to_string(),
+                    "scaleDenominator": grid.scale_denominator(zoom),
+                    "cellSize": grid.resolution(zoom),
+                    "cornerOfOrigin": "topLeft",
+                    "pointOfOrigin": origin,
+                    "tileWidth": grid.tile_size(),
+                    "tileHeight": grid.tile_size(),
+                    "matrixWidth": width,
+                    "matrixHeight": height
+                })
+            })
+            .collect();
+        let axes = if grid.srid == 4326 {
+            ["Lat", "Lon"]
+        } else {
+            ["X", "Y"]
+        };
+        Some(json!({
+            "id": tms_id,
+            "crs": self.ogcapi_crs(),
+            "orderedAxes": axes,
+            "tileMatrices": tile_matrices
+        }))
+    }
+    /// Tilesets as OGC API collections
+    pub fn get_ogcapi_collections(&self, baseurl: &str) -> serde_json::Value {
+        let collections: Vec<_> = self
+            .tilesets
+            .iter()
+            .filter_map(|ts| self.get_ogcapi_collection(baseurl, &ts.name))
+            .collect();
+        json!({
+            "links": [link(
+                format!("{}/ogcapi/collections", baseurl),
+                "self",
+                "application/json",
+                "Collections"
+            )],
+            "collections": collections
+        })
+    }
+    /// Collection description of tileset
+    pub fn get_ogcapi_collection(&self, baseurl: &str, tileset: &str) -> Option<serde_json::Value> {
+        let ts = self.get_tileset(tileset)?;
+        let ext = ts.get_extent();
+        let url = format!("{}/ogcapi/collections/{}", baseurl, ts.name);
+        Some(json!({
+            "id": ts.name,
+            "title": ts.name,
+            "extent": {
+                "spatial": {
+                    "bbox": [[ext.minx, ext.miny, ext.maxx, ext.maxy]],
+                    "crs": "http://www.opengis.net/def/crs/OGC/1.3/CRS84"
+                }
+            },
+            "dataType": "vector",
+            "links": [
+                link(url.clone(), "self", "application/json", &ts.name),
+                link(
+                    format!("{}/tiles", url),
+                    "http://www.opengis.net/def/rel/ogc/1.0/tilesets-vector",
+                    "application/json",
+                    "Vector tilesets"
+                ),
+            ]
+        }))
+    }
+    /// List of vector tilesets of a collection
+    pub fn get_ogcapi_tilesets(&self, baseurl: &str, tileset: &str) -> Option<serde_json::Value> {
+        let ts = self.get_tileset(tileset)?;
+        let id = tile_matrix_set_id(&self.grid);
+        Some(json!({
+            "tilesets": [{
+                "title": ts.name,
+                "dataType": "vector",
+                "crs": self.ogcapi_crs(),
+                "links": [
+                    link(
+                        format!("{}/ogcapi/collections/{}/tiles/{}", baseurl, ts.name, id),
+                        "self",
+                        "application/json",
+                        &id
+                    ),
+                    link(
+                        format!("{}/ogcapi/tileMatrixSets/{}", baseurl, id),
+                        "http://www.opengis.net/def/rel/ogc/1.0/tiling-scheme",
+                        "application/json",
+                        &id
+                    ),
+                ]
+            }]
+        }))
+    }
+    /// Tileset metadata with templated tile URL
+    pub fn get_ogcapi_tileset(
+        &self,
+        baseurl: &str,
+        tileset: &str,
+        tms_id: &str,
+    ) -> Option<serde_json::Value> {
+        let ts = self.get_tileset(tileset)?;
+        if tms_id != tile_matrix_set_id(&self.grid) {
+            return None;
+        }
+        let url = format!(
+            "{}/ogcapi/collections/{}/tiles/{}",
+            baseurl, ts.name, tms_id
+        );
+        let mut layers: Vec<serde_json::Value> = Vec::new();
+        for layer in &ts.layers {
+            if layers.iter().any(|l| l["id"] == *layer.mvt_name()) {
+                continue;
+            }
+            layers.push(json!({
+                "id": layer.mvt_name(),
+                "geometryType": layer.geometry_type,
+                "minTileMatrix": layer.minzoom().to_string(),
+                "maxTileMatrix": layer.maxzoom(22).to_string()
+            }));
+        }
+        let mut tile_link = link(
+            format!("{}/{{tileMatrix}}/{{tileRow}}/{{tileCol}}", url),
+            "item",
+            MVT_MIME,
+            "Mapbox vector tiles",
+        );
+        tile_link["templated"] = json!(true);
+        let limits: Vec<_> = (ts.minzoom()..=ts.maxzoom())
+            .filter(|&zoom| zoom < self.grid.nlevels())
+            .map(|zoom| {
+                let (width, height) = self.grid.matrix_size(zoom);
+                json!({
+                    "tileMatrix": zoom.to_string(),
+                    "minTileRow": 0,
+                    "maxTileRow": height - 1,
+                    "minTileCol": 0,
+                    "maxTileCol": width - 1
+                })
+            })
+            .collect();
+        Some(json!({
+            "title": ts.name,
+            "dataType": "vector",
+            "crs": self.ogcapi_crs(),
+            "tileMatrixSetId": tms_id,
+            "tileMatrixSetLimits": limits,
+            "layers": layers,
+            "links": [
+                link(url, "self", "application/json", &ts.name),
+                link(
+                    format!("{}/ogcapi/tileMatrixSets/{}", baseurl, tms_id),
+                    "http://www.opengis.net/def/rel/ogc/1.0/tiling-scheme",
+                    "application/json",
+                    tms_id
+                ),
+                tile_link,
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+use core::Config;
+
+#[test]
+fn test_ogcapi_tile_matrix_set() {
+    use core::read_config;
+
+    let config = read_config("src/test/example.toml").unwrap();
+    let service = MvtService::from_config(&config).unwrap();
+
+    let tms = service
+        .get_ogcapi_tile_matrix_set("WebMercatorQuad")
+        .unwrap();
+    assert_eq!(tms["crs"], "http://www.opengis.net/def/crs/EPSG/0/3857");
+    assert_eq!(tms["tileMatrices"].as_array().unwrap().len(), 23);
+    assert_eq!(
+        tms["tileMatrices"][1],
+        json!({
+            "id": "1",
+            "scaleDenominator": 279541132.0143589,
+            "cellSize": 78271.51696402048,
+            "cornerOfOrigin": "topLeft",
+            "pointOfOrigin": [-20037508.342789248, 20037508.34278924],
+            "tileWidth": 256,
+            "tileHeight": 256,
+            "matrixWidth": 2,
+            "matrixHeight": 2
+        })
+    );
+    assert!(service
+        .get_ogcapi_tile_matrix_set("WorldCRS84Quad")
+        .is_none());
+}
+
+#[test]
+fn test_ogcapi_tileset() {
+    use core::read_config;
+
+    let config = read_config("src/test/example.toml").unwrap();
+    let service = MvtService::from_config(&config).unwrap();
+
+    let collections = service.get_ogcapi_collections("http://127.0.0.1");
+    assert_eq!(collections["collections"][0]["id"], "osm");
+
+    let tileset = service
+        .get_ogcapi_tileset("http://127.0.0.1", "osm", "WebMercatorQuad")
+        .unwrap();
+    assert_eq!(tileset["dataType"], "vector");
+    assert_eq!(tileset["layers"][0]["id"], "points");
+    assert_eq!(tileset["tileMatrixSetLimits"][2]["maxTileRow"], 3);
+    assert_eq!(
+        tileset["links"][2]["href"],
+        "http://127.0.0.1/ogcapi/collections/osm/tiles/WebMercatorQuad/{tileMatrix}/{tileRow}/{tileCol}"
+    );
+    assert!(service
+        .get_ogcapi_tileset("http://127.0.0.1", "unknown", "WebMercatorQuad")
+        .is_none());
+    assert!(service
+        .get_ogcapi_tileset("http://127.0.0.1", "osm", "unknown")
+        .is_none());
+}
@@ -28,12 +28,45 @@ pub fn get_user_name() -> String {
     env::var("LOGNAME").unwrap_or("".to_string())
 }
 
-fn read_xml(fname: &str) -> Result<Element, io::Error> {
+pub fn read_xml(fname: &str) -> Result<Element, io::Error> {
     let file = File::open(fname)?;
     let mut reader = BufReader::new(file);
     Element::from_reader(&mut reader).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
 }
 
+/// PostgreSQL connection URI from connection parameters
+pub fn pg_uri(params: &HashMap<&str, &str>) -> String {
+    //postgresql://[user[:password]@][netloc][:port][/dbname][?param1=value1&...]
+    let mut uri = "postgresql://".to_string();
+    if params.contains_key("user") {
+        uri.push_str(params["user"]);
+        if params.contains_key("password") {
+            uri.push_str(":");
+            uri.push_str(params["password"]);
+        }
+        uri.push_str("@");
+    }
+    if params.contains_key("host") {
+        uri.push_str(params["host"]);
+    } else {
+        // socket
+        if !params.contains_key("user") {
+            uri.push_str(&get_user_name());
+            uri.push_str("@");
+        }
+        uri.push_str("%2Frun%2Fpostgresql"); // TODO
+    }
+    if params.contains_key("port") {
+        uri.push_str(":");
+        uri.push_str(params["port"]);
+    }
+    if params.contains_key("dbname") {
+        uri.push_str("/");
+        uri.push_str(params["dbname"]);
+    }
+    uri
+}
+
 #[derive(Debug)]
 struct PgLayerInfo {
     pub dbconn: String,
@@ -62,34 +95,7 @@ impl PgLayerInfo {
             })
             .collect();
 
-        //postgresql://[user[:password]@][netloc][:port][/dbname][?param1=value1&...]
-        let mut uri = "postgresql://".to_string();
-        if params.contains_key("user") {
-            uri.push_str(params["user"]);
-            if params.contains_key("password") {
-                uri.push_str(":");
-                uri.push_str(params["password"]);
-            }
-            uri.push_str("@");
-        }
-        if params.contains_key("host") {
-            uri.push_str(params["host"]);
-        } else {
-            // socket
-            if !params.contains_key("user") {
-                uri.push_str(&get_user_name());
-                uri.push_str("@");
-            }
-            uri.push_str("%2Frun%2Fpostgresql"); // TODO
-        }
-        if params.contains_key("port") {
-            uri.push_str(":");
-            uri.push_str(params["port"]);
-        }
-        if params.contains_key("dbname") {
-            uri.push_str("/");
-            uri.push_str(params["dbname"]);
-        }
+        let uri = pg_uri(&params);
 
         PgLayerInfo {
             dbconn: uri,
@@ -164,7 +170,11 @@ pub fn read_qgs(fname: &str) -> (Datasources, Tileset) {
         center: None,
         start_zoom: None,
         layers: Vec::new(),
-        cache_limits: None
+        cache_limits: None,
+        cache_control_max_age: None,
+        pixel_size: None,
+        utfgrid_key: None,
+        style_json: None,
     };
     for qgslayer in projectlayers.find_all("maplayer") {
         let layertype = qgslayer.get_attr("type").expect("Missing attribute 'type'");
@@ -0,0 +1,175 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Progress of tile generation for resuming an interrupted run
+
+use core::grid::Extent;
+use serde_json;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::time::Instant;
+
+/// Minimal interval between writes of the state file in seconds
+const SAVE_INTERVAL: u64 = 10;
+
+/// Parameters of a generation run, which have to be unchanged for resuming it
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct SeedParams {
+    pub tileset: Option<String>,
+    pub minzoom: Option<u8>,
+    pub maxzoom: Option<u8>,
+    pub extent: Option<Extent>,
+    pub extent_srid: Option<i32>,
+    /// Configured extent of each tileset
+    pub tileset_extents: BTreeMap<String, Option<Extent>>,
+    /// Number of generator nodes times number of threads
+    pub nodes: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SeedStateFile {
+    params: SeedParams,
+    completed: BTreeMap<String, BTreeMap<u8, u64>>,
+}
+
+/// Number of completed tiles per tileset and zoom level, persisted in a JSON file
+pub struct SeedState {
+    path: String,
+    params: SeedParams,
+    /// Tiles in iteration order of the level, which have been generated
+    completed: BTreeMap<String, BTreeMap<u8, u64>>,
+    saved: Instant,
+}
+
+impl SeedState {
+    /// Read state file or start with an empty state, if it doesn't exist.
+    /// A state file written with other `params` is rejected.
+    pub fn load(path: &str, params: SeedParams) -> Result<SeedState, String> {
+        let completed = match File::open(path) {
+            Ok(file) => {
+                let state: SeedStateFile = serde_json::from_reader(file)
+                    .map_err(|e| format!("Invalid seed state file '{}' - {}", path, e))?;
+                if state.params != params {
+                    return Err(format!(
+                        "Seed state file '{}' was written with other generation parameters - remove it to start a new run",
+                        path
+                    ));
+                }
+                state.completed
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Error reading '{}' - {}", path, e)),
+        };
+        Ok(SeedState {
+            path: path.to_string(),
+            params,
+            completed,
+            saved: Instant::now(),
+        })
+    }
+    /// Number of completed tiles of a level
+    pub fn completed(&self, tileset: &str, zoom: u8) -> u64 {
+        self.completed
+            .get(tileset)
+            .and_then(|levels| levels.get(&zoom))
+            .cloned()
+            .unwrap_or(0)
+    }
+    /// Set number of completed tiles of a level and save the state periodically
+    pub fn set_completed(&mut self, tileset: &str, zoom: u8, tiles: u64) {
+        self.completed
+            .entry(tileset.to_string())
+            .or_insert_with(BTreeMap::new)
+            .insert(zoom, tiles);
+        if self.saved.elapsed().as_secs() >= SAVE_INTERVAL {
+            self.save();
+        }
+    }
+    /// Write state file
+    pub fn save(&mut self) {
+        // Write into temporary file first, to keep the last state when interrupted
+        let tmppath = format!("{}.tmp", self.path);
+        let state = SeedStateFile {
+            params: self.params.clone(),
+            completed: self.completed.clone(),
+        };
+        let result = File::create(&tmppath)
+            .and_then(|file| {
+                serde_json::to_writer(file, &state)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            })
+            .and_then(|_| fs::rename(&tmppath, &self.path));
+        if let Err(e) = result {
+            error!("Error writing seed state file '{}': {}", self.path, e);
+        }
+        self.saved = Instant::now();
+    }
+    /// Remove state file after completion
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[test]
+fn test_seed_state() {
+    use std::env;
+    use std::process;
+
+    // Unique per test process, concurrent test runs don't share the file
+    let mut path = env::temp_dir();
+    path.push(format!("t_rex_seed_state_test_{}.json", process::id()));
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+
+    let params = SeedParams {
+        tileset: None,
+        minzoom: Some(3),
+        maxzoom: Some(6),
+        extent: None,
+        extent_srid: None,
+        tileset_extents: BTreeMap::new(),
+        nodes: 1,
+    };
+    let mut state = SeedState::load(path, params.clone()).unwrap();
+    assert_eq!(state.completed("points", 3), 0);
+    state.set_completed("points", 3, 42);
+    state.set_completed("points", 4, 7);
+    assert_eq!(state.completed("points", 3), 42);
+    state.save();
+
+    let state = SeedState::load(path, params.clone()).unwrap();
+    assert_eq!(state.completed("points", 3), 42);
+    assert_eq!(state.completed("points", 4), 7);
+    assert_eq!(state.completed("lines", 3), 0);
+
+    // Resuming with other parameters is rejected
+    let mut other = params.clone();
+    other.nodes = 4;
+    assert!(SeedState::load(path, other).is_err());
+    let mut other = params.clone();
+    other.tileset_extents.insert(
+        "points".to_string(),
+        Some(Extent {
+            minx: 5.9,
+            miny: 45.8,
+            maxx: 10.5,
+            maxy: 47.8,
+        }),
+    );
+    assert!(SeedState::load(path, other).is_err());
+
+    state.remove();
+    assert_eq!(
+        SeedState::load(path, params.clone())
+            .unwrap()
+            .completed("points", 3),
+        0
+    );
+
+    File::create(path).unwrap();
+    assert!(SeedState::load(path, params).is_err());
+    let _ = fs::remove_file(path);
+}
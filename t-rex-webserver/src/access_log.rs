@@ -0,0 +1,113 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Structured request log
+
+use actix_web::middleware::{Finished, Middleware, Started};
+use actix_web::{HttpRequest, HttpResponse, Result};
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Log target of request log records
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// Path parameters of tile requests included in the log record
+const TILE_PARAMS: &[&str] = &["tileset", "z", "x", "y", "row", "col"];
+
+struct RequestStart(Instant);
+
+/// Middleware logging each request as JSON object
+pub struct JsonLogger;
+
+fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+/// Log record of a finished request
+fn log_record(
+    method: &str,
+    path: &str,
+    status: u16,
+    latency: Duration,
+    size: u64,
+    remote: Option<&str>,
+    params: &[(&str, &str)],
+) -> Value {
+    let mut record = Map::new();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(secs)
+        .unwrap_or(0.0);
+    record.insert("timestamp".to_string(), json!(timestamp));
+    record.insert("method".to_string(), json!(method));
+    record.insert("path".to_string(), json!(path));
+    record.insert("status".to_string(), json!(status));
+    let latency_ms = latency.as_secs() as f64 * 1000.0 + latency.subsec_nanos() as f64 / 1e6;
+    record.insert("latency_ms".to_string(), json!(latency_ms));
+    record.insert("size".to_string(), json!(size));
+    if let Some(remote) = remote {
+        record.insert("remote".to_string(), json!(remote));
+    }
+    for &(key, value) in params {
+        // Tile coordinates as numbers
+        let value = value
+            .parse::<u32>()
+            .map(|num| json!(num))
+            .unwrap_or(json!(value));
+        record.insert(key.to_string(), value);
+    }
+    Value::Object(record)
+}
+
+impl<S> Middleware<S> for JsonLogger {
+    fn start(&self, req: &HttpRequest<S>) -> Result<Started> {
+        req.extensions_mut().insert(RequestStart(Instant::now()));
+        Ok(Started::Done)
+    }
+    fn finish(&self, req: &HttpRequest<S>, resp: &HttpResponse) -> Finished {
+        let latency = req
+            .extensions()
+            .get::<RequestStart>()
+            .map(|start| start.0.elapsed())
+            .unwrap_or_default();
+        let match_info = req.match_info();
+        let params: Vec<(&str, &str)> = TILE_PARAMS
+            .iter()
+            .filter_map(|&key| match_info.get(key).map(|value| (key, value)))
+            .collect();
+        let record = log_record(
+            req.method().as_str(),
+            req.path(),
+            resp.status().as_u16(),
+            latency,
+            resp.response_size(),
+            req.connection_info().remote(),
+            &params,
+        );
+        info!(target: ACCESS_LOG_TARGET, "{}", record);
+        Finished::Done
+    }
+}
+
+#[test]
+fn test_log_record() {
+    let record = log_record(
+        "GET",
+        "/osm/3/4/2.pbf",
+        200,
+        Duration::from_millis(12),
+        1024,
+        Some("127.0.0.1:50000"),
+        &[("tileset", "osm"), ("z", "3"), ("x", "4"), ("y", "2")],
+    );
+    assert!(record["timestamp"].as_f64().unwrap() > 0.0);
+    assert_eq!(record["status"], 200);
+    assert_eq!(record["latency_ms"], 12.0);
+    assert_eq!(record["size"], 1024);
+    assert_eq!(record["remote"], "127.0.0.1:50000");
+    assert_eq!(record["tileset"], "osm");
+    assert_eq!(record["z"], 3);
+    assert_eq!(record["y"], 2);
+}
@@ -0,0 +1,201 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Validation of JSON Web Tokens (https://tools.ietf.org/html/rfc7519)
+
+use base64;
+use core::config::WebserverJwtCfg;
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use serde_json::Value;
+use std::collections::HashMap;
+
+struct JwtKey {
+    /// Key id (`kid`) of JWKS key
+    kid: Option<String>,
+    hmac: bool,
+    /// HMAC secret or DER encoded RSA public key
+    key: Vec<u8>,
+}
+
+pub struct JwtValidator {
+    keys: Vec<JwtKey>,
+    claims: HashMap<String, String>,
+}
+
+impl JwtValidator {
+    /// Validator for HMAC signed tokens
+    pub fn from_secret(cfg: &WebserverJwtCfg, secret: &str) -> JwtValidator {
+        JwtValidator {
+            keys: vec![JwtKey {
+                kid: None,
+                hmac: true,
+                key: secret.as_bytes().to_vec(),
+            }],
+            claims: cfg.claims.clone(),
+        }
+    }
+    /// Validator for tokens signed with one of the RSA keys of a JSON Web Key Set
+    pub fn from_jwks(cfg: &WebserverJwtCfg, jwks: &Value) -> Result<JwtValidator, String> {
+        let jwk_keys = jwks["keys"]
+            .as_array()
+            .ok_or("JWKS without keys".to_string())?;
+        let mut keys = Vec::new();
+        for jwk in jwk_keys {
+            if jwk["kty"] != "RSA" {
+                continue;
+            }
+            let component = |name: &str| {
+                jwk[name]
+                    .as_str()
+                    .and_then(|val| base64::decode_config(val, base64::URL_SAFE_NO_PAD).ok())
+                    .ok_or(format!("Invalid RSA key parameter '{}'", name))
+            };
+            keys.push(JwtKey {
+                kid: jwk["kid"].as_str().map(|kid| kid.to_string()),
+                hmac: false,
+                key: rsa_public_key_der(&component("n")?, &component("e")?),
+            });
+        }
+        if keys.is_empty() {
+            return Err("JWKS without RSA keys".to_string());
+        }
+        Ok(JwtValidator {
+            keys,
+            claims: cfg.claims.clone(),
+        })
+    }
+    /// Check signature, expiration and required claims of token
+    pub fn validate(&self, token: &str) -> Result<(), String> {
+        let header = decode_header(token).map_err(|e| e.to_string())?;
+        let hmac = match header.alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => true,
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => false,
+        };
+        let validation = Validation::new(header.alg);
+        for jwt_key in &self.keys {
+            let other_kid =
+                header.kid.is_some() && jwt_key.kid.is_some() && header.kid != jwt_key.kid;
+            if jwt_key.hmac != hmac || other_kid {
+                continue;
+            }
+            if let Ok(data) = decode::<Value>(token, &jwt_key.key, &validation) {
+                return self.check_claims(&data.claims);
+            }
+        }
+        Err("Invalid token".to_string())
+    }
+    fn check_claims(&self, claims: &Value) -> Result<(), String> {
+        for (name, expected) in &self.claims {
+            let satisfied = match claims.get(name) {
+                Some(&Value::String(ref val)) => val == expected,
+                // e.g. multiple audiences
+                Some(&Value::Array(ref vals)) => {
+                    vals.iter().any(|val| val.as_str() == Some(expected))
+                }
+                Some(val) => val.to_string() == *expected,
+                None => false,
+            };
+            if !satisfied {
+                return Err(format!("Claim '{}' not satisfied", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes: Vec<u8> = (0..8)
+        .rev()
+        .map(|i| (len >> (i * 8)) as u8)
+        .skip_while(|&b| b == 0)
+        .collect();
+    let mut der = vec![0x80 | bytes.len() as u8];
+    der.extend(bytes);
+    der
+}
+
+fn der_integer(bytes: &[u8]) -> Vec<u8> {
+    let mut value: Vec<u8> = bytes.iter().cloned().skip_while(|&b| b == 0).collect();
+    // Positive integers must not have the sign bit set
+    if value.first().map(|&b| b & 0x80 != 0).unwrap_or(true) {
+        value.insert(0, 0);
+    }
+    let mut der = vec![0x02];
+    der.extend(der_length(value.len()));
+    der.extend(value);
+    der
+}
+
+/// DER encoded RSAPublicKey (PKCS#1) from modulus and exponent
+fn rsa_public_key_der(n: &[u8], e: &[u8]) -> Vec<u8> {
+    let mut content = der_integer(n);
+    content.extend(der_integer(e));
+    let mut der = vec![0x30];
+    der.extend(der_length(content.len()));
+    der.extend(content);
+    der
+}
+
+#[cfg(test)]
+fn test_cfg(claims: &[(&str, &str)]) -> WebserverJwtCfg {
+    WebserverJwtCfg {
+        secret: Some("secret".to_string()),
+        jwks_url: None,
+        claims: claims
+            .iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    }
+}
+
+#[test]
+fn test_hmac_token() {
+    use jsonwebtoken::{encode, Header};
+
+    let claims = json!({"sub": "tiles", "aud": ["t-rex", "other"], "exp": 4102444800u64});
+    let token = encode(&Header::default(), &claims, b"secret").unwrap();
+
+    let validator = JwtValidator::from_secret(&test_cfg(&[]), "secret");
+    assert_eq!(validator.validate(&token), Ok(()));
+    assert!(validator.validate("invalid").is_err());
+
+    let validator = JwtValidator::from_secret(&test_cfg(&[]), "other");
+    assert_eq!(validator.validate(&token), Err("Invalid token".to_string()));
+
+    let validator = JwtValidator::from_secret(&test_cfg(&[("aud", "t-rex")]), "secret");
+    assert_eq!(validator.validate(&token), Ok(()));
+    let validator = JwtValidator::from_secret(&test_cfg(&[("sub", "admin")]), "secret");
+    assert_eq!(
+        validator.validate(&token),
+        Err("Claim 'sub' not satisfied".to_string())
+    );
+
+    let expired = json!({"sub": "tiles", "exp": 1000000000});
+    let token = encode(&Header::default(), &expired, b"secret").unwrap();
+    let validator = JwtValidator::from_secret(&test_cfg(&[]), "secret");
+    assert!(validator.validate(&token).is_err());
+}
+
+#[test]
+fn test_rsa_public_key_der() {
+    assert_eq!(
+        rsa_public_key_der(&[0xc1, 0x02], &[0x01, 0x00, 0x01]),
+        vec![0x30, 0x0a, 0x02, 0x03, 0x00, 0xc1, 0x02, 0x02, 0x03, 0x01, 0x00, 0x01]
+    );
+    assert_eq!(der_length(0x7f), vec![0x7f]);
+    assert_eq!(der_length(0x101), vec![0x82, 0x01, 0x01]);
+
+    let jwks = json!({"keys": [
+        {"kty": "EC", "kid": "ec"},
+        {"kty": "RSA", "kid": "key1", "n": "wQI", "e": "AQAB"}
+    ]});
+    let validator = JwtValidator::from_jwks(&test_cfg(&[]), &jwks).unwrap();
+    assert_eq!(validator.keys.len(), 1);
+    assert_eq!(validator.keys[0].kid, Some("key1".to_string()));
+    assert!(JwtValidator::from_jwks(&test_cfg(&[]), &json!({"keys": []})).is_err());
+}
@@ -3,23 +3,34 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+//! Actix web server of the t-rex vector tile server.
+
 extern crate clap;
 #[macro_use]
 extern crate log;
 extern crate actix;
 extern crate actix_web;
+extern crate base64;
 extern crate futures;
+extern crate jsonwebtoken;
 extern crate open;
+extern crate rustls;
 #[macro_use]
 extern crate lazy_static;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
 
 extern crate t_rex_core;
 extern crate t_rex_service;
 
-use t_rex_core::{cache, core, datasource, service};
-use t_rex_service::{datasource_type, mvt_service, read_qgs};
+use t_rex_core::{cache, core, datasource, mvt, service};
+use t_rex_service::{args, datasource_type, mvt_service, read_qgs};
 
+pub mod access_log;
+mod jwt;
+mod ratelimit;
 pub mod server;
+mod systemd;
@@ -0,0 +1,214 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Request rate limiting per client (token bucket)
+
+use actix_web::http::{header, StatusCode};
+use actix_web::middleware::{Middleware, Started};
+use actix_web::{HttpRequest, HttpResponse, Result};
+use core::config::WebserverRateLimitCfg;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Number of clients triggering the removal of idle buckets
+const MAX_CLIENTS: usize = 10000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    by_api_key: bool,
+    api_keys: HashSet<String>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn from_config(cfg: &WebserverRateLimitCfg) -> Result<RateLimiter, String> {
+        if cfg.requests_per_second <= 0.0 {
+            return Err("requests_per_second must be positive".to_string());
+        }
+        let by_api_key = match cfg.client_key.as_ref().map(|k| k.as_str()) {
+            None | Some("ip") => false,
+            Some("api_key") => true,
+            Some(key) => return Err(format!("Invalid client_key '{}'", key)),
+        };
+        let api_keys: HashSet<String> = cfg.api_keys.iter().flat_map(|keys| keys.clone()).collect();
+        if by_api_key && api_keys.is_empty() {
+            return Err("client_key 'api_key' requires a list of api_keys".to_string());
+        }
+        Ok(RateLimiter {
+            rate: cfg.requests_per_second,
+            burst: cfg
+                .burst
+                .map(|burst| burst as f64)
+                .unwrap_or(cfg.requests_per_second)
+                .max(1.0),
+            by_api_key,
+            api_keys,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+    /// Take a token from the bucket of `client`.
+    /// Returns the number of seconds until the next request is allowed, if the limit is exceeded.
+    fn acquire(&self, client: &str, now: Instant) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(client) {
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.retain(|_, bucket| bucket.refilled(now, rate, burst) < burst);
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = bucket.refilled(now, self.rate, self.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / self.rate).ceil() as u64)
+        }
+    }
+    fn client_key<S>(&self, req: &HttpRequest<S>) -> String {
+        if self.by_api_key {
+            let api_key = req
+                .headers()
+                .get("X-API-Key")
+                .and_then(|val| val.to_str().ok())
+                .map(|val| val.to_string())
+                .or(req.query().get("api_key").cloned());
+            // Unknown keys would allow bypassing the limit by sending random keys
+            if let Some(api_key) = api_key.filter(|key| self.api_keys.contains(key)) {
+                return format!("key:{}", api_key);
+            }
+        }
+        req.peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl Bucket {
+    fn refilled(&self, now: Instant, rate: f64, burst: f64) -> f64 {
+        let elapsed = now.duration_since(self.updated);
+        let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+        (self.tokens + secs * rate).min(burst)
+    }
+}
+
+/// Middleware responding with 429 Too Many Requests when the client exceeds the rate limit
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+    /// Monitoring endpoints without rate limit
+    exempt_paths: Vec<String>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Arc<RateLimiter>, path_prefix: &str) -> RateLimit {
+        RateLimit {
+            limiter,
+            exempt_paths: vec![
+                format!("{}/health", path_prefix),
+                format!("{}/metrics", path_prefix),
+            ],
+        }
+    }
+}
+
+impl<S> Middleware<S> for RateLimit {
+    fn start(&self, req: &HttpRequest<S>) -> Result<Started> {
+        if self.exempt_paths.iter().any(|path| path == req.path()) {
+            return Ok(Started::Done);
+        }
+        let client = self.limiter.client_key(req);
+        match self.limiter.acquire(&client, Instant::now()) {
+            Ok(()) => Ok(Started::Done),
+            Err(retry_after) => {
+                debug!("Rate limit exceeded by {}", client);
+                Ok(Started::Response(
+                    HttpResponse::build(StatusCode::TOO_MANY_REQUESTS)
+                        .header(header::RETRY_AFTER, retry_after.to_string())
+                        .finish(),
+                ))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rate_limit() {
+    use std::time::Duration;
+
+    let cfg = WebserverRateLimitCfg {
+        requests_per_second: 2.0,
+        burst: Some(3),
+        client_key: None,
+        api_keys: None,
+    };
+    let limiter = RateLimiter::from_config(&cfg).unwrap();
+    let now = Instant::now();
+    for _ in 0..3 {
+        assert_eq!(limiter.acquire("127.0.0.1", now), Ok(()));
+    }
+    assert_eq!(limiter.acquire("127.0.0.1", now), Err(1));
+    // Other clients have their own bucket
+    assert_eq!(limiter.acquire("::1", now), Ok(()));
+    // One token every 0.5s
+    let later = now + Duration::from_millis(500);
+    assert_eq!(limiter.acquire("127.0.0.1", later), Ok(()));
+    assert_eq!(limiter.acquire("127.0.0.1", later), Err(1));
+    // Refill up to burst size
+    let later = now + Duration::from_secs(60);
+    for _ in 0..3 {
+        assert_eq!(limiter.acquire("127.0.0.1", later), Ok(()));
+    }
+    assert!(limiter.acquire("127.0.0.1", later).is_err());
+
+    let cfg = WebserverRateLimitCfg {
+        requests_per_second: 2.0,
+        burst: None,
+        client_key: Some("user".to_string()),
+        api_keys: None,
+    };
+    assert_eq!(
+        RateLimiter::from_config(&cfg).err(),
+        Some("Invalid client_key 'user'".to_string())
+    );
+
+    let cfg = WebserverRateLimitCfg {
+        requests_per_second: 2.0,
+        burst: None,
+        client_key: Some("api_key".to_string()),
+        api_keys: None,
+    };
+    assert_eq!(
+        RateLimiter::from_config(&cfg).err(),
+        Some("client_key 'api_key' requires a list of api_keys".to_string())
+    );
+}
+
+#[test]
+fn test_rate_limit_client_key() {
+    use actix_web::test::TestRequest;
+
+    let cfg = WebserverRateLimitCfg {
+        requests_per_second: 2.0,
+        burst: None,
+        client_key: Some("api_key".to_string()),
+        api_keys: Some(vec!["secret".to_string()]),
+    };
+    let limiter = RateLimiter::from_config(&cfg).unwrap();
+    let req = TestRequest::with_header("X-API-Key", "secret").finish();
+    assert_eq!(limiter.client_key(&req), "key:secret");
+    // Unknown keys are limited per IP
+    let req = TestRequest::with_uri("/tiles/0/0/0.pbf?api_key=random").finish();
+    let anonymous = TestRequest::with_uri("/tiles/0/0/0.pbf").finish();
+    assert_eq!(limiter.client_key(&req), limiter.client_key(&anonymous));
+}
@@ -0,0 +1,102 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! systemd integration
+//!
+//! Readiness notification for services with `Type=notify` and
+//! listening sockets passed by socket activation (`ListenStream=`).
+
+#[cfg(unix)]
+use std::env;
+use std::io;
+use std::net::TcpListener;
+
+/// First file descriptor passed by systemd (SD_LISTEN_FDS_START)
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Send state to service manager. Returns false if not started by systemd.
+#[cfg(unix)]
+pub fn notify(state: &str) -> io::Result<bool> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket_path = match env::var("NOTIFY_SOCKET") {
+        Ok(path) => path,
+        Err(_) => return Ok(false),
+    };
+    if socket_path.starts_with('@') {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Abstract notification sockets are not supported",
+        ));
+    }
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to(state.as_bytes(), socket_path)?;
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Signal readiness to service manager
+pub fn notify_ready() {
+    match notify("READY=1") {
+        Ok(true) => debug!("Readiness signaled to systemd"),
+        Ok(false) => {}
+        Err(e) => warn!("systemd notification failed: {}", e),
+    }
+}
+
+/// Number of sockets passed to this process
+#[cfg(unix)]
+fn listen_fds_count(listen_pid: Option<String>, listen_fds: Option<String>, pid: u32) -> usize {
+    match (listen_pid, listen_fds) {
+        (Some(listen_pid), Some(listen_fds)) => {
+            if listen_pid.parse::<u32>().ok() != Some(pid) {
+                return 0;
+            }
+            listen_fds.parse::<usize>().unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+/// Listening sockets passed by systemd socket activation
+#[cfg(unix)]
+pub fn listen_fds() -> Vec<TcpListener> {
+    use std::os::unix::io::FromRawFd;
+    use std::process;
+
+    let count = listen_fds_count(
+        env::var("LISTEN_PID").ok(),
+        env::var("LISTEN_FDS").ok(),
+        process::id(),
+    );
+    // Don't pass sockets to child processes
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+    (0..count as i32)
+        .map(|i| unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START + i) })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn listen_fds() -> Vec<TcpListener> {
+    Vec::new()
+}
+
+#[test]
+#[cfg(unix)]
+fn test_listen_fds_count() {
+    let some = |s: &str| Some(s.to_string());
+    assert_eq!(listen_fds_count(some("42"), some("2"), 42), 2);
+    assert_eq!(listen_fds_count(some("41"), some("2"), 42), 0);
+    assert_eq!(listen_fds_count(None, some("2"), 42), 0);
+    assert_eq!(listen_fds_count(some("42"), None, 42), 0);
+    assert_eq!(listen_fds_count(some("42"), some("x"), 42), 0);
+}